@@ -1,5 +1,19 @@
 pub mod ramp;
-use crate::ramp::install::install;
+use crate::ramp::install::{has_signing_identity, install, setup_keychain, signing_identities};
+use crate::ramp::macos::{app_bundle_path, package_macos};
+use crate::ramp::notarize::{notarize_build, NOTARY_PROFILE_KEY};
+use crate::ramp::session::{Session, SigningIdentity};
+use crate::ramp::core::{build_output, get_bundle_id, get_udid_by_target, load_project, sign_build};
+use crate::ramp::emulator::deploy_to_emulator;
+use crate::ramp::adb::{add_wireless_device, adb_path, forget_wireless_device, pair_device, project_package, run_app_action, selected_device, AppAction};
+use crate::ramp::manifest::{bump_version, current_project_manifest, edit_manifest, VersionPart};
+use crate::ramp::logcat::{pump_logs, shared_log_buffer, stream_app_logs, LogLevel};
+use crate::ramp::web::serve_wasm;
+use crate::ramp::bundle::{OptLevel, WASM_OPT_LEVEL_KEY};
+use crate::ramp::profiles::ProfileStore;
+use crate::ramp::wizard::{drive_in_terminal, Wizard, WizardKind};
+use crate::ramp::watcher::{DOWNLOADS_PATH_KEY, DOWNLOAD_TIMEOUT_KEY};
+use crate::ramp::appstore::{ApiKey, AppStoreClient, API_ISSUER_ID_KEY, API_KEY_ID_KEY, API_KEY_PATH_KEY, API_URL_KEY};
 
 use std::io;
 use std::env;
@@ -18,6 +32,239 @@ fn main() -> io::Result<()> {
         //TODO move the binary from the .dmg or the .deb after install is finished
         //TODO terminate the session
         //TODO can we start an external script with a timer here to relaunch ramp gui after closing initial install client?
+    }else if let Some(index) = args.iter().position(|a| a == "-serve") {
+        //build the named project for wasm and serve it locally with auto-rebuild
+        let project = args.get(index + 1).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "usage: -serve <project> [port]"))?;
+        let port = match args.get(index + 2) {
+            Some(port) => port.parse::<u16>().map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid port: {}", port)))?,
+            None => 8080,
+        };
+        let mut session = Session::new()?;
+        session.get_all_paths()?;
+        load_project(&mut session, project)?;
+        serve_wasm(&mut session, port)?;
+    }else if let Some(index) = args.iter().position(|a| a == "-wasm-opt") {
+        //the wasm-opt level of release web builds
+        let usage = "usage: -wasm-opt <none|O1|O2|O3|O4|Os|Oz>";
+        let level = args.get(index + 1).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+        if level != "none" && OptLevel::parse(level).is_none() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, usage));
+        }
+        Session::new()?.set_config(WASM_OPT_LEVEL_KEY, level.trim_start_matches('-'))?;
+    }else if let Some(index) = args.iter().position(|a| a == "-emulator") {
+        //build the named project for android and run it on an emulator, creating the default avd if needed
+        let project = args.get(index + 1).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "usage: -emulator <project> [avd]"))?;
+        let mut session = Session::new()?;
+        session.get_all_paths()?;
+        load_project(&mut session, project)?;
+        build_output(&mut session, "android".to_string(), false)?;
+        let instance = deploy_to_emulator(&mut session, args.get(index + 2).map(|a| a.as_str()))?;
+        println!("running on {}, stop it with `adb -s {} emu kill`", instance.avd, instance.serial);
+    }else if let Some(index) = args.iter().position(|a| a == "-logcat") {
+        //stream the running app's logs from the selected device, optionally filtered by minimum level and search text
+        let usage = "usage: -logcat <project> [V|D|I|W|E|F] [search]";
+        let project = args.get(index + 1).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+        let mut session = Session::new()?;
+        session.get_all_paths()?;
+        load_project(&mut session, project)?;
+        {
+            let buffer = shared_log_buffer();
+            let mut buffer = buffer.lock().unwrap();
+            if let Some(level) = args.get(index + 2) {
+                buffer.filter.min_level = level.chars().next().and_then(LogLevel::from_char)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+            }
+            buffer.filter.search = args.get(index + 3).cloned();
+        }
+        let package = project_package(&session)?;
+        let device = selected_device(&mut session)?;
+        let (_stream, receiver) = stream_app_logs(&mut session, &device, &package)?;
+        pump_logs(&mut session, receiver, true)?;
+    }else if let Some(index) = args.iter().position(|a| a == "-android-action") {
+        //manage the project's app on the selected android device
+        let usage = "usage: -android-action <uninstall|clear|stop|pull|grant|revoke|reinstall> <project> [permission]";
+        let action = args.get(index + 1).and_then(|a| AppAction::parse(a)).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+        let project = args.get(index + 2).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+        let mut session = Session::new()?;
+        session.get_all_paths()?;
+        load_project(&mut session, project)?;
+        if action == AppAction::CleanReinstall {
+            build_output(&mut session, "android".to_string(), false)?;
+        }
+        run_app_action(&mut session, action, args.get(index + 3).map(|a| a.as_str()))?;
+    }else if let Some(index) = args.iter().position(|a| a == "-pair") {
+        //pair an android 11+ device over wireless debugging and connect to it
+        let usage = "usage: -pair <pairing host:port> <code> [connect host:port]";
+        let pair_address = args.get(index + 1).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+        let code = args.get(index + 2).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+        let mut session = Session::new()?;
+        session.get_all_paths()?;
+        pair_device(&adb_path(&mut session)?, pair_address, code)?;
+        println!("paired with {}", pair_address);
+        match args.get(index + 3) {
+            Some(address) => {
+                add_wireless_device(&mut session, address)?;
+                println!("connected to {}, it will be reconnected automatically before deploying", address);
+            }
+            None => println!("connect with -connect <host:port> using the address shown under Wireless debugging"),
+        }
+    }else if let Some(index) = args.iter().position(|a| a == "-connect" || a == "-forget") {
+        //connect to (and remember) or forget a paired wireless device
+        let address = args.get(index + 1).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "usage: -connect|-forget <host:port>"))?;
+        let mut session = Session::new()?;
+        session.get_all_paths()?;
+        if args[index] == "-connect" {
+            add_wireless_device(&mut session, address)?;
+            println!("connected to {}", address);
+        } else {
+            forget_wireless_device(&mut session, address)?;
+            println!("forgot {}", address);
+        }
+    }else if let Some(index) = args.iter().position(|a| a == "-bump") {
+        //bump the project's version in Cargo.toml, keeping its formatting and comments
+        let usage = "usage: -bump <project> <major|minor|patch>";
+        let project = args.get(index + 1).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+        let part = args.get(index + 2).and_then(|p| VersionPart::parse(p)).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+        let mut session = Session::new()?;
+        session.get_all_paths()?;
+        load_project(&mut session, project)?;
+        let current = current_project_manifest(&session)?.version;
+        let next = bump_version(&current, part)?;
+        edit_manifest(&format!("{}/{}", session.projects_path.as_ref().unwrap(), project), |manifest| manifest.set_version(&next))?;
+        println!("{} {} -> {}", project, current, next);
+    }else if let Some(index) = args.iter().position(|a| a == "-profiles") {
+        //manage the project's ios provisioning profiles
+        let usage = "usage: -profiles <project> [list|import <file>|select <uuid>|delete <uuid>]";
+        let project = args.get(index + 1).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+        let mut session = Session::new()?;
+        session.get_all_paths()?;
+        load_project(&mut session, project)?;
+        let store = ProfileStore::open(&session)?;
+        let argument = || args.get(index + 3).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage));
+        match args.get(index + 2).map(|a| a.as_str()).unwrap_or("list") {
+            "list" => {
+                let active = store.active(&session)?.map(|p| p.profile.uuid);
+                for stored in store.list()? {
+                    let marker = if active.as_ref() == Some(&stored.profile.uuid) { "*" } else { " " };
+                    println!("{} {} {}", marker, stored.profile.uuid, stored.summary());
+                }
+            }
+            "import" => println!("{}", store.import(std::path::Path::new(argument()?))?.summary()),
+            "select" => println!("active profile: {}", store.select(&session, argument()?)?.summary()),
+            "delete" => store.delete(&session, argument()?)?,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, usage)),
+        }
+    }else if let Some(index) = args.iter().position(|a| a == "-wizard") {
+        //resume or start a developer portal wizard in the terminal
+        let usage = "usage: -wizard <keychain|bundle_id|provision> [project]";
+        let kind = args.get(index + 1).and_then(|k| WizardKind::parse(k)).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+        let mut session = Session::new()?;
+        session.get_all_paths()?;
+        if let Some(project) = args.get(index + 2) {
+            load_project(&mut session, project)?;
+        }
+        let wizard = match Wizard::load(&session, kind)? {
+            Some(wizard) => wizard,
+            None if kind == WizardKind::Keychain => Wizard::new(kind),
+            None => {
+                let project = session.current_project.clone().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+                let wizard = Wizard::new(kind).with_value("project", &project);
+                match kind {
+                    WizardKind::Provision => wizard
+                        .with_value("udid", &get_udid_by_target("iphone")?)
+                        .with_value("target_os", "ios")
+                        .with_value("release", "false")
+                        .with_value("bundle_id", &get_bundle_id(&mut session, "ios")?),
+                    _ => wizard,
+                }
+            }
+        };
+        if let Some(project) = wizard.value("project") {
+            session.update_current_project(project)?;
+        }
+        let mut wizard = wizard.with_api_if_configured(&session)?;
+        drive_in_terminal(&mut session, &mut wizard)?;
+    }else if let Some(index) = args.iter().position(|a| a == "-downloads") {
+        //where portal downloads are watched for and how long to wait on them
+        let usage = "usage: -downloads <dir> [timeout seconds]";
+        let dir = args.get(index + 1).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+        let session = Session::new()?;
+        session.set_config(DOWNLOADS_PATH_KEY, dir)?;
+        if let Some(timeout) = args.get(index + 2) {
+            timeout.parse::<u64>().map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+            session.set_config(DOWNLOAD_TIMEOUT_KEY, timeout)?;
+        }
+    }else if let Some(index) = args.iter().position(|a| a == "-appstore-key") {
+        //use an App Store Connect API key instead of the developer portal, the url is for testing against a mock server
+        let usage = "usage: -appstore-key <key id> <issuer id> <AuthKey.p8> [api url]";
+        let argument = |offset: usize| args.get(index + offset).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage));
+        let (key_id, issuer_id) = (argument(1)?, argument(2)?);
+        let key_path = std::fs::canonicalize(argument(3)?)?.to_string_lossy().to_string();
+        ApiKey::load(key_id, issuer_id, std::path::Path::new(&key_path))?;
+        let session = Session::new()?;
+        session.set_config(API_KEY_ID_KEY, key_id)?;
+        session.set_config(API_ISSUER_ID_KEY, issuer_id)?;
+        session.set_config(API_KEY_PATH_KEY, &key_path)?;
+        session.set_config(API_URL_KEY, args.get(index + 4).map(|u| u.as_str()).unwrap_or(""))?;
+        if let Some(client) = AppStoreClient::from_config(&session)? {
+            println!("App Store Connect API key {} saved, using {}", key_id, client.base_url);
+        }
+    }else if let Some(index) = args.iter().position(|a| a == "-certs") {
+        //the signing identity of each apple platform and build type
+        let usage = "usage: -certs [list | set <ios|macos> <debug|release> [identity] | setup <development|distribution|developer_id>]";
+        let mut session = Session::new()?;
+        session.get_all_paths()?;
+        match args.get(index + 1).map(|a| a.as_str()).unwrap_or("list") {
+            "list" => {
+                let installed = signing_identities().unwrap_or_default();
+                for (target_os, release) in [("ios", false), ("ios", true), ("macos", false), ("macos", true)] {
+                    let identity = session.certs.identity(target_os, release)?;
+                    let found = installed.iter().find(|i| i.contains(identity)).map(|i| i.as_str()).unwrap_or("not in keychain");
+                    println!("{} {}: {} ({})", target_os, if release { "release" } else { "debug" }, identity, found);
+                }
+            }
+            "set" => {
+                let target_os = args.get(index + 2).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+                let release = match args.get(index + 3).map(|a| a.as_str()) {
+                    Some("debug") => false,
+                    Some("release") => true,
+                    _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, usage)),
+                };
+                session.set_cert(target_os, release, args.get(index + 4).map(|a| a.as_str()).unwrap_or(""))?;
+                println!("{} builds now sign with {}", target_os, session.certs.identity(target_os, release)?);
+            }
+            "setup" => {
+                let identity = args.get(index + 2)
+                    .and_then(|a| SigningIdentity::parse(a))
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, usage))?;
+                setup_keychain(&mut session, identity)?;
+            }
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, usage)),
+        }
+    }else if let Some(index) = args.iter().position(|a| a == "-notary-profile") {
+        //notarize with credentials saved by `xcrun notarytool store-credentials <profile>` instead of the App Store Connect API key
+        let profile = args.get(index + 1).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "usage: -notary-profile <keychain profile>"))?;
+        Session::new()?.set_config(NOTARY_PROFILE_KEY, profile)?;
+    }else if let Some(index) = args.iter().position(|a| a == "-notarize") {
+        //sign the built macos app for release, notarize it, staple the ticket and repackage it with the ticket
+        let project = args.get(index + 1).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "usage: -notarize <project>"))?;
+        let mut session = Session::new()?;
+        session.get_all_paths()?;
+        load_project(&mut session, project)?;
+        let bundle_path = app_bundle_path(&session);
+        if !std::path::Path::new(&bundle_path).exists() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("app bundle not found at {}, build for macos first", bundle_path)));
+        }
+        if !has_signing_identity(&session.certs.macos_release)? {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no {} identity in the keychain, create one with -certs setup developer_id", session.certs.macos_release),
+            ));
+        }
+        sign_build(&mut session, "macos", true)?;
+        notarize_build(&session, std::path::Path::new(&bundle_path))?;
+        let artifacts = package_macos(&mut session)?;
+        println!("distribution packages: {}", artifacts.join(", "));
     }else{
         #[cfg(not(target_arch="wasm32"))]
         { 
@@ -1,9 +1,9 @@
 use pelican_ui::drawable::{Drawable, Color, Align};
 use pelican_ui::{include_dir, drawables, Component, Context, Application, Plugin};
-use pelican_ui::layouts::{Offset, Stack};
+use pelican_ui::layouts::{Column, Offset, Padding, Size, Stack};
 use pelican_ui::events::{OnEvent, Event, TickEvent};
 use pelican_ui::components::button::PrimaryButton;
-use pelican_ui::components::{ExpandableText, Icon, Text, TextStyle, TextSize, TextInput};
+use pelican_ui::components::{ExpandableText, Icon, Text, TextStyle, TextSize, TextInput, Toggle, RadioSelector};
 use pelican_ui::components::interface::general::{Bumper, Content, Header, Interface, Page};
 use pelican_ui::plugin::PelicanUI;
 use pelican_ui::components::interface::navigation::{AppPage, RootInfo, NavigationEvent};
@@ -11,8 +11,16 @@ use pelican_ui::interactions::Button;
 use crate::pages::start::StartScreen;
 use crate::ramp::session::{Session};
 use crate::ramp::core::{new_project};
+use crate::ramp::aab::{android_abi, ANDROID_TARGETS};
+use crate::ramp::apk::{AndroidBuildOptions, ApkLayout};
+use crate::ramp::adb::{adb_path, connected_devices, list_devices, AndroidDevice, SELECTED_DEVICE_KEY};
+use crate::ramp::logcat::{shared_log_buffer, LogLevel};
+use pelican_ui::utils::Callback;
 
 use serde::{Serialize, Deserialize};
+use std::io;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
 
 #[derive(Debug, Component)]
 pub struct AndroidScreen(Stack, Page);
@@ -52,7 +60,7 @@ impl AndroidScreen {
         let explainer = ExpandableText::new(
             ctx,
             //content
-            "Ramp supports streaming install via usb tether. Please connect an Android device with developer debugging enabled.",
+            "Ramp supports streaming install via usb tether or wireless debugging. Please connect an Android device with developer debugging enabled, or pair an Android 11+ device over wifi with ramp_gui -pair <host:port> <code> <connect host:port>. Release builds are signed with an upload key kept in ~/.ramp_keystores, its password is stored in your system keychain. App bundles (.aab) for the Play Store include every Android ABI and are written to packages/android. No device? Run ramp_gui -emulator <project> to boot a headless emulator and deploy to it, or ramp_gui -logcat <project> to follow the running app's logs in a terminal. Uninstall, clear data, force-stop, pull app data, grant or revoke permissions and clean reinstall with ramp_gui -android-action <action> <project>.",
             //Size
             TextSize::H3,
             //style
@@ -62,25 +70,57 @@ impl AndroidScreen {
             None
         );
 
-        let tether = ExpandableText::new(
-            ctx,
-            //content
-            "Device Connection Status: Not Ready",
-            //Size
-            TextSize::H4,
-            //style
-            TextStyle::Secondary,
-            //alignment
-            Align::Center,
-            None
-        );
+        //devices adb already knows, saved wireless devices are reconnected in the background
+        let devices = Session::new().ok().and_then(|mut session| {
+            session.get_all_paths().ok()?;
+            list_devices(&adb_path(&mut session).ok()?).ok()
+        }).unwrap_or_default();
+        let selected = Session::new().ok().and_then(|session| session.get_config(SELECTED_DEVICE_KEY).ok().flatten());
+        let ready: Vec<AndroidDevice> = devices.iter().filter(|d| d.is_ready()).cloned().collect();
+        let tether = DeviceStatus::new(ctx, &devices);
+
+        //abi selection and apk layout, saved to the ramp config for the next android build
+        let options = Session::new().ok().and_then(|session| AndroidBuildOptions::load(&session).ok()).unwrap_or_default();
+        let mut items: Vec<Box<dyn Drawable>> = vec![Box::new(text), Box::new(explainer), Box::new(tether)];
+        if !ready.is_empty() {
+            let names: Vec<String> = ready.iter().map(|d| d.display_name()).collect();
+            let index = ready.iter().position(|d| Some(&d.serial) == selected.as_ref()).unwrap_or(0);
+            let choices: Vec<(&str, &str, Callback)> = ready.iter().zip(names.iter()).map(|(device, name)| {
+                let serial = device.serial.clone();
+                let callback: Callback = Box::new(move |_ctx: &mut Context| {
+                    if let Err(e) = Session::new().and_then(|session| session.set_config(SELECTED_DEVICE_KEY, &serial)) {
+                        println!("failed to save the selected android device: {}", e);
+                    }
+                });
+                let kind = if device.is_emulator() { "Emulator" } else if device.is_wireless() { "Wireless device" } else { "USB device" };
+                (name.as_str(), kind, callback)
+            }).collect();
+            items.push(Box::new(RadioSelector::new(ctx, index, choices)));
+        }
+        for target in ANDROID_TARGETS {
+            let label = format!("Build {}", android_abi(target).unwrap_or(target));
+            let selected = options.targets.iter().any(|t| t == target);
+            items.push(Box::new(Toggle::new(ctx, &label, selected, move |_ctx: &mut Context, enabled: bool| {
+                update_android_options(|options| options.set_target(target, enabled));
+            })));
+        }
+        let layout_index = if options.layout == ApkLayout::Split { 1 } else { 0 };
+        items.push(Box::new(RadioSelector::new(ctx, layout_index, vec![
+            ("Fat APK", "One release APK containing every selected ABI", Box::new(|_ctx: &mut Context| {
+                update_android_options(|options| options.layout = ApkLayout::Fat);
+            })),
+            ("Split APKs", "One release APK per ABI, each with its own version code", Box::new(|_ctx: &mut Context| {
+                update_android_options(|options| options.layout = ApkLayout::Split);
+            })),
+        ])));
+        items.push(Box::new(LogPanel::new(ctx)));
 
         let content = Content::new(
             ctx,
             // Vertically center items
             Offset::Center,
             // All items must be boxed as Box<dyn Drawable>
-            vec![Box::new(text), Box::new(explainer), Box::new(tether)]
+            items
         );
 
         let bumper = Bumper::home(
@@ -96,4 +136,136 @@ impl AndroidScreen {
 
         Ok(Self(Stack::default(), Page::new(header, content, Some(bumper))))
     }
-}
\ No newline at end of file
+}
+
+//load, change and save the android build options from a page callback
+fn update_android_options(change: impl FnOnce(&mut AndroidBuildOptions)) {
+    let result = Session::new().and_then(|session| {
+        let mut options = AndroidBuildOptions::load(&session)?;
+        change(&mut options);
+        options.save(&session)
+    });
+    if let Err(e) = result {
+        println!("failed to save android build options: {}", e);
+    }
+}
+
+//the connection status line, ready devices and guidance for the ones that aren't
+fn device_status(devices: &[AndroidDevice], pickable: &[String]) -> String {
+    let ready: Vec<&AndroidDevice> = devices.iter().filter(|d| d.is_ready()).collect();
+    let mut status = match ready.len() {
+        0 => "Device Connection Status: Not Ready".to_string(),
+        1 => "Device Connection Status: 1 device ready".to_string(),
+        n => format!("Device Connection Status: {} devices ready, pick one below", n),
+    };
+    for device in devices.iter().filter(|d| !d.is_ready()) {
+        status.push_str(&format!("\n{} is {}: {}", device.display_name(), device.state.as_str(), device.state.guidance().unwrap_or("")));
+    }
+    for device in ready.iter().filter(|d| !pickable.contains(&d.serial)) {
+        status.push_str(&format!("\n{} reconnected, reopen this page to pick it", device.display_name()));
+    }
+    status
+}
+
+//device connection status, updated once the saved wireless devices have been reconnected
+#[derive(Debug, Component)]
+pub struct DeviceStatus(
+    Column,
+    ExpandableText,
+    #[skip] Vec<String>,
+    #[skip] Option<Receiver<io::Result<Vec<AndroidDevice>>>>,
+);
+
+impl DeviceStatus {
+    pub fn new(ctx: &mut Context, devices: &[AndroidDevice]) -> Self {
+        let pickable: Vec<String> = devices.iter().filter(|d| d.is_ready()).map(|d| d.serial.clone()).collect();
+        let text = ExpandableText::new(
+            ctx,
+            //content
+            &device_status(devices, &pickable),
+            //Size
+            TextSize::H4,
+            //style
+            TextStyle::Secondary,
+            //alignment
+            Align::Center,
+            None
+        );
+        //adb connect blocks on every unreachable address, so it never runs on the ui thread
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(Session::new().and_then(|mut session| {
+                session.get_all_paths()?;
+                connected_devices(&mut session)
+            }));
+        });
+        DeviceStatus(Column::new(0.0, Offset::Center, Size::Fill, Padding::default()), text, pickable, Some(receiver))
+    }
+}
+
+impl OnEvent for DeviceStatus {
+    fn on_event(&mut self, _ctx: &mut Context, event: Box<dyn Event>) -> Vec<Box<dyn Event>> {
+        if event.downcast_ref::<TickEvent>().is_some() {
+            let finished = self.3.as_ref().and_then(|receiver| receiver.try_recv().ok());
+            if let Some(result) = finished {
+                self.3 = None;
+                match result {
+                    Ok(devices) => self.1.0.spans[0] = device_status(&devices, &self.2),
+                    Err(e) => println!("failed to reconnect wireless android devices: {}", e),
+                }
+            }
+        }
+        vec![event]
+    }
+}
+
+//lines of the running app's log shown at once
+const LOG_PANEL_LINES: usize = 40;
+
+//live logcat output of the app deployed from ramp, filtered by level and search text
+#[derive(Debug, Component)]
+pub struct LogPanel(Column, TextInput, RadioSelector, ExpandableText);
+
+impl LogPanel {
+    pub fn new(ctx: &mut Context) -> Self {
+        let search = TextInput::new(
+            ctx,
+            None,
+            Some("App Logs"),
+            Some("Search tag or message..."),
+            Some("Logs stream here after deploying to a device, native crashes are symbolicated against the unstripped libraries in target/"),
+            None
+        );
+        let levels = [
+            ("Verbose", "Every log line", LogLevel::Verbose),
+            ("Debug", "Debug and above", LogLevel::Debug),
+            ("Info", "Info and above", LogLevel::Info),
+            ("Warn", "Warnings and errors", LogLevel::Warn),
+            ("Error", "Errors and crashes only", LogLevel::Error),
+        ];
+        let choices: Vec<(&str, &str, Callback)> = levels.iter().map(|(name, description, level)| {
+            let level = *level;
+            let callback: Callback = Box::new(move |_ctx: &mut Context| {
+                shared_log_buffer().lock().unwrap().filter.min_level = level;
+            });
+            (*name, *description, callback)
+        }).collect();
+        let current = shared_log_buffer().lock().unwrap().filter.min_level;
+        let index = levels.iter().position(|(_, _, level)| *level == current).unwrap_or(0);
+        let level_selector = RadioSelector::new(ctx, index, choices);
+        let log = ExpandableText::new(ctx, "", TextSize::Sm, TextStyle::Secondary, Align::Left, None);
+        LogPanel(Column::new(16.0, Offset::Start, Size::Fill, Padding::default()), search, level_selector, log)
+    }
+}
+
+impl OnEvent for LogPanel {
+    fn on_event(&mut self, _ctx: &mut Context, event: Box<dyn Event>) -> Vec<Box<dyn Event>> {
+        if event.downcast_ref::<TickEvent>().is_some() {
+            let buffer = shared_log_buffer();
+            let mut buffer = buffer.lock().unwrap();
+            buffer.filter.search = Some(self.1.value());
+            self.3.0.spans[0] = buffer.render(LOG_PANEL_LINES);
+        }
+        vec![event]
+    }
+}
@@ -1,6 +1,6 @@
 use pelican_ui::drawable::{Drawable, Color, Align};
 use pelican_ui::{include_dir, drawables, Component, Context, Application, Plugin};
-use pelican_ui::layouts::{Offset, Stack};
+use pelican_ui::layouts::{Column, Offset, Padding, Size, Stack};
 use pelican_ui::events::{OnEvent, Event, TickEvent};
 use pelican_ui::components::button::PrimaryButton;
 use pelican_ui::components::{ExpandableText, Icon, Text, TextStyle, TextSize, TextInput};
@@ -9,10 +9,18 @@ use pelican_ui::plugin::PelicanUI;
 use pelican_ui::components::interface::navigation::{AppPage, RootInfo, NavigationEvent};
 use pelican_ui::interactions::Button;
 use crate::pages::start::StartScreen;
+use crate::pages::wizard::WizardScreen;
+use crate::ramp::wizard::{Wizard, WizardKind};
 use crate::ramp::session::{Session};
-use crate::ramp::core::{new_project};
+use crate::ramp::core::{new_project, get_udid_by_target, LAST_PROJECT_KEY};
+use crate::ramp::profiles::{profile_issues, ProfileStore, StoredProfile};
+use pelican_ui::components::RadioSelector;
+use pelican_ui::utils::Callback;
+use chrono::Utc;
 
 use serde::{Serialize, Deserialize};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
 
 #[derive(Debug, Component)]
 pub struct IOSScreen(Stack, Page);
@@ -61,7 +69,7 @@ impl IOSScreen {
         let explainer = ExpandableText::new(
             ctx,
             //content
-            "Ramp supports streaming install via usb tether. Please connect an IOS device with developer debugging enabled. You will be required to provision the device.",
+            "Ramp supports streaming install via usb tether. Please connect an IOS device with developer debugging enabled. You will be required to provision the device, the Apple developer portal steps continue here or in a terminal with ramp_gui -wizard <keychain|bundle_id|provision> <project>.",
             //Size
             TextSize::H3,
             //style
@@ -84,19 +92,56 @@ impl IOSScreen {
             None
         );
 
-        let provision = ExpandableText::new(
-            ctx,
-            //content
-            "Device Provision Status: Not Ready",
-            //Size
-            TextSize::H4,
-            //style
-            TextStyle::Secondary,
-            //alignment
-            Align::Center,
-            None
-        );
+        //provisioning profiles of the last loaded project, flagged when they expire soon or miss the connected device
+        let store = Session::new().ok().and_then(|mut session| {
+            let project = session.get_config(LAST_PROJECT_KEY).ok().flatten()?;
+            session.update_current_project(&project).ok()?;
+            let store = ProfileStore::open(&session).ok()?;
+            let profiles = store.list().ok()?;
+            let active = store.active(&session).ok().flatten();
+            Some((store, profiles, active))
+        });
+        let shown = store.as_ref().map(|(_, profiles, active)| active.as_ref().or(profiles.first()).cloned());
+        let provision = ProvisionStatus::new(ctx, shown);
+
+
+        let mut items: Vec<Box<dyn Drawable>> = vec![Box::new(text), Box::new(bundle_input), Box::new(explainer), Box::new(tether), Box::new(provision)];
+        if let Some((store, profiles, active)) = store.filter(|(_, profiles, _)| profiles.len() > 1) {
+            let index = profiles.iter().position(|p| Some(&p.profile.uuid) == active.as_ref().map(|a| &a.profile.uuid)).unwrap_or(0);
+            let descriptions: Vec<String> = profiles.iter().map(profile_description).collect();
+            let choices: Vec<(&str, &str, Callback)> = profiles.iter().zip(descriptions.iter()).map(|(profile, description)| {
+                let uuid = profile.profile.uuid.clone();
+                let store = store.clone();
+                let callback: Callback = Box::new(move |_ctx: &mut Context| {
+                    if let Err(e) = Session::new().and_then(|session| store.select(&session, &uuid)) {
+                        println!("failed to select the provisioning profile: {}", e);
+                    }
+                });
+                (profile.profile.name.as_str(), description.as_str(), callback)
+            }).collect();
+            items.push(Box::new(RadioSelector::new(ctx, index, choices)));
+        }
 
+        //wizards started from a build or deploy that are waiting on the developer portal
+        let pending: Vec<WizardKind> = Session::new().map(|session| {
+            [WizardKind::Keychain, WizardKind::BundleId, WizardKind::Provision].into_iter()
+                .filter(|kind| Wizard::load(&session, *kind).ok().flatten().is_some())
+                .collect()
+        }).unwrap_or_default();
+        for kind in &pending {
+            let kind = *kind;
+            items.push(Box::new(PrimaryButton::new(ctx, &format!("Continue {}", kind.title()), move |ctx: &mut Context| {
+                let page = Box::new(WizardScreen::new(ctx, kind).unwrap());
+                ctx.trigger_event(NavigationEvent::Push(Some(page)))
+            }, false)));
+        }
+        //the keychain wizard needs nothing from a build, so it can be started here at any time
+        if !pending.contains(&WizardKind::Keychain) {
+            items.push(Box::new(PrimaryButton::new(ctx, "Set Up Signing Certificate", |ctx: &mut Context| {
+                let page = Box::new(WizardScreen::new(ctx, WizardKind::Keychain).unwrap());
+                ctx.trigger_event(NavigationEvent::Push(Some(page)))
+            }, false)));
+        }
 
         // Combine icon, heading, and subtext into page content
         let content = Content::new(
@@ -104,7 +149,7 @@ impl IOSScreen {
             // Vertically center items
             Offset::Center,
             // All items must be boxed as Box<dyn Drawable>
-            vec![Box::new(text), Box::new(bundle_input), Box::new(explainer), Box::new(tether), Box::new(provision)]
+            items
         );
 
         let bumper = Bumper::home(
@@ -122,4 +167,75 @@ impl IOSScreen {
         // Return a default Stack
         Ok(Self(Stack::default(), Page::new(header, content, Some(bumper))))
     }
-}
\ No newline at end of file
+}
+//the shown profile's summary and issues, None when no project is loaded
+fn provision_status(shown: Option<&Option<StoredProfile>>, udid: Option<&str>) -> String {
+    match shown {
+        Some(Some(profile)) => {
+            let mut status = format!("Provisioning Profile: {}", profile.summary());
+            for issue in profile_issues(profile, udid, Utc::now()) {
+                status.push_str(&format!("\nWARNING: {}", issue));
+            }
+            status
+        }
+        Some(None) => "Device Provision Status: Not Ready\nNo provisioning profiles imported, add one with ramp_gui -profiles <project> import <file>".to_string(),
+        None => "Device Provision Status: Not Ready".to_string(),
+    }
+}
+
+//provisioning status of the active profile, the device check is added once the connected iphone has been looked up
+#[derive(Debug, Component)]
+pub struct ProvisionStatus(
+    Column,
+    ExpandableText,
+    #[skip] Option<Option<StoredProfile>>,
+    #[skip] Option<Receiver<Option<String>>>,
+);
+
+impl ProvisionStatus {
+    pub fn new(ctx: &mut Context, shown: Option<Option<StoredProfile>>) -> Self {
+        let text = ExpandableText::new(
+            ctx,
+            //content
+            &provision_status(shown.as_ref(), None),
+            //Size
+            TextSize::H4,
+            //style
+            TextStyle::Secondary,
+            //alignment
+            Align::Center,
+            None
+        );
+        //xctrace takes seconds to list devices, so it never runs on the ui thread
+        let receiver = matches!(shown, Some(Some(_))).then(|| {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                let _ = sender.send(get_udid_by_target("iphone").ok());
+            });
+            receiver
+        });
+        ProvisionStatus(Column::new(0.0, Offset::Center, Size::Fill, Padding::default()), text, shown, receiver)
+    }
+}
+
+impl OnEvent for ProvisionStatus {
+    fn on_event(&mut self, _ctx: &mut Context, event: Box<dyn Event>) -> Vec<Box<dyn Event>> {
+        if event.downcast_ref::<TickEvent>().is_some() {
+            let udid = self.3.as_ref().and_then(|receiver| receiver.try_recv().ok());
+            if let Some(udid) = udid {
+                self.3 = None;
+                self.1.0.spans[0] = provision_status(self.2.as_ref(), udid.as_deref());
+            }
+        }
+        vec![event]
+    }
+}
+
+fn profile_description(stored: &StoredProfile) -> String {
+    format!(
+        "{}, {} devices, expires {}",
+        stored.kind().as_str(),
+        stored.profile.provisioned_devices.len(),
+        stored.expires().map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_else(|| "never".to_string())
+    )
+}
@@ -58,13 +58,26 @@ impl MacOSScreen {
             None
         );
 
+        let explainer = ExpandableText::new(
+            ctx,
+            //content
+            "Ramp builds for Apple Silicon and Intel, combines both into a universal binary and assembles the .app bundle with your icon and Info.plist in the project's packages/macos directory. Release builds are also packaged as a zip and, where hdiutil is available, a .dmg with an Applications shortcut.",
+            //Size
+            TextSize::H3,
+            //style
+            TextStyle::Primary,
+            //alignment
+            Align::Center,
+            None
+        );
+
         // Combine icon, heading, and subtext into page content
         let content = Content::new(
             ctx,
             // Vertically center items
             Offset::Center,
             // All items must be boxed as Box<dyn Drawable>
-            vec![Box::new(text), Box::new(bundle_input)]
+            vec![Box::new(text), Box::new(bundle_input), Box::new(explainer)]
         );
 
         let bumper = Bumper::home(
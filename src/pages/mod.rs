@@ -6,4 +6,5 @@ pub mod macos;
 pub mod android;
 pub mod windows;
 pub mod linux;
-pub mod wasm;
\ No newline at end of file
+pub mod wasm;
+pub mod wizard;
@@ -10,7 +10,12 @@ use pelican_ui::components::interface::navigation::{AppPage, RootInfo, Navigatio
 use pelican_ui::interactions::Button;
 use crate::pages::start::StartScreen;
 use crate::ramp::session::{Session};
-use crate::ramp::core::{new_project};
+use crate::ramp::core::{new_project, LAST_PROJECT_KEY};
+use crate::ramp::bundle::{bundle_history, size_delta, OptLevel, WASM_OPT_LEVEL_KEY};
+use pelican_ui::components::RadioSelector;
+use pelican_ui::utils::Callback;
+use pelican_ui::components::list_item::{ListItem, ListItemGroup, ListItemInfoLeft};
+use pelican_ui::components::avatar::{AvatarContent, AvatarIconStyle};
 
 use serde::{Serialize, Deserialize};
 
@@ -49,14 +54,112 @@ impl WASMScreen {
             None
         );
 
+        let explainer = ExpandableText::new(
+            ctx,
+            //content
+            "Ramp runs wasm-bindgen on your build and writes a ready to host index.html, loader and favicon into the project's dist directory. Release builds are optimised with wasm-opt at the level chosen below, stripped, precompressed and packaged as an installable PWA with a web app manifest and offline service worker.",
+            //Size
+            TextSize::H3,
+            //style
+            TextStyle::Primary,
+            //alignment
+            Align::Center,
+            None
+        );
+
+        let server = ExpandableText::new(
+            ctx,
+            //content
+            "Local Server: ramp_gui -serve <project> serves dist at http://127.0.0.1:8080 and rebuilds on save",
+            //Size
+            TextSize::H4,
+            //style
+            TextStyle::Secondary,
+            //alignment
+            Align::Center,
+            None
+        );
+
+        //release bundle size history of the last loaded project, newest first
+        let session = Session::new().unwrap_or_default();
+        let project = session.get_config(LAST_PROJECT_KEY).ok().flatten().filter(|p| !p.is_empty());
+        let history = match (session.projects_path.as_ref(), project) {
+            (Some(projects), Some(project)) => bundle_history(&format!("{}/{}", projects, project)).unwrap_or_default(),
+            _ => vec![],
+        };
+        let list_items: Vec<ListItem> = history.iter().enumerate().rev().take(5).map(|(index, entry)| {
+            let delta = match size_delta(&history, index) {
+                Some(delta) => format!(" ({:+} bytes)", delta),
+                None => String::new(),
+            };
+            ListItem::new(
+                ctx,
+                Some(AvatarContent::Icon("monitor".to_string(), AvatarIconStyle::Primary)),
+                ListItemInfoLeft::new(
+                    &format!("{} build", entry.profile),
+                    &format!("{}: {} raw, {} brotli{}", entry.timestamp, entry.raw, entry.brotli, delta),
+                    None,
+                    None
+                ),
+                None,
+                None,
+                None,
+                |_ctx: &mut Context| {}
+            )
+        }).collect();
+
+        let sizes = ExpandableText::new(
+            ctx,
+            //content
+            if list_items.is_empty() { "Bundle Size: no release builds recorded yet" } else { "Release Bundle Size History" },
+            //Size
+            TextSize::H4,
+            //style
+            TextStyle::Secondary,
+            //alignment
+            Align::Center,
+            None
+        );
+
+        let optimisation = ExpandableText::new(
+            ctx,
+            //content
+            "Release Optimisation: wasm-opt level",
+            //Size
+            TextSize::H4,
+            //style
+            TextStyle::Secondary,
+            //alignment
+            Align::Center,
+            None
+        );
+
+        //the wasm-opt level of release builds, saved in the ramp config
+        let level = OptLevel::from_config(&session).unwrap_or(Some(OptLevel::Oz));
+        let levels: Vec<Option<OptLevel>> = std::iter::once(None).chain(OptLevel::ALL.into_iter().map(Some)).collect();
+        let index = levels.iter().position(|l| *l == level).unwrap_or(levels.len() - 1);
+        let choices: Vec<(&str, &str, Callback)> = levels.iter().map(|level| {
+            let value = level.map(|l| l.as_str()).unwrap_or("none");
+            let callback: Callback = Box::new(move |_ctx: &mut Context| {
+                if let Err(e) = Session::new().and_then(|session| session.set_config(WASM_OPT_LEVEL_KEY, value)) {
+                    println!("failed to save the wasm-opt level: {}", e);
+                }
+            });
+            (value, level.map(|l| l.description()).unwrap_or("skip wasm-opt"), callback)
+        }).collect();
+        let selector = RadioSelector::new(ctx, index, choices);
 
         // Combine icon, heading, and subtext into page content
+        let mut items: Vec<Box<dyn Drawable>> = vec![Box::new(text), Box::new(explainer), Box::new(server), Box::new(optimisation), Box::new(selector), Box::new(sizes)];
+        if !list_items.is_empty() {
+            items.push(Box::new(ListItemGroup::new(list_items)));
+        }
         let content = Content::new(
             ctx,
             // Vertically center items
             Offset::Center,
             // All items must be boxed as Box<dyn Drawable>
-            vec![Box::new(text)]
+            items
         );
 
         let bumper = Bumper::home(
@@ -49,6 +49,31 @@ impl WindowsScreen {
             None
         );
 
+        let explainer = ExpandableText::new(
+            ctx,
+            //content
+            "Ramp cross compiles for x86_64-pc-windows-gnu and packages a portable zip, a WiX source and an MSI installer into the project's packages/windows directory.",
+            //Size
+            TextSize::H3,
+            //style
+            TextStyle::Primary,
+            //alignment
+            Align::Center,
+            None
+        );
+
+        let version = ExpandableText::new(
+            ctx,
+            //content
+            "Product name, version and company are read from Cargo.toml ([package.metadata.ramp] company)",
+            //Size
+            TextSize::H4,
+            //style
+            TextStyle::Secondary,
+            //alignment
+            Align::Center,
+            None
+        );
 
         // Combine icon, heading, and subtext into page content
         let content = Content::new(
@@ -56,7 +81,7 @@ impl WindowsScreen {
             // Vertically center items
             Offset::Center,
             // All items must be boxed as Box<dyn Drawable>
-            vec![Box::new(text)]
+            vec![Box::new(text), Box::new(explainer), Box::new(version)]
         );
 
         let bumper = Bumper::home(
@@ -0,0 +1,242 @@
+use pelican_ui::drawable::{Drawable, Align};
+use pelican_ui::{Component, Context};
+use pelican_ui::layouts::{Column, Offset, Padding, Size, Stack};
+use pelican_ui::events::{OnEvent, Event, TickEvent};
+use pelican_ui::components::button::PrimaryButton;
+use pelican_ui::components::{ExpandableText, TextStyle, TextSize, TextInput};
+use pelican_ui::components::interface::general::{Content, Header, Page};
+use pelican_ui::components::interface::navigation::{AppPage, NavigationEvent};
+use crate::pages::ios::IOSScreen;
+use crate::ramp::session::Session;
+use crate::ramp::wizard::{Completion, StepStatus, Wizard, WizardKind, WizardStep};
+use crate::ramp::watcher::CancelToken;
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+//ticks between checks of the keychain step and before running an App Store Connect API step
+const CHECK_TICKS: u32 = 180;
+
+//sent by the continue button so the panel can read its inputs
+#[derive(Debug, Clone, Copy)]
+pub struct WizardContinueEvent;
+
+impl Event for WizardContinueEvent {
+    fn pass(self: Box<Self>, _ctx: &mut Context, children: &Vec<((f32, f32), (f32, f32))>) -> Vec<Option<Box<dyn Event>>> {
+        children.iter().map(|_| Some(Box::new(*self) as Box<dyn Event>)).collect()
+    }
+}
+
+#[derive(Debug, Component)]
+pub struct WizardScreen(Stack, Page);
+
+impl OnEvent for WizardScreen {}
+
+impl AppPage for WizardScreen {}
+
+impl WizardScreen {
+    pub fn new(ctx: &mut Context, kind: WizardKind) -> Result<Self, String> {
+        let header = Header::home(
+            ctx,
+            kind.title(),
+            Some(("close", Box::new(|ctx: &mut Context| {
+                let page = Box::new(IOSScreen::new(ctx).unwrap());
+                ctx.trigger_event(NavigationEvent::Push(Some(page)))})
+            ))
+        );
+
+        let panel = WizardPanel::new(ctx, kind);
+        let content = Content::new(
+            ctx,
+            Offset::Center,
+            vec![Box::new(panel) as Box<dyn Drawable>]
+        );
+
+        Ok(Self(Stack::default(), Page::new(header, content, None)))
+    }
+}
+
+//the session a wizard's actions run in, pointed at the wizard's project
+fn wizard_session(wizard: &Wizard) -> io::Result<Session> {
+    let mut session = Session::new()?;
+    session.get_all_paths()?;
+    if let Some(project) = wizard.value("project") {
+        session.update_current_project(project)?;
+    }
+    Ok(session)
+}
+
+fn step_instructions(step: &WizardStep) -> String {
+    step.instructions.iter().enumerate().map(|(index, line)| format!("{}. {}", index + 1, line)).collect::<Vec<_>>().join("\n")
+}
+
+//the current step of a saved wizard: its instructions, inputs and what it is waiting for
+#[derive(Debug, Component)]
+pub struct WizardPanel(
+    Column,
+    ExpandableText,
+    ExpandableText,
+    Vec<TextInput>,
+    ExpandableText,
+    PrimaryButton,
+    #[skip] Option<Wizard>,
+    #[skip] Option<usize>,
+    #[skip] u32,
+    #[skip] Option<(CancelToken, Receiver<io::Result<PathBuf>>)>,
+    #[skip] Option<Receiver<(Wizard, io::Result<StepStatus>)>>,
+    #[skip] bool,
+);
+
+impl WizardPanel {
+    pub fn new(ctx: &mut Context, kind: WizardKind) -> Self {
+        //only the keychain wizard can start without values from a build or deploy
+        let wizard = Session::new().ok().and_then(|session| {
+            Wizard::load(&session, kind).ok().flatten()
+                .or_else(|| (kind == WizardKind::Keychain).then(|| Wizard::new(kind)))?
+                .with_api_if_configured(&session).ok()
+        });
+        let title = ExpandableText::new(ctx, "", TextSize::H3, TextStyle::Heading, Align::Center, None);
+        let instructions = ExpandableText::new(ctx, "", TextSize::Md, TextStyle::Primary, Align::Left, None);
+        let status = ExpandableText::new(ctx, "", TextSize::Sm, TextStyle::Secondary, Align::Center, None);
+        let button = PrimaryButton::new(ctx, "Continue", |ctx: &mut Context| ctx.trigger_event(WizardContinueEvent), false);
+        let mut panel = WizardPanel(
+            Column::new(16.0, Offset::Center, Size::Fill, Padding::default()),
+            title,
+            instructions,
+            Vec::new(),
+            status,
+            button,
+            wizard,
+            None,
+            0,
+            None,
+            None,
+            false,
+        );
+        panel.show_step(ctx);
+        panel
+    }
+
+    //rebuild the text and inputs when the wizard moved to another step
+    fn show_step(&mut self, ctx: &mut Context) {
+        let Some(wizard) = self.6.as_ref() else {
+            self.1.0.spans[0] = "Nothing to do".to_string();
+            self.2.0.spans[0] = "This wizard starts when ramp needs it, for example when deploying to a device that isn't provisioned yet.".to_string();
+            return;
+        };
+        if self.7 == Some(wizard.step) {
+            return;
+        }
+        self.7 = Some(wizard.step);
+        //the previous step's download isn't waited on anymore
+        if let Some((cancel, _)) = self.9.take() {
+            cancel.cancel();
+        }
+        match wizard.current() {
+            Some(step) => {
+                self.1.0.spans[0] = step.title.to_string();
+                self.2.0.spans[0] = step_instructions(&step);
+                self.3 = step.inputs.iter().map(|input| {
+                    TextInput::new(ctx, wizard.value(input.key), Some(input.label), input.default.as_deref(), None, None)
+                }).collect();
+                self.4.0.spans[0] = String::new();
+                let watch = wizard_session(wizard).and_then(|mut session| {
+                    wizard.start(&mut session)?;
+                    wizard.watcher(&session)
+                });
+                match watch {
+                    Ok(Some(watcher)) => {
+                        self.4.0.spans[0] = format!("waiting for {} in {}", watcher.pattern, watcher.dir.display());
+                        let cancel = CancelToken::default();
+                        self.9 = Some((cancel.clone(), watcher.spawn(cancel)));
+                    }
+                    Ok(None) => {}
+                    Err(e) => self.4.0.spans[0] = format!("ERROR: {}", e),
+                }
+            }
+            None => {
+                self.1.0.spans[0] = format!("{} finished", wizard.kind.title());
+                self.2.0.spans[0] = String::new();
+                self.3 = Vec::new();
+            }
+        }
+    }
+
+    //steps can call App Store Connect or the keychain, so they run on a worker thread and the wizard comes back with the result
+    fn advance(&mut self) {
+        if self.10.is_some() {
+            return;
+        }
+        let Some(mut wizard) = self.6.clone() else { return };
+        if let Some(step) = wizard.current() {
+            for (input, field) in step.inputs.iter().zip(self.3.iter_mut()) {
+                wizard.set_input(input.key, &field.value());
+            }
+            if step.completion == Completion::Automatic {
+                self.4.0.spans[0] = "working with App Store Connect...".to_string();
+            }
+        }
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let result = wizard_session(&wizard).and_then(|mut session| wizard.advance(&mut session));
+            let _ = sender.send((wizard, result));
+        });
+        self.10 = Some(receiver);
+    }
+
+    //a failed step waits for the continue button instead of being retried on the next check
+    fn advanced(&mut self, ctx: &mut Context, wizard: Wizard, result: io::Result<StepStatus>) {
+        self.6 = Some(wizard);
+        self.11 = result.is_err();
+        self.4.0.spans[0] = match result {
+            Ok(StepStatus::Complete) => String::new(),
+            Ok(StepStatus::Waiting(reason)) => reason,
+            Err(e) => format!("ERROR: {}. Fix the problem and press Continue to try again.", e),
+        };
+        self.show_step(ctx);
+    }
+}
+
+impl OnEvent for WizardPanel {
+    fn on_event(&mut self, ctx: &mut Context, event: Box<dyn Event>) -> Vec<Box<dyn Event>> {
+        if event.downcast_ref::<WizardContinueEvent>().is_some() {
+            self.11 = false;
+            self.advance();
+        } else if event.downcast_ref::<TickEvent>().is_some() {
+            if let Some((wizard, result)) = self.10.as_ref().and_then(|receiver| receiver.try_recv().ok()) {
+                self.10 = None;
+                self.advanced(ctx, wizard, result);
+            }
+            //download steps finish by themselves once the watcher finds the file
+            match self.9.as_ref().and_then(|(_, receiver)| receiver.try_recv().ok()) {
+                Some(Ok(_)) => {
+                    self.9 = None;
+                    self.advance();
+                }
+                Some(Err(e)) => {
+                    self.9 = None;
+                    self.4.0.spans[0] = format!("{}. Enter the file's path above and continue.", e);
+                }
+                None => {}
+            }
+            self.8 += 1;
+            let automatic = self.6.as_ref()
+                .and_then(|w| w.current())
+                .is_some_and(|step| matches!(step.completion, Completion::KeychainUnlocked | Completion::Automatic));
+            if automatic && !self.11 && self.8 >= CHECK_TICKS {
+                self.8 = 0;
+                self.advance();
+            }
+        }
+        vec![event]
+    }
+}
+
+impl Drop for WizardPanel {
+    fn drop(&mut self) {
+        if let Some((cancel, _)) = self.9.take() {
+            cancel.cancel();
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use super::apk::is_signature_file;
+use super::helper::capitalize_first;
+use super::keystore::{ensure_android_keystore, AndroidKeystore};
+use super::manifest::ProjectManifest;
+use super::session::Session;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::Command;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+pub const BUNDLETOOL_VERSION: &str = "1.17.2";
+
+//the android triples installed by install_build_targets, every one is packaged into a bundle
+pub const ANDROID_TARGETS: [&str; 3] = ["aarch64-linux-android", "i686-linux-android", "x86_64-linux-android"];
+
+//the android abi directory name for a rust target triple
+pub fn android_abi(target: &str) -> Option<&'static str> {
+    match target {
+        "aarch64-linux-android" => Some("arm64-v8a"),
+        "armv7-linux-androideabi" => Some("armeabi-v7a"),
+        "i686-linux-android" => Some("x86"),
+        "x86_64-linux-android" => Some("x86_64"),
+        _ => None,
+    }
+}
+
+//where an entry of a proto format apk belongs in a bundletool module zip, None for entries that are dropped
+pub fn module_entry_path(apk_entry: &str) -> Option<String> {
+    if apk_entry.ends_with('/') || is_signature_file(apk_entry) {
+        //directories and the apk's own signature files, the rest of META-INF goes under root/
+        return None;
+    }
+    if apk_entry == "AndroidManifest.xml" {
+        return Some("manifest/AndroidManifest.xml".to_string());
+    }
+    if apk_entry == "resources.pb"
+        || apk_entry.starts_with("res/")
+        || apk_entry.starts_with("lib/")
+        || apk_entry.starts_with("assets/")
+    {
+        return Some(apk_entry.to_string());
+    }
+    if apk_entry.ends_with(".dex") && !apk_entry.contains('/') {
+        return Some(format!("dex/{}", apk_entry));
+    }
+    Some(format!("root/{}", apk_entry))
+}
+
+//repack a proto format apk (from `aapt2 convert --output-format proto`) into the base module layout bundletool expects
+pub fn build_base_module(proto_apk: &Path, module_zip: &Path) -> io::Result<Vec<String>> {
+    let mut archive = ZipArchive::new(File::open(proto_apk)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Failed to read {}: {}", proto_apk.display(), e)))?;
+    let mut module = ZipWriter::new(File::create(module_zip)?);
+    let mut abis = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Failed to read apk entry: {}", e)))?;
+        let Some(path) = module_entry_path(entry.name()) else { continue };
+        if let Some(abi) = path.strip_prefix("lib/").and_then(|p| p.split('/').next()) {
+            if !abis.iter().any(|a| a == abi) {
+                abis.push(abi.to_string());
+            }
+        }
+        //keep stored entries stored, android maps uncompressed native libs and resources straight from the file
+        let options = SimpleFileOptions::default().compression_method(match entry.compression() {
+            CompressionMethod::Stored => CompressionMethod::Stored,
+            _ => CompressionMethod::Deflated,
+        });
+        let mut contents = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut contents)?;
+        module
+            .start_file(path, options)
+            .map_err(|e| io::Error::other(format!("Failed to write module entry: {}", e)))?;
+        module.write_all(&contents)?;
+    }
+    if !archive.file_names().any(|name| name == "AndroidManifest.xml") {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "apk has no AndroidManifest.xml"));
+    }
+    module
+        .finish()
+        .map_err(|e| io::Error::other(format!("Failed to finish module zip: {}", e)))?;
+    Ok(abis)
+}
+
+fn java_tool(session: &mut Session, tool: &str) -> io::Result<String> {
+    Ok(format!("{}/bin/{}", session.get_path("java_path")?, tool))
+}
+
+fn run_tool(name: &str, command: &mut Command) -> io::Result<()> {
+    let output = command
+        .output()
+        .map_err(|e| io::Error::other(format!("Failed to run {}: {}", name, e)))?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{} failed: {}{}",
+            name,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(())
+}
+
+//sign the bundle with the upload key, play rejects bundles signed with the debug key
+pub fn sign_bundle(session: &mut Session, aab_path: &str, keystore: &AndroidKeystore) -> io::Result<()> {
+    let jarsigner = java_tool(session, "jarsigner")?;
+    run_tool(
+        "jarsigner",
+        Command::new(&jarsigner)
+            .args([
+                "-keystore", &keystore.path, "-storepass:env", "RAMP_KEYSTORE_PASSWORD",
+                "-sigalg", "SHA256withRSA", "-digestalg", "SHA-256", aab_path, &keystore.alias,
+            ])
+            .env("RAMP_KEYSTORE_PASSWORD", &keystore.password),
+    )?;
+    run_tool("jarsigner -verify", Command::new(&jarsigner).args(["-verify", aab_path]))
+}
+
+//turn the multi-abi release apk built by cargo-apk into a signed .aab ready for play upload
+pub fn android_bundle_post_build(session: &mut Session) -> io::Result<String> {
+    let project = session.current_project.as_ref().unwrap().to_string();
+    let project_path = format!("{}/{}", session.projects_path.as_ref().unwrap(), &project);
+    let apk_path = format!("{}/target/release/apk/{}.apk", &project_path, capitalize_first(&project));
+    if !Path::new(&apk_path).exists() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("release apk not found at {}", &apk_path)));
+    }
+    let work_path = format!("{}/target/release/aab", &project_path);
+    fs::create_dir_all(&work_path)?;
+
+    //bundletool needs resources in aapt2's protobuf format rather than the binary apk format
+    let proto_apk = format!("{}/proto.apk", &work_path);
+    run_tool(
+        "aapt2 convert",
+        Command::new(format!("{}/aapt2", session.get_path("build_tools_path")?))
+            .args(["convert", "--output-format", "proto", "-o", &proto_apk, &apk_path]),
+    )?;
+    let module_zip = format!("{}/base.zip", &work_path);
+    let abis = build_base_module(Path::new(&proto_apk), Path::new(&module_zip))?;
+    let missing: Vec<&str> = ANDROID_TARGETS
+        .iter()
+        .filter_map(|t| android_abi(t))
+        .filter(|abi| !abis.iter().any(|a| a == abi))
+        .collect();
+    if !missing.is_empty() {
+        println!("WARNING: the bundle has no native libraries for {}", missing.join(", "));
+    }
+
+    let version = ProjectManifest::load(&project_path)?.version;
+    let packages_path = format!("{}/packages/android", &project_path);
+    fs::create_dir_all(&packages_path)?;
+    let aab_path = format!("{}/{}-{}.aab", &packages_path, &project, &version);
+    run_tool(
+        "bundletool build-bundle",
+        Command::new(java_tool(session, "java")?).args([
+            "-jar",
+            &session.get_path("bundletool_path")?,
+            "build-bundle",
+            &format!("--modules={}", &module_zip),
+            &format!("--output={}", &aab_path),
+            "--overwrite",
+        ]),
+    )?;
+
+    let keystore = ensure_android_keystore(session)?;
+    sign_bundle(session, &aab_path, &keystore)?;
+    println!(
+        "signed app bundle with abis [{}] ({} bytes)",
+        abis.join(", "),
+        fs::metadata(&aab_path)?.len()
+    );
+    Ok(aab_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_apk_entries_into_the_base_module() {
+        let table: [(&str, Option<&str>); 17] = [
+            ("AndroidManifest.xml", Some("manifest/AndroidManifest.xml")),
+            ("classes.dex", Some("dex/classes.dex")),
+            ("classes2.dex", Some("dex/classes2.dex")),
+            ("lib/arm64-v8a/libmain.so", Some("lib/arm64-v8a/libmain.so")),
+            ("lib/x86_64/libc++_shared.so", Some("lib/x86_64/libc++_shared.so")),
+            ("assets/fonts/Inter.ttf", Some("assets/fonts/Inter.ttf")),
+            ("res/mipmap-hdpi/ic_launcher.png", Some("res/mipmap-hdpi/ic_launcher.png")),
+            ("resources.pb", Some("resources.pb")),
+            //anything else is kept as is under root/
+            ("kotlin/kotlin.kotlin_builtins", Some("root/kotlin/kotlin.kotlin_builtins")),
+            ("assets/classes.dex", Some("assets/classes.dex")),
+            ("META-INF/services/java.sql.Driver", Some("root/META-INF/services/java.sql.Driver")),
+            //signature files and directories are dropped
+            ("META-INF/MANIFEST.MF", None),
+            ("META-INF/CERT.SF", None),
+            ("META-INF/CERT.RSA", None),
+            ("META-INF/ANDROIDD.EC", None),
+            ("res/", None),
+            ("META-INF/", None),
+        ];
+        for (entry, expected) in table {
+            assert_eq!(module_entry_path(entry).as_deref(), expected, "{}", entry);
+        }
+    }
+}
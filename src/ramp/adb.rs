@@ -0,0 +1,537 @@
+use super::core::deploy_android_apk;
+use super::manifest::current_project_manifest;
+use super::session::Session;
+use std::fs;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+//ramp config key holding the serial picked on the android page
+pub const SELECTED_DEVICE_KEY: &str = "android_device_serial";
+
+//ramp config key holding the comma separated host:port of every connected wireless device
+pub const WIRELESS_DEVICES_KEY: &str = "android_wireless_devices";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceState {
+    //authorised and ready for adb commands
+    Device,
+    //usb debugging hasn't been accepted on the device yet
+    Unauthorized,
+    Offline,
+    //linux udev rules don't give the user access to the device
+    NoPermissions,
+    Other(String),
+}
+
+impl DeviceState {
+    fn parse(state: &str) -> Self {
+        match state {
+            "device" => DeviceState::Device,
+            "unauthorized" => DeviceState::Unauthorized,
+            "offline" => DeviceState::Offline,
+            s if s.starts_with("no permissions") => DeviceState::NoPermissions,
+            s => DeviceState::Other(s.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            DeviceState::Device => "ready",
+            DeviceState::Unauthorized => "unauthorized",
+            DeviceState::Offline => "offline",
+            DeviceState::NoPermissions => "no permissions",
+            DeviceState::Other(s) => s,
+        }
+    }
+
+    //what the user has to do before ramp can deploy to a device in this state
+    pub fn guidance(&self) -> Option<&'static str> {
+        match self {
+            DeviceState::Device => None,
+            DeviceState::Unauthorized => Some("unlock the device and accept the \"Allow USB debugging?\" prompt, replug the cable if no prompt appears"),
+            DeviceState::Offline => Some("the device stopped responding to adb, replug it or run `adb kill-server` and try again"),
+            DeviceState::NoPermissions => Some("add a udev rule for the device's vendor id and add your user to the plugdev group, then replug it"),
+            DeviceState::Other(_) => Some("the device is in a state ramp can't deploy to, check it with `adb devices -l`"),
+        }
+    }
+}
+
+//a device or emulator as reported by `adb devices -l`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AndroidDevice {
+    pub serial: String,
+    pub state: DeviceState,
+    pub model: Option<String>,
+    pub product: Option<String>,
+    pub device: Option<String>,
+    pub transport_id: Option<u32>,
+}
+
+impl AndroidDevice {
+    pub fn is_ready(&self) -> bool {
+        self.state == DeviceState::Device
+    }
+
+    pub fn is_emulator(&self) -> bool {
+        self.serial.starts_with("emulator-")
+    }
+
+    //connected over wireless debugging, either by host:port or through adb's mdns discovery
+    pub fn is_wireless(&self) -> bool {
+        self.serial.contains("._adb-tls-connect._tcp") || (!self.is_emulator() && self.serial.contains(':'))
+    }
+
+    //name for the device picker, adb reports models with underscores for spaces
+    pub fn display_name(&self) -> String {
+        match &self.model {
+            Some(model) => format!("{} ({})", model.replace('_', " "), self.serial),
+            None => self.serial.clone(),
+        }
+    }
+}
+
+//parse the output of `adb devices -l`
+pub fn parse_devices(output: &str) -> Vec<AndroidDevice> {
+    output
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with("List of devices") && !l.starts_with('*'))
+        .filter_map(|line| {
+            let (serial, rest) = line.split_once(char::is_whitespace)?;
+            let rest = rest.trim_start();
+            //properties are the trailing key:value words, the state is everything before them
+            let words: Vec<&str> = rest.split_whitespace().collect();
+            let first_property = words
+                .iter()
+                .position(|w| {
+                    ["usb:", "product:", "model:", "device:", "transport_id:"].iter().any(|p| w.starts_with(p))
+                })
+                .unwrap_or(words.len());
+            let property = |key: &str| {
+                words[first_property..]
+                    .iter()
+                    .find_map(|w| w.strip_prefix(key).and_then(|v| v.strip_prefix(':')))
+                    .map(|v| v.to_string())
+            };
+            Some(AndroidDevice {
+                serial: serial.to_string(),
+                state: DeviceState::parse(&words[..first_property].join(" ")),
+                model: property("model"),
+                product: property("product"),
+                device: property("device"),
+                transport_id: property("transport_id").and_then(|t| t.parse().ok()),
+            })
+        })
+        .collect()
+}
+
+pub fn adb_path(session: &mut Session) -> io::Result<String> {
+    Ok(format!("{}/adb", session.get_path("platform_tools_path")?))
+}
+
+pub fn list_devices(adb_path: &str) -> io::Result<Vec<AndroidDevice>> {
+    let output = Command::new(adb_path)
+        .args(["devices", "-l"])
+        .output()
+        .map_err(|e| io::Error::other(format!("Failed to run adb devices: {}", e)))?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "adb devices failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(parse_devices(&String::from_utf8_lossy(&output.stdout)))
+}
+
+//pick the device to deploy to: the preferred serial if given, otherwise the only ready device
+pub fn select_device(devices: &[AndroidDevice], preferred: Option<&str>) -> io::Result<AndroidDevice> {
+    let not_ready = |device: &AndroidDevice| {
+        io::Error::other(format!(
+            "{} is {}: {}",
+            device.display_name(),
+            device.state.as_str(),
+            device.state.guidance().unwrap_or("")
+        ))
+    };
+    if let Some(serial) = preferred {
+        match devices.iter().find(|d| d.serial == serial) {
+            Some(device) if device.is_ready() => return Ok(device.clone()),
+            Some(device) => return Err(not_ready(device)),
+            None => println!("selected android device {} is not connected", serial),
+        }
+    }
+    let ready: Vec<&AndroidDevice> = devices.iter().filter(|d| d.is_ready()).collect();
+    match ready.as_slice() {
+        [device] => Ok((*device).clone()),
+        [] => match devices.iter().find(|d| !d.is_ready()) {
+            Some(device) => Err(not_ready(device)),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no android device detected, connect one with usb debugging enabled or start an emulator",
+            )),
+        },
+        several => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} android devices are connected ({}), pick one on the Android page",
+                several.len(),
+                several.iter().map(|d| d.serial.as_str()).collect::<Vec<_>>().join(", ")
+            ),
+        )),
+    }
+}
+
+//host:port in the form adb pair and adb connect expect
+pub fn parse_wireless_address(address: &str) -> io::Result<String> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("expected host:port, got {}", address));
+    let (host, port) = address.trim().rsplit_once(':').ok_or_else(invalid)?;
+    if host.is_empty() || port.parse::<u16>().is_err() {
+        return Err(invalid());
+    }
+    Ok(format!("{}:{}", host, port))
+}
+
+//pair with a device showing "Pair device with pairing code" in its wireless debugging settings (android 11+)
+//the pairing port is only used once, connecting uses the port shown on the wireless debugging screen
+pub fn pair_device(adb_path: &str, pair_address: &str, code: &str) -> io::Result<()> {
+    let address = parse_wireless_address(pair_address)?;
+    let output = Command::new(adb_path).args(["pair", &address, code]).output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() || !stdout.contains("Successfully paired") {
+        return Err(io::Error::other(format!(
+            "pairing with {} failed, check the code and that the pairing dialog is still open: {}{}",
+            &address,
+            stdout.trim(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+//adb connect exits with 0 even when the connection failed
+pub fn connect_device(adb_path: &str, address: &str) -> io::Result<()> {
+    let address = parse_wireless_address(address)?;
+    let output = Command::new(adb_path).args(["connect", &address]).output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() || !(stdout.contains("connected to") || stdout.contains("already connected")) {
+        return Err(io::Error::other(format!("could not connect to {}: {}", &address, stdout.trim())));
+    }
+    Ok(())
+}
+
+pub fn disconnect_device(adb_path: &str, address: &str) -> io::Result<()> {
+    Command::new(adb_path).args(["disconnect", address]).output()?;
+    Ok(())
+}
+
+pub fn known_wireless_devices(session: &Session) -> io::Result<Vec<String>> {
+    Ok(session
+        .get_config(WIRELESS_DEVICES_KEY)?
+        .map(|v| v.split(',').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect())
+        .unwrap_or_default())
+}
+
+fn save_wireless_devices(session: &Session, addresses: &[String]) -> io::Result<()> {
+    session.set_config(WIRELESS_DEVICES_KEY, &addresses.join(","))
+}
+
+//connect to a wireless device and remember it for reconnecting later
+pub fn add_wireless_device(session: &mut Session, address: &str) -> io::Result<()> {
+    let address = parse_wireless_address(address)?;
+    connect_device(&adb_path(session)?, &address)?;
+    let mut known = known_wireless_devices(session)?;
+    if !known.contains(&address) {
+        known.push(address);
+        save_wireless_devices(session, &known)?;
+    }
+    Ok(())
+}
+
+pub fn forget_wireless_device(session: &mut Session, address: &str) -> io::Result<()> {
+    disconnect_device(&adb_path(session)?, address)?;
+    let known: Vec<String> = known_wireless_devices(session)?.into_iter().filter(|a| a != address).collect();
+    save_wireless_devices(session, &known)
+}
+
+//connected devices, reconnecting known wireless devices that dropped off (the phone slept or changed network)
+pub fn connected_devices(session: &mut Session) -> io::Result<Vec<AndroidDevice>> {
+    let adb_path = adb_path(session)?;
+    let devices = list_devices(&adb_path)?;
+    if reconnect_wireless_devices(session, &adb_path, &devices)? { list_devices(&adb_path) } else { Ok(devices) }
+}
+
+//`adb connect` the saved wireless devices missing from `devices`, true if any came back
+//each unreachable address blocks until adb gives up on it, so the gui runs this off the ui thread
+pub fn reconnect_wireless_devices(session: &Session, adb_path: &str, devices: &[AndroidDevice]) -> io::Result<bool> {
+    let mut reconnected = false;
+    for address in known_wireless_devices(session)? {
+        if devices.iter().any(|d| d.serial == address && d.is_ready()) {
+            continue;
+        }
+        match connect_device(adb_path, &address) {
+            Ok(()) => reconnected = true,
+            Err(e) => println!("wireless device {} is unreachable: {}", address, e),
+        }
+    }
+    Ok(reconnected)
+}
+
+//the device picked on the android page, falling back to the only connected device
+pub fn selected_device(session: &mut Session) -> io::Result<AndroidDevice> {
+    let devices = connected_devices(session)?;
+    let preferred = session.get_config(SELECTED_DEVICE_KEY)?;
+    select_device(&devices, preferred.as_deref())
+}
+
+//an adb command addressed to one device
+pub fn adb_command(adb_path: &str, serial: &str) -> Command {
+    let mut command = Command::new(adb_path);
+    command.args(["-s", serial]);
+    command
+}
+
+//per-app management actions run against the selected device
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AppAction {
+    Uninstall,
+    ClearData,
+    ForceStop,
+    PullData,
+    GrantPermission,
+    RevokePermission,
+    //force-stop, uninstall and deploy the debug apk again with fresh data
+    CleanReinstall,
+}
+
+impl AppAction {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "uninstall" => Some(AppAction::Uninstall),
+            "clear" => Some(AppAction::ClearData),
+            "stop" => Some(AppAction::ForceStop),
+            "pull" => Some(AppAction::PullData),
+            "grant" => Some(AppAction::GrantPermission),
+            "revoke" => Some(AppAction::RevokePermission),
+            "reinstall" => Some(AppAction::CleanReinstall),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AppAction::Uninstall => "uninstall",
+            AppAction::ClearData => "clear",
+            AppAction::ForceStop => "stop",
+            AppAction::PullData => "pull",
+            AppAction::GrantPermission => "grant",
+            AppAction::RevokePermission => "revoke",
+            AppAction::CleanReinstall => "reinstall",
+        }
+    }
+}
+
+//the application id from [package.metadata.android] of the current project
+pub fn project_package(session: &Session) -> io::Result<String> {
+    Ok(current_project_manifest(session)?.android_package()?.to_string())
+}
+
+//run an adb command on a device, pm and am report most failures on stdout with a zero exit code
+fn run_adb(adb_path: &str, serial: &str, args: &[&str]) -> io::Result<String> {
+    let output = adb_command(adb_path, serial).args(args).output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() || stdout.contains("Failure") || stderr.contains("Exception") {
+        return Err(io::Error::other(format!("adb {} failed: {}{}", args.join(" "), stdout.trim(), stderr.trim())));
+    }
+    Ok(stdout)
+}
+
+//returns false if the package wasn't installed
+pub fn uninstall_app(adb_path: &str, serial: &str, package: &str) -> io::Result<bool> {
+    match run_adb(adb_path, serial, &["uninstall", package]) {
+        Ok(_) => Ok(true),
+        Err(e) if e.to_string().contains("DELETE_FAILED_INTERNAL_ERROR") || e.to_string().contains("Unknown package") => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn clear_app_data(adb_path: &str, serial: &str, package: &str) -> io::Result<()> {
+    run_adb(adb_path, serial, &["shell", "pm", "clear", package])?;
+    Ok(())
+}
+
+pub fn force_stop_app(adb_path: &str, serial: &str, package: &str) -> io::Result<()> {
+    run_adb(adb_path, serial, &["shell", "am", "force-stop", package])?;
+    Ok(())
+}
+
+//permissions can be given as CAMERA or android.permission.CAMERA
+pub fn qualify_permission(permission: &str) -> String {
+    if permission.contains('.') {
+        permission.to_string()
+    } else {
+        format!("android.permission.{}", permission.to_uppercase())
+    }
+}
+
+//grant or revoke a runtime permission, only dangerous permissions declared in the manifest can be changed
+pub fn set_permission(adb_path: &str, serial: &str, package: &str, permission: &str, granted: bool) -> io::Result<()> {
+    let verb = if granted { "grant" } else { "revoke" };
+    run_adb(adb_path, serial, &["shell", "pm", verb, package, &qualify_permission(permission)])?;
+    Ok(())
+}
+
+//copy the app's private data directory to dest with run-as, which only works for debuggable (debug) builds
+pub fn pull_app_data(adb_path: &str, serial: &str, package: &str, dest: &str) -> io::Result<()> {
+    let output = adb_command(adb_path, serial).args(["exec-out", "run-as", package, "tar", "cf", "-", "."]).output()?;
+    //run-as errors arrive on stdout through exec-out
+    if !output.status.success() || output.stdout.starts_with(b"run-as:") {
+        return Err(io::Error::other(format!(
+            "could not read the data of {}, only debug builds can be pulled: {}{}",
+            package,
+            String::from_utf8_lossy(&output.stdout[..output.stdout.len().min(200)]).trim(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    fs::create_dir_all(dest)?;
+    let mut tar = Command::new("tar").args(["-xf", "-", "-C", dest]).stdin(Stdio::piped()).spawn()?;
+    tar.stdin.take().unwrap().write_all(&output.stdout)?;
+    if !tar.wait()?.success() {
+        return Err(io::Error::other(format!("failed to extract the data of {} to {}", package, dest)));
+    }
+    Ok(())
+}
+
+//run an app action for the current project on the selected device
+pub fn run_app_action(session: &mut Session, action: AppAction, permission: Option<&str>) -> io::Result<()> {
+    let device = selected_device(session)?;
+    let adb_path = adb_path(session)?;
+    let package = project_package(session)?;
+    let permission = || {
+        permission.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{} needs a permission name", action.as_str())))
+    };
+    match action {
+        AppAction::Uninstall => {
+            if !uninstall_app(&adb_path, &device.serial, &package)? {
+                println!("{} is not installed on {}", &package, device.display_name());
+            }
+        }
+        AppAction::ClearData => clear_app_data(&adb_path, &device.serial, &package)?,
+        AppAction::ForceStop => force_stop_app(&adb_path, &device.serial, &package)?,
+        AppAction::PullData => {
+            let dest = format!(
+                "{}/{}/packages/android/data/{}",
+                session.projects_path.as_ref().unwrap(),
+                session.current_project.as_ref().unwrap(),
+                &device.serial
+            );
+            pull_app_data(&adb_path, &device.serial, &package, &dest)?;
+            println!("app data copied to {}", dest);
+        }
+        AppAction::GrantPermission => set_permission(&adb_path, &device.serial, &package, permission()?, true)?,
+        AppAction::RevokePermission => set_permission(&adb_path, &device.serial, &package, permission()?, false)?,
+        AppAction::CleanReinstall => {
+            force_stop_app(&adb_path, &device.serial, &package)?;
+            uninstall_app(&adb_path, &device.serial, &package)?;
+            deploy_android_apk(session, &device)?;
+        }
+    }
+    println!("{} {} on {}", action.as_str(), &package, device.display_name());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEVICES: &str = include_str!("../../tests/fixtures/android/devices.txt");
+
+    fn serials(devices: &[AndroidDevice]) -> Vec<&str> {
+        devices.iter().map(|d| d.serial.as_str()).collect()
+    }
+
+    #[test]
+    fn parses_adb_devices_output() {
+        let devices = parse_devices(DEVICES);
+        assert_eq!(
+            serials(&devices),
+            [
+                "R58M123ABC",
+                "emulator-5554",
+                "0A171FDD40063C",
+                "192.168.1.20:37415",
+                "adb-2B0BC1AC-xq9KcL._adb-tls-connect._tcp",
+                "HT7A1A234567"
+            ]
+        );
+        assert_eq!(
+            devices[0],
+            AndroidDevice {
+                serial: "R58M123ABC".to_string(),
+                state: DeviceState::Device,
+                model: Some("SM_G973F".to_string()),
+                product: Some("beyond1lteeea".to_string()),
+                device: Some("beyond1".to_string()),
+                transport_id: Some(3),
+            }
+        );
+        assert_eq!(devices[0].display_name(), "SM G973F (R58M123ABC)");
+        assert!(devices[1].is_emulator() && !devices[1].is_wireless());
+        assert_eq!(devices[1].transport_id, Some(1));
+    }
+
+    #[test]
+    fn parses_devices_that_are_not_ready() {
+        let devices = parse_devices(DEVICES);
+        assert_eq!(devices[2].state, DeviceState::Unauthorized);
+        assert_eq!(devices[2].model, None);
+        assert_eq!(devices[2].transport_id, Some(4));
+        assert_eq!(devices[3].state, DeviceState::Offline);
+        //the no permissions state runs over several words before the properties
+        assert_eq!(devices[5].state, DeviceState::NoPermissions);
+        assert_eq!(devices[5].transport_id, Some(5));
+        assert!(devices.iter().filter(|d| !d.is_ready()).all(|d| d.state.guidance().is_some()));
+        assert_eq!(parse_devices("List of devices attached\nabc123\trecovery\n")[0].state, DeviceState::Other("recovery".to_string()));
+    }
+
+    #[test]
+    fn recognises_wireless_devices() {
+        let devices = parse_devices(DEVICES);
+        let wireless: Vec<&str> = devices.iter().filter(|d| d.is_wireless()).map(|d| d.serial.as_str()).collect();
+        assert_eq!(wireless, ["192.168.1.20:37415", "adb-2B0BC1AC-xq9KcL._adb-tls-connect._tcp"]);
+    }
+
+    #[test]
+    fn selects_the_preferred_or_only_ready_device() {
+        let devices = parse_devices(DEVICES);
+        assert_eq!(select_device(&devices, Some("emulator-5554")).unwrap().serial, "emulator-5554");
+        let error = select_device(&devices, Some("0A171FDD40063C")).unwrap_err();
+        assert!(error.to_string().starts_with("0A171FDD40063C is unauthorized: unlock the device"));
+        //three devices are ready, so ramp can't guess
+        let error = select_device(&devices, None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error.to_string().starts_with("3 android devices are connected (R58M123ABC, emulator-5554, adb-2B0BC1AC"));
+    }
+
+    #[test]
+    fn falls_back_when_the_preferred_device_has_gone() {
+        let devices = parse_devices(DEVICES);
+        let only_emulator: Vec<AndroidDevice> = devices.iter().filter(|d| d.is_emulator() || !d.is_ready()).cloned().collect();
+        assert_eq!(select_device(&only_emulator, Some("R58M123ABC")).unwrap().serial, "emulator-5554");
+        //without a ready device the first device that isn't ready explains why
+        let not_ready: Vec<AndroidDevice> = devices.iter().filter(|d| !d.is_ready()).cloned().collect();
+        assert!(select_device(&not_ready, Some("R58M123ABC")).unwrap_err().to_string().contains("is unauthorized"));
+        assert_eq!(select_device(&[], Some("R58M123ABC")).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn parses_wireless_addresses() {
+        assert_eq!(parse_wireless_address("192.168.1.20:37415").unwrap(), "192.168.1.20:37415");
+        assert_eq!(parse_wireless_address("  pixel.local:5555\n").unwrap(), "pixel.local:5555");
+        //ipv6 hosts keep their colons
+        assert_eq!(parse_wireless_address("[fe80::1]:5555").unwrap(), "[fe80::1]:5555");
+        for address in ["192.168.1.20", ":5555", "192.168.1.20:", "192.168.1.20:port", "192.168.1.20:70000"] {
+            assert_eq!(parse_wireless_address(address).unwrap_err().kind(), io::ErrorKind::InvalidInput, "{}", address);
+        }
+    }
+}
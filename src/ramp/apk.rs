@@ -0,0 +1,412 @@
+use super::aab::{android_abi, ANDROID_TARGETS};
+use super::axml::{parse_axml, set_version_code};
+use super::helper::capitalize_first;
+use super::keystore::{verify_apk_signature, CARGO_APK_KEYSTORE_ENV, CARGO_APK_KEYSTORE_PASSWORD_ENV};
+use super::manifest::ProjectManifest;
+use super::session::Session;
+use super::windows::WindowsVersionInfo;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+//split version codes are offset per abi, so the base code has to stay below this
+const ABI_VERSION_STRIDE: u32 = 10_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApkLayout {
+    //one apk with native libraries for every selected abi
+    Fat,
+    //one apk per abi, each with its own version code
+    Split,
+}
+
+impl ApkLayout {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApkLayout::Fat => "fat",
+            ApkLayout::Split => "split",
+        }
+    }
+}
+
+//which abis to build and how to package them, persisted in the .ramp config
+#[derive(Debug, Clone, PartialEq)]
+pub struct AndroidBuildOptions {
+    pub targets: Vec<String>,
+    pub layout: ApkLayout,
+}
+
+impl Default for AndroidBuildOptions {
+    fn default() -> Self {
+        AndroidBuildOptions {
+            targets: ANDROID_TARGETS.iter().map(|t| t.to_string()).collect(),
+            layout: ApkLayout::Fat,
+        }
+    }
+}
+
+impl AndroidBuildOptions {
+    pub fn load(session: &Session) -> io::Result<Self> {
+        let mut options = AndroidBuildOptions::default();
+        if let Some(targets) = session.get_config("android_targets")? {
+            let selected: Vec<String> = ANDROID_TARGETS
+                .iter()
+                .filter(|t| targets.split(',').any(|s| s.trim() == **t))
+                .map(|t| t.to_string())
+                .collect();
+            if !selected.is_empty() {
+                options.targets = selected;
+            }
+        }
+        if session.get_config("android_apk_layout")?.as_deref() == Some("split") {
+            options.layout = ApkLayout::Split;
+        }
+        Ok(options)
+    }
+
+    pub fn save(&self, session: &Session) -> io::Result<()> {
+        session.set_config("android_targets", &self.targets.join(","))?;
+        session.set_config("android_apk_layout", self.layout.as_str())
+    }
+
+    //enable or disable a target, keeping install order and at least one target selected
+    pub fn set_target(&mut self, target: &str, enabled: bool) {
+        let mut selected: Vec<String> = ANDROID_TARGETS
+            .iter()
+            .filter(|t| if **t == target { enabled } else { self.targets.iter().any(|s| s == *t) })
+            .map(|t| t.to_string())
+            .collect();
+        if selected.is_empty() {
+            println!("WARNING: at least one android abi must be built, keeping {}", target);
+            selected.push(target.to_string());
+        }
+        self.targets = selected;
+    }
+
+    pub fn target_args(&self) -> String {
+        self.targets.iter().map(|t| format!(" --target {}", t)).collect()
+    }
+}
+
+//play serves the highest compatible version code, so 64 bit abis rank above their 32 bit fallbacks
+pub fn abi_version_offset(abi: &str) -> Option<u32> {
+    match abi {
+        "armeabi-v7a" => Some(1),
+        "arm64-v8a" => Some(2),
+        "x86" => Some(3),
+        "x86_64" => Some(4),
+        _ => None,
+    }
+}
+
+pub fn split_version_code(base: u32, abi: &str) -> io::Result<u32> {
+    if base >= ABI_VERSION_STRIDE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("version code {} is too large to offset per abi (must be below {})", base, ABI_VERSION_STRIDE),
+        ));
+    }
+    let offset = abi_version_offset(abi)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("unknown android abi: {}", abi)))?;
+    Ok(offset * ABI_VERSION_STRIDE + base)
+}
+
+//the manifest's version_code, or one derived from the crate version as major*10000 + minor*100 + patch
+pub fn base_version_code(manifest: &ProjectManifest) -> u32 {
+    if let Some(version_code) = manifest.android.version_code {
+        return version_code;
+    }
+    let [major, minor, patch, _] = WindowsVersionInfo::from_manifest(manifest).numeric_version();
+    major as u32 * 10_000 + minor.min(99) as u32 * 100 + patch.min(99) as u32
+}
+
+#[derive(Debug, Clone)]
+pub struct ApkArtifact {
+    //None for a fat apk covering every selected abi
+    pub abi: Option<String>,
+    pub version_code: Option<u32>,
+    pub path: String,
+    pub size: u64,
+}
+
+pub fn size_report(artifacts: &[ApkArtifact]) -> String {
+    let mut report = String::from("abi          version code  size (KB)  path\n");
+    for artifact in artifacts {
+        report.push_str(&format!(
+            "{:<12} {:>12}  {:>9.1}  {}\n",
+            artifact.abi.as_deref().unwrap_or("universal"),
+            artifact.version_code.map(|c| c.to_string()).unwrap_or_else(|| "-".to_string()),
+            artifact.size as f64 / 1024.0,
+            artifact.path
+        ));
+    }
+    report
+}
+
+fn zip_error(e: zip::result::ZipError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Failed to rewrite apk: {}", e))
+}
+
+//v1 (jar) signature files, apksigner writes new ones
+//the rest of META-INF, such as service loader files, belongs to the app
+pub fn is_signature_file(name: &str) -> bool {
+    let Some(file) = name.strip_prefix("META-INF/") else { return false };
+    let file = file.to_ascii_uppercase();
+    !file.contains('/') && (file == "MANIFEST.MF" || [".SF", ".RSA", ".DSA", ".EC"].iter().any(|ext| file.ends_with(ext)))
+}
+
+//copy an apk to output with a new AndroidManifest.xml, leaving out the signature that no longer matches
+fn replace_manifest(archive: &mut ZipArchive<File>, manifest: &[u8], output: &str) -> io::Result<()> {
+    let mut writer = ZipWriter::new(File::create(output)?);
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i).map_err(zip_error)?;
+        let name = entry.name().to_string();
+        if is_signature_file(&name) {
+            continue;
+        }
+        if name == "AndroidManifest.xml" {
+            drop(entry);
+            writer
+                .start_file(name, SimpleFileOptions::default().compression_method(CompressionMethod::Deflated))
+                .map_err(zip_error)?;
+            writer.write_all(manifest)?;
+        } else {
+            writer.raw_copy_file(entry).map_err(zip_error)?;
+        }
+    }
+    writer.finish().map_err(zip_error)?;
+    Ok(())
+}
+
+//give a built apk its per-abi version code, the project's Cargo.toml is never touched
+//the manifest is patched in place, then the apk is realigned and signed again with the release key
+fn stamp_version_code(session: &mut Session, apk_path: &str, version_code: u32, signing_env: &[(&'static str, String)]) -> io::Result<()> {
+    let mut archive = ZipArchive::new(File::open(apk_path)?).map_err(zip_error)?;
+    let mut manifest = Vec::new();
+    archive.by_name("AndroidManifest.xml").map_err(zip_error)?.read_to_end(&mut manifest)?;
+    if parse_axml(&manifest)?.attribute("versionCode") == Some(version_code.to_string().as_str()) {
+        return Ok(());
+    }
+    let patched = set_version_code(&manifest, version_code)?;
+
+    let unaligned = format!("{}.unaligned", apk_path);
+    replace_manifest(&mut archive, &patched, &unaligned)?;
+    drop(archive);
+
+    let build_tools = session.get_path("build_tools_path")?;
+    let zipalign = Command::new(format!("{}/zipalign", build_tools))
+        .args(["-p", "-f", "4", &unaligned, apk_path])
+        .output()
+        .map_err(|e| io::Error::other(format!("Failed to run zipalign: {}", e)));
+    let _ = fs::remove_file(&unaligned);
+    let zipalign = zipalign?;
+    if !zipalign.status.success() {
+        return Err(io::Error::other(format!("zipalign failed: {}", String::from_utf8_lossy(&zipalign.stderr))));
+    }
+
+    let keystore = signing_env
+        .iter()
+        .find(|(k, _)| *k == CARGO_APK_KEYSTORE_ENV)
+        .map(|(_, v)| v.as_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no release keystore to sign the apk with"))?;
+    //the password stays in the environment rather than argv
+    let output = Command::new(format!("{}/apksigner", build_tools))
+        .args(["sign", "--ks", keystore, "--ks-pass", &format!("env:{}", CARGO_APK_KEYSTORE_PASSWORD_ENV), apk_path])
+        .env("JAVA_HOME", session.get_path("java_path")?)
+        .envs(signing_env.iter().map(|(k, v)| (*k, v.as_str())))
+        .output()
+        .map_err(|e| io::Error::other(format!("Failed to run apksigner: {}", e)))?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("apksigner failed to sign {}: {}", apk_path, String::from_utf8_lossy(&output.stderr))));
+    }
+    Ok(())
+}
+
+fn run_cargo_apk(session: &mut Session, project_path: &str, args: &str, signing_env: &[(&'static str, String)]) -> io::Result<()> {
+    let output = Command::new("bash")
+        .arg("-c")
+        .arg(format!("{} {}", session.get_path("cargo_path")?, args))
+        .current_dir(project_path)
+        .env("JAVA_HOME", session.get_path("java_path")?)
+        .env("ANDROID_HOME", session.get_path("sdk_path")?)
+        .env("NDK_HOME", session.get_path("ndk_path")?)
+        .envs(signing_env.iter().map(|(k, v)| (*k, v.as_str())))
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("Cargo build failed: {}", String::from_utf8_lossy(&output.stderr))));
+    }
+    Ok(())
+}
+
+//build the selected abis as one fat apk or one apk per abi, release artifacts are copied to packages/android
+//debug builds are always fat so usb deployment has a single apk to install
+pub fn build_android_apks(
+    session: &mut Session,
+    release: bool,
+    signing_env: &[(&'static str, String)],
+) -> io::Result<Vec<ApkArtifact>> {
+    let options = AndroidBuildOptions::load(session)?;
+    let project = session.current_project.as_ref().unwrap().to_string();
+    let project_path = format!("{}/{}", session.projects_path.as_ref().unwrap(), &project);
+    let profile = if release { "release" } else { "debug" };
+    let built_apk = format!("{}/target/{}/apk/{}.apk", &project_path, profile, capitalize_first(&project));
+
+    if !release {
+        run_cargo_apk(session, &project_path, &format!("apk build --lib{}", options.target_args()), signing_env)?;
+        let size = fs::metadata(&built_apk)?.len();
+        return Ok(vec![ApkArtifact { abi: None, version_code: None, path: built_apk, size }]);
+    }
+
+    let manifest = ProjectManifest::load(&project_path)?;
+    let base = base_version_code(&manifest);
+    let version = manifest.version;
+    let packages_path = format!("{}/packages/android", &project_path);
+    fs::create_dir_all(&packages_path)?;
+
+    //(abi, version code, cargo target args) for each apk to produce
+    let builds: Vec<(Option<String>, u32, String)> = match options.layout {
+        ApkLayout::Fat => vec![(None, base, options.target_args())],
+        ApkLayout::Split => options
+            .targets
+            .iter()
+            .map(|t| {
+                let abi = android_abi(t).unwrap_or(t.as_str()).to_string();
+                Ok((Some(abi.clone()), split_version_code(base, &abi)?, format!(" --target {}", t)))
+            })
+            .collect::<io::Result<_>>()?,
+    };
+
+    let mut artifacts = Vec::new();
+    for (abi, version_code, target_args) in builds {
+        run_cargo_apk(session, &project_path, &format!("apk build --release{}", target_args), signing_env)?;
+        stamp_version_code(session, &built_apk, version_code, signing_env)?;
+        let path = format!(
+            "{}/{}-{}-{}.apk",
+            &packages_path,
+            &project,
+            &version,
+            abi.as_deref().unwrap_or("universal")
+        );
+        fs::copy(&built_apk, &path)?;
+        let signer = verify_apk_signature(session, &path)?;
+        println!("verified signature of {}\n{}", &path, signer);
+        let size = fs::metadata(&path)?.len();
+        artifacts.push(ApkArtifact { abi, version_code: Some(version_code), path, size });
+    }
+
+    //leave only this build's apks behind so stale splits are never uploaded
+    for entry in fs::read_dir(&packages_path)? {
+        let path = entry?.path();
+        let name = path.to_string_lossy().to_string();
+        if name.ends_with(".apk") && !artifacts.iter().any(|a| a.path == name) && Path::new(&name).is_file() {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(artifacts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ramp::helper::test_dir;
+
+    fn manifest(toml: &str) -> ProjectManifest {
+        ProjectManifest::parse(toml, None).unwrap()
+    }
+
+    #[test]
+    fn offsets_version_codes_per_abi() {
+        assert_eq!(split_version_code(10203, "armeabi-v7a").unwrap(), 10_010_203);
+        assert_eq!(split_version_code(10203, "arm64-v8a").unwrap(), 20_010_203);
+        assert_eq!(split_version_code(10203, "x86").unwrap(), 30_010_203);
+        assert_eq!(split_version_code(10203, "x86_64").unwrap(), 40_010_203);
+        assert_eq!(split_version_code(ABI_VERSION_STRIDE - 1, "x86_64").unwrap(), 49_999_999);
+        assert_eq!(split_version_code(ABI_VERSION_STRIDE, "arm64-v8a").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(split_version_code(1, "mips").is_err());
+        //every target's abi has an offset
+        assert!(ANDROID_TARGETS.iter().all(|t| android_abi(t).and_then(abi_version_offset).is_some()));
+    }
+
+    #[test]
+    fn derives_the_base_version_code() {
+        assert_eq!(base_version_code(&manifest("[package]\nname = \"demo\"\nversion = \"1.2.3\"\n")), 10203);
+        assert_eq!(base_version_code(&manifest("[package]\nname = \"demo\"\nversion = \"12.0.45\"\n")), 120045);
+        //minor and patch are capped so they can't spill into the next part
+        assert_eq!(base_version_code(&manifest("[package]\nname = \"demo\"\nversion = \"1.150.7\"\n")), 19907);
+        let explicit = "[package]\nname = \"demo\"\nversion = \"1.2.3\"\n\n[package.metadata.android.manifest]\nversion_code = 42\n";
+        assert_eq!(base_version_code(&manifest(explicit)), 42);
+    }
+
+    #[test]
+    fn keeps_at_least_one_target() {
+        let mut options = AndroidBuildOptions::default();
+        assert_eq!(options.targets, ANDROID_TARGETS);
+        for target in &ANDROID_TARGETS[1..] {
+            options.set_target(target, false);
+        }
+        assert_eq!(options.targets, [ANDROID_TARGETS[0]]);
+        options.set_target(ANDROID_TARGETS[0], false);
+        assert_eq!(options.targets, [ANDROID_TARGETS[0]]);
+        //enabled targets keep the install order, not the order they were picked in
+        options.set_target(ANDROID_TARGETS[2], true);
+        options.set_target(ANDROID_TARGETS[1], true);
+        options.set_target(ANDROID_TARGETS[1], true);
+        assert_eq!(options.targets, &ANDROID_TARGETS[..3]);
+        assert_eq!(options.target_args(), ANDROID_TARGETS[..3].iter().map(|t| format!(" --target {}", t)).collect::<String>());
+        options.set_target("mips-linux-android", true);
+        assert_eq!(options.targets, &ANDROID_TARGETS[..3]);
+    }
+
+    #[test]
+    fn drops_only_signature_files() {
+        let dir = test_dir("apk-manifest");
+        let apk = dir.join("demo.apk");
+        let entries: [(&str, &[u8]); 9] = [
+            ("AndroidManifest.xml", b"old manifest"),
+            ("classes.dex", b"dex"),
+            ("lib/arm64-v8a/libmain.so", b"elf"),
+            ("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0"),
+            ("META-INF/CERT.SF", b"sf"),
+            ("META-INF/CERT.RSA", b"rsa"),
+            ("META-INF/ANDROIDD.EC", b"ec"),
+            ("META-INF/services/java.sql.Driver", b"org.example.Driver"),
+            ("META-INF/kotlinx_coroutines_core.version", b"1.7.3"),
+        ];
+        let mut writer = ZipWriter::new(File::create(&apk).unwrap());
+        for (name, content) in entries {
+            writer.start_file(name, SimpleFileOptions::default()).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let output = dir.join("demo.apk.unaligned");
+        let mut archive = ZipArchive::new(File::open(&apk).unwrap()).unwrap();
+        replace_manifest(&mut archive, b"new manifest", &output.display().to_string()).unwrap();
+        let mut rewritten = ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        let mut names: Vec<String> = rewritten.file_names().map(String::from).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "AndroidManifest.xml",
+                "META-INF/kotlinx_coroutines_core.version",
+                "META-INF/services/java.sql.Driver",
+                "classes.dex",
+                "lib/arm64-v8a/libmain.so"
+            ]
+        );
+        let mut manifest = String::new();
+        rewritten.by_name("AndroidManifest.xml").unwrap().read_to_string(&mut manifest).unwrap();
+        assert_eq!(manifest, "new manifest");
+        let mut driver = String::new();
+        rewritten.by_name("META-INF/services/java.sql.Driver").unwrap().read_to_string(&mut driver).unwrap();
+        assert_eq!(driver, "org.example.Driver");
+        assert!(is_signature_file("META-INF/cert.rsa"));
+        assert!(!is_signature_file("assets/CERT.RSA"));
+    }
+}
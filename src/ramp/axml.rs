@@ -0,0 +1,413 @@
+use super::manifest::ProjectManifest;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use zip::ZipArchive;
+
+//chunk types of android's compiled binary xml
+const RES_STRING_POOL_TYPE: u16 = 0x0001;
+const RES_XML_TYPE: u16 = 0x0003;
+const RES_XML_START_ELEMENT_TYPE: u16 = 0x0102;
+const RES_XML_END_ELEMENT_TYPE: u16 = 0x0103;
+const RES_XML_RESOURCE_MAP_TYPE: u16 = 0x0180;
+
+const UTF8_FLAG: u32 = 1 << 8;
+const NO_INDEX: u32 = 0xffff_ffff;
+
+//typed value kinds used by manifest attributes
+const TYPE_STRING: u8 = 0x03;
+const TYPE_INT_DEC: u8 = 0x10;
+const TYPE_INT_HEX: u8 = 0x11;
+const TYPE_INT_BOOLEAN: u8 = 0x12;
+
+//android attribute resource ids, used when aapt2 strips the attribute name strings
+const ANDROID_ATTRIBUTES: [(u32, &str); 5] = [
+    (0x0101_0003, "name"),
+    (0x0101_000e, "enabled"),
+    (0x0101_0010, "exported"),
+    (0x0101_021b, "versionCode"),
+    (0x0101_0202, "targetActivity"),
+];
+
+pub const ANDROID_NAMESPACE: &str = "http://schemas.android.com/apk/res/android";
+
+//activity cargo-apk declares for every native app
+pub const NATIVE_ACTIVITY: &str = "android.app.NativeActivity";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlAttribute {
+    pub namespace: Option<String>,
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct XmlElement {
+    pub name: String,
+    pub attributes: Vec<XmlAttribute>,
+    pub children: Vec<XmlElement>,
+}
+
+impl XmlElement {
+    //attribute value by local name, android: attributes are preferred over unqualified ones
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .filter(|a| a.name == name)
+            .max_by_key(|a| a.namespace.as_deref() == Some(ANDROID_NAMESPACE))
+            .map(|a| a.value.as_str())
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> + 'a {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid binary xml: {}", message))
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> io::Result<u16> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| invalid("truncated chunk"))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> io::Result<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| invalid("truncated chunk"))
+}
+
+//lengths in utf-8 pools take one byte, or two when the high bit is set
+fn utf8_pool_length(chunk: &[u8], offset: usize) -> io::Result<(usize, usize)> {
+    let first = *chunk.get(offset).ok_or_else(|| invalid("string offset out of range"))? as usize;
+    if first & 0x80 == 0 {
+        return Ok((first, 1));
+    }
+    let second = *chunk.get(offset + 1).ok_or_else(|| invalid("string offset out of range"))? as usize;
+    Ok((((first & 0x7f) << 8) | second, 2))
+}
+
+//decode a string pool chunk into its strings
+fn parse_string_pool(chunk: &[u8]) -> io::Result<Vec<String>> {
+    let count = u32_at(chunk, 8)? as usize;
+    let flags = u32_at(chunk, 16)?;
+    let strings_start = u32_at(chunk, 20)? as usize;
+    //the count is untrusted, its offsets have to fit in the chunk before anything is allocated for it
+    if count > chunk.len().saturating_sub(28) / 4 {
+        return Err(invalid("string pool count out of range"));
+    }
+    let mut strings = Vec::with_capacity(count);
+    for i in 0..count {
+        let mut offset = strings_start + u32_at(chunk, 28 + i * 4)? as usize;
+        if flags & UTF8_FLAG != 0 {
+            //utf-16 length first (unused), then the utf-8 byte length
+            let (_, used) = utf8_pool_length(chunk, offset)?;
+            let (length, used_bytes) = utf8_pool_length(chunk, offset + used)?;
+            offset += used + used_bytes;
+            let bytes = chunk.get(offset..offset + length).ok_or_else(|| invalid("string overruns pool"))?;
+            strings.push(String::from_utf8_lossy(bytes).to_string());
+        } else {
+            let mut length = u16_at(chunk, offset)? as usize;
+            offset += 2;
+            if length & 0x8000 != 0 {
+                length = ((length & 0x7fff) << 16) | u16_at(chunk, offset)? as usize;
+                offset += 2;
+            }
+            let units = (0..length).map(|j| u16_at(chunk, offset + j * 2)).collect::<io::Result<Vec<u16>>>()?;
+            strings.push(String::from_utf16_lossy(&units));
+        }
+    }
+    Ok(strings)
+}
+
+//prefer the framework name for the attribute's resource id when there is one
+fn attribute_name(strings: &[String], resource_ids: &[u32], name_index: u32) -> String {
+    resource_ids
+        .get(name_index as usize)
+        .and_then(|id| ANDROID_ATTRIBUTES.iter().find(|(known, _)| known == id))
+        .map(|(_, known)| known.to_string())
+        .or_else(|| strings.get(name_index as usize).cloned())
+        .unwrap_or_default()
+}
+
+//parse compiled binary xml (an apk's AndroidManifest.xml) into an element tree
+pub fn parse_axml(bytes: &[u8]) -> io::Result<XmlElement> {
+    if u16_at(bytes, 0)? != RES_XML_TYPE {
+        return Err(invalid("missing xml header"));
+    }
+    let mut strings: Vec<String> = Vec::new();
+    let mut resource_ids: Vec<u32> = Vec::new();
+    let mut stack: Vec<XmlElement> = Vec::new();
+    let mut root = None;
+    let string = |strings: &[String], index: u32| -> Option<String> {
+        if index == NO_INDEX { None } else { strings.get(index as usize).cloned() }
+    };
+
+    let mut offset = u16_at(bytes, 2)? as usize;
+    while offset + 8 <= bytes.len() {
+        let chunk_type = u16_at(bytes, offset)?;
+        let header_size = u16_at(bytes, offset + 2)? as usize;
+        let size = u32_at(bytes, offset + 4)? as usize;
+        if size < 8 || offset + size > bytes.len() {
+            return Err(invalid("chunk size out of range"));
+        }
+        let chunk = &bytes[offset..offset + size];
+        match chunk_type {
+            RES_STRING_POOL_TYPE => strings = parse_string_pool(chunk)?,
+            RES_XML_RESOURCE_MAP_TYPE => {
+                resource_ids = (header_size..size).step_by(4).map(|o| u32_at(chunk, o)).collect::<io::Result<_>>()?;
+            }
+            RES_XML_START_ELEMENT_TYPE => {
+                let ext = header_size;
+                let name = string(&strings, u32_at(chunk, ext + 4)?).unwrap_or_default();
+                let attribute_start = u16_at(chunk, ext + 8)? as usize;
+                let attribute_size = u16_at(chunk, ext + 10)? as usize;
+                let attribute_count = u16_at(chunk, ext + 12)? as usize;
+                if ext + attribute_start + attribute_count * attribute_size > chunk.len() {
+                    return Err(invalid("attributes overrun the element"));
+                }
+                let mut attributes = Vec::with_capacity(attribute_count);
+                for i in 0..attribute_count {
+                    let at = ext + attribute_start + i * attribute_size;
+                    let name_index = u32_at(chunk, at + 4)?;
+                    let raw_value = u32_at(chunk, at + 8)?;
+                    let data_type = *chunk.get(at + 15).ok_or_else(|| invalid("truncated attribute"))?;
+                    let data = u32_at(chunk, at + 16)?;
+                    let attribute_name = attribute_name(&strings, &resource_ids, name_index);
+                    let value = match data_type {
+                        TYPE_STRING => string(&strings, data).unwrap_or_default(),
+                        TYPE_INT_BOOLEAN => (data != 0).to_string(),
+                        TYPE_INT_DEC => (data as i32).to_string(),
+                        TYPE_INT_HEX => format!("0x{:08x}", data),
+                        _ => string(&strings, raw_value).unwrap_or_else(|| format!("@0x{:08x}", data)),
+                    };
+                    attributes.push(XmlAttribute {
+                        namespace: string(&strings, u32_at(chunk, at)?),
+                        name: attribute_name,
+                        value,
+                    });
+                }
+                stack.push(XmlElement { name, attributes, children: Vec::new() });
+            }
+            RES_XML_END_ELEMENT_TYPE => {
+                let element = stack.pop().ok_or_else(|| invalid("unbalanced end element"))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => root = Some(element),
+                }
+            }
+            //namespaces, cdata and unknown chunks carry nothing launching needs
+            _ => {}
+        }
+        offset += size;
+    }
+    root.ok_or_else(|| invalid("no root element"))
+}
+
+//rewrite android:versionCode on the root manifest element in place, the value is a fixed size int so nothing moves
+pub fn set_version_code(bytes: &[u8], version_code: u32) -> io::Result<Vec<u8>> {
+    if u16_at(bytes, 0)? != RES_XML_TYPE {
+        return Err(invalid("missing xml header"));
+    }
+    let mut patched = bytes.to_vec();
+    let mut strings: Vec<String> = Vec::new();
+    let mut resource_ids: Vec<u32> = Vec::new();
+    let mut offset = u16_at(bytes, 2)? as usize;
+    while offset + 8 <= bytes.len() {
+        let chunk_type = u16_at(bytes, offset)?;
+        let header_size = u16_at(bytes, offset + 2)? as usize;
+        let size = u32_at(bytes, offset + 4)? as usize;
+        if size < 8 || offset + size > bytes.len() {
+            return Err(invalid("chunk size out of range"));
+        }
+        let chunk = &bytes[offset..offset + size];
+        match chunk_type {
+            RES_STRING_POOL_TYPE => strings = parse_string_pool(chunk)?,
+            RES_XML_RESOURCE_MAP_TYPE => {
+                resource_ids = (header_size..size).step_by(4).map(|o| u32_at(chunk, o)).collect::<io::Result<_>>()?;
+            }
+            RES_XML_START_ELEMENT_TYPE => {
+                let ext = header_size;
+                let attribute_start = u16_at(chunk, ext + 8)? as usize;
+                let attribute_size = u16_at(chunk, ext + 10)? as usize;
+                let attribute_count = u16_at(chunk, ext + 12)? as usize;
+                for i in 0..attribute_count {
+                    let at = ext + attribute_start + i * attribute_size;
+                    if attribute_name(&strings, &resource_ids, u32_at(chunk, at + 4)?) != "versionCode" {
+                        continue;
+                    }
+                    let data_type = *chunk.get(at + 15).ok_or_else(|| invalid("truncated attribute"))?;
+                    if data_type != TYPE_INT_DEC && data_type != TYPE_INT_HEX {
+                        return Err(invalid("versionCode is not an integer"));
+                    }
+                    u32_at(chunk, at + 16)?;
+                    let data = offset + at + 16;
+                    patched[data..data + 4].copy_from_slice(&version_code.to_le_bytes());
+                    return Ok(patched);
+                }
+                //only the root manifest element carries the version code
+                return Err(io::Error::new(io::ErrorKind::NotFound, "AndroidManifest.xml has no versionCode"));
+            }
+            _ => {}
+        }
+        offset += size;
+    }
+    Err(invalid("no root element"))
+}
+
+//the component `am start -n` needs to open an app
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaunchTarget {
+    pub package: String,
+    pub activity: String,
+}
+
+impl LaunchTarget {
+    pub fn component(&self) -> String {
+        format!("{}/{}", self.package, self.activity)
+    }
+}
+
+//expand a manifest class name relative to the package
+fn qualify(package: &str, name: &str) -> String {
+    if name.starts_with('.') {
+        format!("{}{}", package, name)
+    } else if !name.contains('.') {
+        format!("{}.{}", package, name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn is_launcher(activity: &XmlElement) -> bool {
+    activity.children_named("intent-filter").any(|filter| {
+        filter.children_named("action").any(|a| a.attribute("name") == Some("android.intent.action.MAIN"))
+            && filter
+                .children_named("category")
+                .any(|c| c.attribute("name") == Some("android.intent.category.LAUNCHER"))
+    })
+}
+
+//find the enabled activity or activity-alias with a MAIN/LAUNCHER intent filter
+pub fn launch_target(manifest: &XmlElement) -> Option<LaunchTarget> {
+    let package = manifest.attribute("package")?.to_string();
+    let application = manifest.children_named("application").next()?;
+    application
+        .children
+        .iter()
+        .filter(|c| c.name == "activity" || c.name == "activity-alias")
+        .filter(|c| c.attribute("enabled") != Some("false"))
+        .find(|c| is_launcher(c))
+        .and_then(|c| c.attribute("name"))
+        .map(|name| LaunchTarget { activity: qualify(&package, name), package })
+}
+
+//read the compiled manifest out of an apk and find its launcher activity
+pub fn launch_target_from_apk(apk_path: &Path) -> io::Result<LaunchTarget> {
+    let mut archive = ZipArchive::new(File::open(apk_path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Failed to read {}: {}", apk_path.display(), e)))?;
+    let mut entry = archive
+        .by_name("AndroidManifest.xml")
+        .map_err(|_| io::Error::new(io::ErrorKind::NotFound, "apk has no AndroidManifest.xml"))?;
+    let mut bytes = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut bytes)?;
+    let manifest = parse_axml(&bytes)?;
+    launch_target(&manifest)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no MAIN/LAUNCHER activity in AndroidManifest.xml"))
+}
+
+//fallback when the apk can't be read: cargo-apk always launches through NativeActivity
+pub fn launch_target_from_manifest(manifest: &ProjectManifest) -> io::Result<LaunchTarget> {
+    Ok(LaunchTarget { package: manifest.android_package()?.to_string(), activity: NATIVE_ACTIVITY.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ramp::helper::test_dir;
+    use std::fs;
+
+    //aapt2 style: utf-8 pool, attribute names stripped down to the resource map, a disabled and an enabled launcher alias
+    const ALIAS_UTF8: &[u8] = include_bytes!("../../tests/fixtures/android/alias_utf8.axml");
+    //aapt style: utf-16 pool with attribute names spelled out and NativeActivity as the launcher
+    const NATIVE_UTF16: &[u8] = include_bytes!("../../tests/fixtures/android/native_utf16.axml");
+    const ALIAS_APK: &[u8] = include_bytes!("../../tests/fixtures/android/alias.apk");
+
+    #[test]
+    fn parses_a_utf8_pool_with_resource_id_attributes() {
+        let manifest = parse_axml(ALIAS_UTF8).unwrap();
+        assert_eq!(manifest.name, "manifest");
+        assert_eq!(manifest.attribute("package"), Some("com.example.demo"));
+        assert_eq!(manifest.attribute("versionCode"), Some("3"));
+        let application = manifest.children_named("application").next().unwrap();
+        let names: Vec<&str> = application.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["activity", "activity-alias", "activity-alias"]);
+        let activity = &application.children[0];
+        assert_eq!(activity.attribute("name"), Some(NATIVE_ACTIVITY));
+        assert_eq!(activity.attribute("exported"), Some("true"));
+        assert_eq!(activity.attributes[0].namespace.as_deref(), Some(ANDROID_NAMESPACE));
+        assert_eq!(application.children[1].attribute("enabled"), Some("false"));
+        assert_eq!(application.children[2].attribute("targetActivity"), Some(NATIVE_ACTIVITY));
+    }
+
+    #[test]
+    fn parses_a_utf16_pool() {
+        let manifest = parse_axml(NATIVE_UTF16).unwrap();
+        assert_eq!(manifest.attribute("package"), Some("org.ramp.native"));
+        assert_eq!(manifest.attribute("versionCode"), Some("0x00000010"));
+        let target = launch_target(&manifest).unwrap();
+        assert_eq!(target.component(), "org.ramp.native/android.app.NativeActivity");
+    }
+
+    #[test]
+    fn resolves_the_enabled_launcher_alias() {
+        let target = launch_target(&parse_axml(ALIAS_UTF8).unwrap()).unwrap();
+        assert_eq!(target.package, "com.example.demo");
+        assert_eq!(target.activity, "com.example.demo.Launcher");
+    }
+
+    #[test]
+    fn reads_the_launch_target_from_an_apk() {
+        let apk = test_dir("axml").join("alias.apk");
+        fs::write(&apk, ALIAS_APK).unwrap();
+        assert_eq!(launch_target_from_apk(&apk).unwrap().component(), "com.example.demo/com.example.demo.Launcher");
+    }
+
+    #[test]
+    fn rejects_truncated_xml() {
+        assert!(parse_axml(&ALIAS_UTF8[..ALIAS_UTF8.len() / 2]).is_err());
+        assert!(parse_axml(&NATIVE_UTF16[8..]).is_err());
+    }
+
+    #[test]
+    fn sets_the_version_code_in_place() {
+        let patched = set_version_code(ALIAS_UTF8, 20_000_003).unwrap();
+        assert_eq!(patched.len(), ALIAS_UTF8.len());
+        let manifest = parse_axml(&patched).unwrap();
+        assert_eq!(manifest.attribute("versionCode"), Some("20000003"));
+        assert_eq!(launch_target(&manifest), launch_target(&parse_axml(ALIAS_UTF8).unwrap()));
+        let patched = set_version_code(NATIVE_UTF16, 7).unwrap();
+        assert_eq!(parse_axml(&patched).unwrap().attribute("versionCode"), Some("0x00000007"));
+    }
+
+    #[test]
+    fn rejects_counts_larger_than_their_chunk() {
+        //the string pool is the first chunk after the 8 byte xml header
+        let mut huge_pool = ALIAS_UTF8.to_vec();
+        huge_pool[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(parse_axml(&huge_pool).unwrap_err().to_string(), "invalid binary xml: string pool count out of range");
+
+        let mut offset = 8;
+        while u16_at(ALIAS_UTF8, offset).unwrap() != RES_XML_START_ELEMENT_TYPE {
+            offset += u32_at(ALIAS_UTF8, offset + 4).unwrap() as usize;
+        }
+        let count_at = offset + u16_at(ALIAS_UTF8, offset + 2).unwrap() as usize + 12;
+        let mut huge_element = ALIAS_UTF8.to_vec();
+        huge_element[count_at..count_at + 2].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert_eq!(parse_axml(&huge_element).unwrap_err().to_string(), "invalid binary xml: attributes overrun the element");
+    }
+}
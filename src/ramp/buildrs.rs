@@ -0,0 +1,447 @@
+use super::manifest::declares_dependency;
+use super::pwa::content_hash;
+use super::session::Session;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+//file ramp owns and regenerates, the project's build.rs only includes it
+pub const RAMP_BUILD_FILE: &str = "ramp_build.rs";
+
+pub const MARKER_START: &str = "// >>> ramp build (managed by ramp, do not edit between these markers)";
+pub const MARKER_END: &str = "// <<< ramp build";
+
+const HASH_PREFIX: &str = "// ramp-hash: ";
+
+//the call ramp inserts at the top of the build script's main
+pub const RAMP_BUILD_CALL: &str = "ramp_build();";
+
+#[derive(Debug, PartialEq)]
+pub enum BuildScriptStatus {
+    //no build.rs existed, a new one was written
+    Created,
+    //ramp's include was merged into an existing build.rs
+    Merged,
+    //build.rs already includes ramp_build.rs correctly
+    UpToDate,
+}
+
+//the generated module body, without its hash header
+fn ramp_build_body(ico_path: &str) -> String {
+    format!(
+        r#"// generated by ramp, edits to this file are detected and will not be overwritten
+fn ramp_build() {{
+    //embed the windows icon and version info from app.rc
+    if std::env::var("CARGO_CFG_TARGET_OS").map(|os| os == "windows").unwrap_or(false)
+        && std::path::Path::new("{}").exists()
+    {{
+        let _ = embed_resource::compile("app.rc", embed_resource::NONE).manifest_optional();
+    }}
+}}
+"#,
+        ico_path.replace('\\', "/").replace('"', "\\\"")
+    )
+}
+
+//the generated module with a hash of its body so later runs can tell if the user edited it
+pub fn ramp_build_module(ico_path: &str) -> String {
+    let body = ramp_build_body(ico_path);
+    format!("{}{}\n{}", HASH_PREFIX, content_hash(body.as_bytes()), body)
+}
+
+//true when the module on disk no longer matches the hash ramp recorded when generating it
+pub fn module_diverged(existing: &str) -> bool {
+    let Some(first_line) = existing.lines().next() else { return true };
+    let Some(recorded) = first_line.strip_prefix(HASH_PREFIX) else { return true };
+    let body = existing.split_once('\n').map(|(_, body)| body).unwrap_or("");
+    content_hash(body.as_bytes()) != recorded.trim()
+}
+
+fn marker_block() -> String {
+    format!("{}\ninclude!(\"{}\");\n{}\n", MARKER_START, RAMP_BUILD_FILE, MARKER_END)
+}
+
+//a fresh build script for projects without one
+pub fn default_build_script() -> String {
+    format!("{}\nfn main() {{\n    {}\n}}\n", marker_block(), RAMP_BUILD_CALL)
+}
+
+//build scripts written by older ramp versions, which are safe to replace outright
+fn is_legacy_generated(existing: &str) -> bool {
+    let significant: Vec<&str> = existing.lines().map(|l| l.trim()).filter(|l| !l.is_empty()).collect();
+    significant.first() == Some(&"use std::io;")
+        && significant.get(1) == Some(&"fn main() {")
+        && existing.contains("embed_resource::compile(\"app.rc\", embed_resource::NONE)")
+        && significant.len() == 7
+}
+
+fn is_ident(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+//where the comment, string or char literal starting at i ends, None when code starts there
+fn skip_non_code(script: &str, i: usize) -> Option<usize> {
+    let bytes = script.as_bytes();
+    let rest = &bytes[i..];
+    if rest.starts_with(b"//") {
+        return Some(i + script[i..].find('\n').unwrap_or(script.len() - i));
+    }
+    if rest.starts_with(b"/*") {
+        //block comments nest in rust
+        let (mut depth, mut j) = (0, i);
+        while j < bytes.len() {
+            if bytes[j..].starts_with(b"/*") {
+                depth += 1;
+                j += 2;
+            } else if bytes[j..].starts_with(b"*/") {
+                depth -= 1;
+                j += 2;
+                if depth == 0 {
+                    return Some(j);
+                }
+            } else {
+                j += 1;
+            }
+        }
+        return Some(bytes.len());
+    }
+    //raw strings, r"..." and r#"..."#, including br"..."
+    if rest[0] == b'r' && (i == 0 || !is_ident(bytes[i - 1]) || (bytes[i - 1] == b'b' && (i == 1 || !is_ident(bytes[i - 2])))) {
+        let hashes = rest[1..].iter().take_while(|b| **b == b'#').count();
+        if rest.get(1 + hashes) == Some(&b'"') {
+            let closing = format!("\"{}", "#".repeat(hashes));
+            let body = i + 2 + hashes;
+            return Some(script[body..].find(&closing).map(|end| body + end + closing.len()).unwrap_or(bytes.len()));
+        }
+        return None;
+    }
+    if rest[0] == b'"' {
+        let mut j = i + 1;
+        while j < bytes.len() {
+            match bytes[j] {
+                b'\\' => j += 2,
+                b'"' => return Some(j + 1),
+                _ => j += 1,
+            }
+        }
+        return Some(bytes.len());
+    }
+    //char literals, anything else after a quote is a lifetime
+    if rest[0] == b'\'' {
+        if rest.get(1) == Some(&b'\\') {
+            return Some(script[i + 2..].find('\'').map(|end| i + 3 + end).unwrap_or(bytes.len()));
+        }
+        let c = script[i + 1..].chars().next()?;
+        if script[i + 1 + c.len_utf8()..].starts_with('\'') {
+            return Some(i + 2 + c.len_utf8());
+        }
+    }
+    None
+}
+
+//for each byte of the script, whether it is code rather than a comment, string or char literal
+fn code_mask(script: &str) -> Vec<bool> {
+    let mut mask = vec![true; script.len()];
+    let mut i = 0;
+    while i < script.len() {
+        match skip_non_code(script, i) {
+            Some(end) => {
+                mask[i..end].iter_mut().for_each(|m| *m = false);
+                i = end;
+            }
+            None => i += 1,
+        }
+    }
+    mask
+}
+
+//the position of the opening brace of the build script's fn main, ignoring comments and strings
+fn main_body_start(script: &str) -> Option<usize> {
+    let mask = code_mask(script);
+    let bytes = script.as_bytes();
+    for (i, _) in script.match_indices("fn") {
+        if !mask[i] || (i > 0 && is_ident(bytes[i - 1])) {
+            continue;
+        }
+        let rest = &script[i + 2..];
+        let name = rest.trim_start();
+        if name.len() == rest.len() {
+            continue;
+        }
+        let Some(after) = name.strip_prefix("main") else { continue };
+        if !after.trim_start().starts_with('(') {
+            continue;
+        }
+        let from = script.len() - after.len();
+        return (from..script.len()).find(|&j| mask[j] && bytes[j] == b'{');
+    }
+    None
+}
+
+//insert the ramp_build() call as the first statement of fn main, returns None if main can't be found
+fn insert_call(script: &str) -> Option<String> {
+    let brace = main_body_start(script)?;
+    let body = &script[brace + 1..];
+    let first_line = body.split('\n').next().unwrap_or("");
+    //main's body is on the same line as the brace, eg fn main() { run(); }
+    if !first_line.trim().is_empty() || !body.contains('\n') {
+        let separator = if body.starts_with(char::is_whitespace) { "" } else { " " };
+        return Some(format!("{} {}{}{}", &script[..=brace], RAMP_BUILD_CALL, separator, body));
+    }
+    let line_end = brace + 1 + first_line.len();
+    let indent = script[line_end..]
+        .lines()
+        .nth(1)
+        .map(|l| &l[..l.len() - l.trim_start().len()])
+        .filter(|i| !i.is_empty())
+        .unwrap_or("    ");
+    Some(format!("{}\n{}{}{}", &script[..line_end], indent, RAMP_BUILD_CALL, &script[line_end..]))
+}
+
+//merge ramp's include into a build script, never touching user code outside the marker block
+//returns the new script (if it changed), its status and any warnings for the user
+pub fn merge_build_script(existing: Option<&str>) -> (Option<String>, BuildScriptStatus, Vec<String>) {
+    let mut warnings = Vec::new();
+    let Some(existing) = existing else {
+        return (Some(default_build_script()), BuildScriptStatus::Created, warnings);
+    };
+    if is_legacy_generated(existing) {
+        warnings.push("replaced a build.rs generated by an older version of ramp".to_string());
+        return (Some(default_build_script()), BuildScriptStatus::Merged, warnings);
+    }
+
+    let block = marker_block();
+    match (existing.find(MARKER_START), existing.find(MARKER_END)) {
+        (Some(start), Some(end)) if start < end => {
+            let end = end + MARKER_END.len();
+            let end = if existing[end..].starts_with('\n') { end + 1 } else { end };
+            let mut status = BuildScriptStatus::UpToDate;
+            let mut script = existing.to_string();
+            if existing[start..end] != block {
+                warnings.push("the ramp block in build.rs was edited, restoring it (move custom code outside the markers)".to_string());
+                script = format!("{}{}{}", &existing[..start], block, &existing[end..]);
+                status = BuildScriptStatus::Merged;
+            }
+            if !script.contains(RAMP_BUILD_CALL) {
+                warnings.push(format!(
+                    "build.rs includes {} but main no longer calls {}, windows icons and version info will not be embedded",
+                    RAMP_BUILD_FILE, RAMP_BUILD_CALL
+                ));
+            }
+            let changed = if status == BuildScriptStatus::Merged { Some(script) } else { None };
+            (changed, status, warnings)
+        }
+        (Some(_), _) | (_, Some(_)) => {
+            warnings.push("build.rs contains an incomplete ramp block, leaving it untouched".to_string());
+            (None, BuildScriptStatus::UpToDate, warnings)
+        }
+        (None, None) => {
+            if existing.contains("embed_resource::compile(\"app.rc\"") {
+                warnings.push("build.rs already compiles app.rc itself, remove that call to avoid embedding the resources twice".to_string());
+            }
+            let script = match insert_call(existing) {
+                Some(script) => script,
+                None => {
+                    warnings.push(format!("could not find fn main in build.rs, call {} from your build script manually", RAMP_BUILD_CALL));
+                    existing.to_string()
+                }
+            };
+            let separator = if script.ends_with('\n') { "\n" } else { "\n\n" };
+            (Some(format!("{}{}{}", script, separator, block)), BuildScriptStatus::Merged, warnings)
+        }
+    }
+}
+
+//true if Cargo.toml declares embed-resource as a build dependency
+fn has_embed_resource(cargo_toml: &str) -> io::Result<bool> {
+    declares_dependency(cargo_toml, "build-dependencies", "embed-resource")
+}
+
+//write ramp_build.rs and wire it into the project's build.rs without clobbering user build logic
+pub fn integrate_build_script(session: &Session) -> io::Result<BuildScriptStatus> {
+    let project_path = format!(
+        "{}/{}",
+        session.projects_path.as_ref().unwrap(),
+        session.current_project.as_ref().unwrap()
+    );
+    let ico_path = format!("{}/assets/resources/icons/windows_icon.ico", &project_path);
+
+    //regenerate ramp's module unless the user has edited it
+    let module_path = format!("{}/{}", &project_path, RAMP_BUILD_FILE);
+    let module = ramp_build_module(&ico_path);
+    match fs::read_to_string(&module_path) {
+        Ok(existing) if existing == module => {}
+        Ok(existing) if module_diverged(&existing) => println!(
+            "WARNING: {} was edited by hand, leaving it in place. Delete it to let ramp regenerate it.",
+            &module_path
+        ),
+        _ => {
+            fs::write(&module_path, &module)?;
+            println!("wrote {}", &module_path);
+        }
+    }
+
+    let build_path = format!("{}/build.rs", &project_path);
+    let existing = if Path::new(&build_path).exists() { Some(fs::read_to_string(&build_path)?) } else { None };
+    let (script, status, warnings) = merge_build_script(existing.as_deref());
+    for warning in &warnings {
+        println!("WARNING: {}", warning);
+    }
+    if let Some(script) = script {
+        fs::write(&build_path, script)?;
+        println!("updated build.rs at {} ({:?})", &build_path, status);
+    }
+
+    let cargo_toml = fs::read_to_string(format!("{}/Cargo.toml", &project_path))?;
+    if !has_embed_resource(&cargo_toml)? {
+        println!("WARNING: add embed-resource to [build-dependencies] in Cargo.toml so build.rs can embed app.rc");
+    }
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ramp::helper::test_session;
+
+    //the build.rs older ramp versions wrote over the project's own
+    const LEGACY: &str = include_str!("../../tests/fixtures/buildrs/legacy_build.rs");
+    const CUSTOM: &str = include_str!("../../tests/fixtures/buildrs/custom_build.rs");
+
+    fn merged_custom() -> String {
+        merge_build_script(Some(CUSTOM)).0.unwrap()
+    }
+
+    #[test]
+    fn inserts_at_the_top_of_a_multi_line_main() {
+        let script = "use std::env;\n\nfn main() {\n  println!(\"hi\");\n}\n";
+        assert_eq!(
+            insert_call(script).unwrap(),
+            "use std::env;\n\nfn main() {\n  ramp_build();\n  println!(\"hi\");\n}\n"
+        );
+    }
+
+    #[test]
+    fn inserts_inside_a_one_line_main() {
+        assert_eq!(insert_call("fn main() { run(); }\n").unwrap(), "fn main() { ramp_build(); run(); }\n");
+        assert_eq!(insert_call("fn main(){}").unwrap(), "fn main(){ ramp_build(); }");
+    }
+
+    #[test]
+    fn skips_main_in_comments_and_strings() {
+        let script = concat!(
+            "// fn main() { old }\n",
+            "/* fn main() { /* nested */ } */\n",
+            "const A: &str = \"fn main() {\";\n",
+            "const B: &str = r#\"fn main() { \"quoted\" }\"#;\n",
+            "const C: char = '{';\n",
+            "fn helper<'a>(s: &'a str) -> &'a str { s }\n",
+            "fn main() -> Result<(), String> {\n    helper(\"x\");\n    Ok(())\n}\n",
+        );
+        let merged = insert_call(script).unwrap();
+        assert!(merged.ends_with("fn main() -> Result<(), String> {\n    ramp_build();\n    helper(\"x\");\n    Ok(())\n}\n"));
+        assert_eq!(merged.matches(RAMP_BUILD_CALL).count(), 1);
+    }
+
+    #[test]
+    fn ignores_functions_named_like_main() {
+        assert_eq!(insert_call("fn main_helper() {}\n"), None);
+        assert_eq!(insert_call("fn not_main() {}\n"), None);
+    }
+
+    #[test]
+    fn merge_appends_the_marker_block() {
+        let (script, status, warnings) = merge_build_script(Some("fn main() { run(); }\n"));
+        assert_eq!(status, BuildScriptStatus::Merged);
+        assert!(warnings.is_empty());
+        assert_eq!(script.unwrap(), format!("fn main() {{ ramp_build(); run(); }}\n\n{}", marker_block()));
+    }
+
+    #[test]
+    fn leaves_a_merged_script_alone() {
+        let merged = merged_custom();
+        assert!(merged.starts_with("use std::env;\n\nfn main() {\n    ramp_build();\n    println!"));
+        assert_eq!(merge_build_script(Some(&merged)), (None, BuildScriptStatus::UpToDate, Vec::new()));
+        assert_eq!(merge_build_script(None), (Some(default_build_script()), BuildScriptStatus::Created, Vec::new()));
+    }
+
+    #[test]
+    fn restores_an_edited_marker_block() {
+        let merged = merged_custom();
+        let edited = merged.replace(&format!("include!(\"{}\");\n", RAMP_BUILD_FILE), "include!(\"my_build.rs\");\nfn extra() {}\n");
+        let (script, status, warnings) = merge_build_script(Some(&edited));
+        assert_eq!(status, BuildScriptStatus::Merged);
+        assert_eq!(script.unwrap(), merged);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("the ramp block in build.rs was edited"));
+    }
+
+    #[test]
+    fn warns_when_main_stops_calling_ramp_build() {
+        let edited = merged_custom().replace("    ramp_build();\n", "");
+        let (script, status, warnings) = merge_build_script(Some(&edited));
+        assert_eq!((script, status), (None, BuildScriptStatus::UpToDate));
+        assert!(warnings[0].contains("main no longer calls ramp_build();"));
+    }
+
+    #[test]
+    fn leaves_an_incomplete_block_untouched() {
+        let incomplete = format!("{}\n{}\ninclude!(\"ramp_build.rs\");\n", CUSTOM, MARKER_START);
+        let expected = (None, BuildScriptStatus::UpToDate, vec!["build.rs contains an incomplete ramp block, leaving it untouched".to_string()]);
+        assert_eq!(merge_build_script(Some(&incomplete)), expected);
+        //an end marker before the start marker is just as broken
+        let reversed = format!("{}\n{}\n{}\n", MARKER_END, CUSTOM, MARKER_START);
+        assert_eq!(merge_build_script(Some(&reversed)), expected);
+    }
+
+    #[test]
+    fn replaces_a_legacy_script() {
+        let (script, status, warnings) = merge_build_script(Some(LEGACY));
+        assert_eq!((script.unwrap(), status), (default_build_script(), BuildScriptStatus::Merged));
+        assert_eq!(warnings, ["replaced a build.rs generated by an older version of ramp"]);
+        //a legacy script the user added to is merged rather than replaced
+        let extended = LEGACY.replace("    }\n    \n", "    println!(\"cargo:rerun-if-changed=app.rc\");\n    }\n");
+        let (script, status, warnings) = merge_build_script(Some(&extended));
+        assert_eq!(status, BuildScriptStatus::Merged);
+        assert!(script.unwrap().contains("fn main() {\n            ramp_build();\n"));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("build.rs already compiles app.rc itself"));
+    }
+
+    #[test]
+    fn detects_a_hand_edited_module() {
+        let module = ramp_build_module("C:\\demo\\windows_icon.ico");
+        assert!(module.contains("Path::new(\"C:/demo/windows_icon.ico\")"));
+        assert!(!module_diverged(&module));
+        assert!(module_diverged(&module.replace("manifest_optional()", "manifest_required()")));
+        //files without ramp's hash line were never generated by ramp
+        assert!(module_diverged(module.split_once('\n').unwrap().1));
+        assert!(module_diverged(""));
+    }
+
+    #[test]
+    fn keeps_a_hand_edited_module() {
+        let mut session = test_session("buildrs");
+        let projects = std::path::PathBuf::from(&session.home);
+        let project = projects.join("demo");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(project.join("Cargo.toml"), "[package]\nname = \"demo\"\n\n[build-dependencies]\nembed-resource = \"2\"\n").unwrap();
+        session.projects_path = Some(projects.display().to_string());
+        session.current_project = Some("demo".to_string());
+
+        assert_eq!(integrate_build_script(&session).unwrap(), BuildScriptStatus::Created);
+        let module_path = project.join(RAMP_BUILD_FILE);
+        let generated = fs::read_to_string(&module_path).unwrap();
+        assert!(!module_diverged(&generated));
+        assert_eq!(fs::read_to_string(project.join("build.rs")).unwrap(), default_build_script());
+
+        let edited = generated.replace("manifest_optional()", "manifest_required()");
+        fs::write(&module_path, &edited).unwrap();
+        assert_eq!(integrate_build_script(&session).unwrap(), BuildScriptStatus::UpToDate);
+        assert_eq!(fs::read_to_string(&module_path).unwrap(), edited);
+        //deleting it lets ramp write it again
+        fs::remove_file(&module_path).unwrap();
+        integrate_build_script(&session).unwrap();
+        assert_eq!(fs::read_to_string(&module_path).unwrap(), generated);
+    }
+}
@@ -0,0 +1,640 @@
+use super::session::Session;
+use chrono::Local;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+
+//number of functions and data segments listed in a bundle report
+const REPORT_TOP_ENTRIES: usize = 15;
+
+//files in dist that are worth precompressing
+const COMPRESSIBLE: [&str; 6] = ["wasm", "js", "html", "css", "json", "webmanifest"];
+
+//features the stable wasm32-unknown-unknown target emits, wasm-opt rejects them unless enabled
+const WASM_OPT_FEATURES: [&str; 6] = [
+    "--enable-bulk-memory",
+    "--enable-multivalue",
+    "--enable-mutable-globals",
+    "--enable-nontrapping-float-to-int",
+    "--enable-reference-types",
+    "--enable-sign-ext",
+];
+
+//ramp config key for the wasm-opt level of release builds, "none" skips wasm-opt
+pub const WASM_OPT_LEVEL_KEY: &str = "wasm_opt_level";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptLevel {
+    O1,
+    O2,
+    O3,
+    O4,
+    Os,
+    Oz,
+}
+
+impl OptLevel {
+    pub const ALL: [OptLevel; 6] = [OptLevel::O1, OptLevel::O2, OptLevel::O3, OptLevel::O4, OptLevel::Os, OptLevel::Oz];
+
+    //"Oz" and "-Oz" alike
+    pub fn parse(level: &str) -> Option<Self> {
+        OptLevel::ALL.into_iter().find(|l| l.as_str() == level.trim_start_matches('-'))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        &self.flag()[1..]
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            OptLevel::O1 => "quick, light optimisation",
+            OptLevel::O2 => "balanced speed and size",
+            OptLevel::O3 => "aggressive, for speed",
+            OptLevel::O4 => "O3 with extra flattening, slowest to run",
+            OptLevel::Os => "small size",
+            OptLevel::Oz => "smallest size (default)",
+        }
+    }
+
+    //the level release builds use, Oz unless another one or "none" is set in the ramp config
+    pub fn from_config(session: &Session) -> io::Result<Option<Self>> {
+        match session.get_config(WASM_OPT_LEVEL_KEY)?.filter(|l| !l.is_empty()) {
+            None => Ok(Some(OptLevel::Oz)),
+            Some(level) if level == "none" => Ok(None),
+            Some(level) => OptLevel::parse(&level)
+                .map(Some)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("invalid {}: {}", WASM_OPT_LEVEL_KEY, level))),
+        }
+    }
+
+    pub fn flag(&self) -> &'static str {
+        match self {
+            OptLevel::O1 => "-O1",
+            OptLevel::O2 => "-O2",
+            OptLevel::O3 => "-O3",
+            OptLevel::O4 => "-O4",
+            OptLevel::Os => "-Os",
+            OptLevel::Oz => "-Oz",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WasmOptimization {
+    pub level: Option<OptLevel>,
+    pub strip_custom_sections: bool,
+    pub gzip: bool,
+    pub brotli: bool,
+}
+
+impl WasmOptimization {
+    //debug builds are left untouched for fast iteration, release builds get the full treatment at the chosen level
+    pub fn for_build(release: bool, level: Option<OptLevel>) -> Self {
+        WasmOptimization {
+            level: if release { level } else { None },
+            strip_custom_sections: release,
+            gzip: release,
+            brotli: release,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileSize {
+    pub name: String,
+    pub raw: u64,
+    pub gzip: u64,
+    pub brotli: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BundleReport {
+    pub timestamp: String,
+    pub profile: String,
+    pub files: Vec<FileSize>,
+    //(name, size in bytes), largest first
+    pub functions: Vec<(String, u64)>,
+    pub data_segments: Vec<(String, u64)>,
+    pub custom_sections: Vec<(String, u64)>,
+}
+
+impl BundleReport {
+    pub fn total(&self) -> FileSize {
+        FileSize {
+            name: "total".to_string(),
+            raw: self.files.iter().map(|f| f.raw).sum(),
+            gzip: self.files.iter().map(|f| f.gzip).sum(),
+            brotli: self.files.iter().map(|f| f.brotli).sum(),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "wasm bundle report {} ({})", self.timestamp, self.profile);
+        let _ = writeln!(text, "\n{:<32} {:>12} {:>12} {:>12}", "file", "raw", "gzip", "brotli");
+        for file in self.files.iter().chain(std::iter::once(&self.total())) {
+            let _ = writeln!(text, "{:<32} {:>12} {:>12} {:>12}", file.name, file.raw, file.gzip, file.brotli);
+        }
+        for (heading, entries) in [
+            ("largest functions", &self.functions),
+            ("largest data segments", &self.data_segments),
+            ("custom sections", &self.custom_sections),
+        ] {
+            let _ = writeln!(text, "\n{}", heading);
+            if entries.is_empty() {
+                let _ = writeln!(text, "  (none)");
+            }
+            for (name, size) in entries {
+                let _ = writeln!(text, "  {:>10}  {}", size, name);
+            }
+        }
+        text
+    }
+}
+
+//one line of the per project bundle history
+#[derive(Debug, Clone)]
+pub struct BundleHistoryEntry {
+    pub timestamp: String,
+    pub profile: String,
+    pub raw: u64,
+    pub gzip: u64,
+    pub brotli: u64,
+}
+
+//run the optional optimisation stage over a wasm-bindgen dist directory
+//returns the optimised module as it was before custom sections were stripped, so reports keep function names
+pub fn optimize_dist(session: &mut Session, dist_path: &str, options: &WasmOptimization) -> io::Result<Vec<u8>> {
+    let wasm_path = format!("{}/main_bg.wasm", dist_path);
+    if let Some(level) = options.level {
+        match session.get_path("wasm_opt_path") {
+            Ok(wasm_opt) if Path::new(&wasm_opt).exists() => {
+                println!("running wasm-opt {} on {}", level.flag(), &wasm_path);
+                let mut command = Command::new(&wasm_opt);
+                command.arg(level.flag()).args(WASM_OPT_FEATURES);
+                //keep the name section through wasm-opt when it is stripped afterwards anyway
+                if options.strip_custom_sections {
+                    command.arg("--debuginfo");
+                }
+                let output = command
+                    .args([&wasm_path, "-o", &wasm_path])
+                    .output()
+                    .map_err(|e| io::Error::other(format!("Failed to run wasm-opt: {}", e)))?;
+                if !output.status.success() {
+                    return Err(io::Error::other(format!(
+                        "wasm-opt failed: {}",
+                        String::from_utf8_lossy(&output.stderr)
+                    )));
+                }
+            }
+            _ => println!("wasm-opt not installed, skipping {} optimisation", level.flag()),
+        }
+    }
+    let module = fs::read(&wasm_path)?;
+    if options.strip_custom_sections {
+        let stripped = strip_custom_sections(&module)?;
+        println!("stripped custom sections: {} -> {} bytes", module.len(), stripped.len());
+        fs::write(&wasm_path, stripped)?;
+    }
+    if options.gzip || options.brotli {
+        for entry in fs::read_dir(dist_path)? {
+            let path = entry?.path();
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+            if !path.is_file() || !COMPRESSIBLE.contains(&extension) {
+                continue;
+            }
+            let bytes = fs::read(&path)?;
+            if options.gzip {
+                fs::write(format!("{}.gz", path.display()), gzip(&bytes)?)?;
+            }
+            if options.brotli {
+                fs::write(format!("{}.br", path.display()), brotli(&bytes)?)?;
+            }
+        }
+        println!("precompressed dist assets");
+    }
+    Ok(module)
+}
+
+pub fn gzip(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+pub fn brotli(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut compressed = Vec::new();
+    {
+        let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+        writer.write_all(bytes)?;
+    }
+    Ok(compressed)
+}
+
+//build a size report for a dist directory and the wasm module inside it
+pub fn bundle_report(dist_path: &str, release: bool, module: &[u8]) -> io::Result<BundleReport> {
+    let mut files = Vec::new();
+    let mut entries: Vec<_> = fs::read_dir(dist_path)?.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    entries.sort();
+    for path in entries {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        if !path.is_file() || extension == "gz" || extension == "br" || extension == "txt" {
+            continue;
+        }
+        let bytes = fs::read(&path)?;
+        files.push(FileSize {
+            name: path.file_name().unwrap().to_string_lossy().to_string(),
+            raw: bytes.len() as u64,
+            gzip: gzip(&bytes)?.len() as u64,
+            brotli: brotli(&bytes)?.len() as u64,
+        });
+    }
+    let summary = summarize_module(module)?;
+    Ok(BundleReport {
+        timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        profile: if release { "release" } else { "debug" }.to_string(),
+        files,
+        functions: summary.functions,
+        data_segments: summary.data_segments,
+        custom_sections: summary.custom_sections,
+    })
+}
+
+//write the report for this build and append it to the project's size history
+pub fn persist_report(project_path: &str, report: &BundleReport) -> io::Result<String> {
+    let reports_dir = format!("{}/.ramp_reports/wasm", project_path);
+    fs::create_dir_all(&reports_dir)?;
+    let report_path = format!(
+        "{}/{}-{}.txt",
+        reports_dir,
+        report.timestamp.replace([' ', ':'], "-"),
+        report.profile
+    );
+    fs::write(&report_path, report.to_text())?;
+    let total = report.total();
+    let mut history = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(format!("{}/history", reports_dir))?;
+    writeln!(
+        history,
+        "{}\t{}\t{}\t{}\t{}",
+        report.timestamp, report.profile, total.raw, total.gzip, total.brotli
+    )?;
+    println!("bundle report written to {}", &report_path);
+    Ok(report_path)
+}
+
+//read every build recorded in the project's size history, oldest first
+pub fn bundle_history(project_path: &str) -> io::Result<Vec<BundleHistoryEntry>> {
+    let history_path = format!("{}/.ramp_reports/wasm/history", project_path);
+    if !Path::new(&history_path).exists() {
+        return Ok(vec![]);
+    }
+    let mut entries = Vec::new();
+    for line in fs::read_to_string(&history_path)?.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 5 {
+            continue;
+        }
+        let (Ok(raw), Ok(gzip), Ok(brotli)) = (fields[2].parse(), fields[3].parse(), fields[4].parse()) else {
+            continue;
+        };
+        entries.push(BundleHistoryEntry {
+            timestamp: fields[0].to_string(),
+            profile: fields[1].to_string(),
+            raw,
+            gzip,
+            brotli,
+        });
+    }
+    Ok(entries)
+}
+
+//the brotli size change in bytes since the previous build of the same profile, None for a profile's first build
+pub fn size_delta(history: &[BundleHistoryEntry], index: usize) -> Option<i64> {
+    let current = history.get(index)?;
+    history[..index]
+        .iter()
+        .rev()
+        .find(|e| e.profile == current.profile)
+        .map(|previous| current.brotli as i64 - previous.brotli as i64)
+}
+
+//the parts of a wasm module a size report cares about
+#[derive(Debug, Default)]
+pub struct ModuleSummary {
+    pub functions: Vec<(String, u64)>,
+    pub data_segments: Vec<(String, u64)>,
+    pub custom_sections: Vec<(String, u64)>,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, pos: 0 }
+    }
+
+    fn eof(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        let b = *self.bytes.get(self.pos).ok_or_else(|| invalid("unexpected end of wasm module"))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| invalid("wasm section overruns the module"))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn leb(&mut self) -> io::Result<u64> {
+        let mut result = 0u64;
+        let mut shift = 0;
+        loop {
+            let b = self.byte()?;
+            if shift < 64 {
+                result |= ((b & 0x7f) as u64) << shift;
+            }
+            if b & 0x80 == 0 {
+                return Ok(result);
+            }
+            shift += 7;
+            //a u64 never needs more than 10 bytes
+            if shift >= 64 {
+                return Err(invalid("leb128 value too long"));
+            }
+        }
+    }
+
+    fn name(&mut self) -> io::Result<String> {
+        let len = self.leb()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).to_string())
+    }
+
+    //skip a constant initializer expression
+    fn const_expr(&mut self) -> io::Result<()> {
+        loop {
+            match self.byte()? {
+                0x0b => return Ok(()),
+                0x41 | 0x42 | 0x23 | 0xd2 => {
+                    self.leb()?;
+                }
+                0xd0 => {
+                    self.byte()?;
+                }
+                op => return Err(invalid(&format!("unsupported opcode 0x{:02x} in constant expression", op))),
+            }
+        }
+    }
+
+    fn limits(&mut self) -> io::Result<()> {
+        let flags = self.byte()?;
+        self.leb()?;
+        if flags & 1 != 0 {
+            self.leb()?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+//split a module into (section id, payload) pairs after validating the header
+fn sections(module: &[u8]) -> io::Result<Vec<(u8, &[u8])>> {
+    if module.len() < 8 || &module[0..4] != b"\0asm" {
+        return Err(invalid("not a wasm module"));
+    }
+    if module[4..8] != [1, 0, 0, 0] {
+        return Err(invalid("unsupported wasm version"));
+    }
+    let mut reader = Reader::new(&module[8..]);
+    let mut sections = Vec::new();
+    while !reader.eof() {
+        let id = reader.byte()?;
+        let len = reader.leb()? as usize;
+        sections.push((id, reader.take(len)?));
+    }
+    Ok(sections)
+}
+
+//drop every custom section (names, producers, debug info) from a module
+pub fn strip_custom_sections(module: &[u8]) -> io::Result<Vec<u8>> {
+    let mut reader = Reader::new(module);
+    let header = reader.take(8)?;
+    let mut stripped = header.to_vec();
+    while !reader.eof() {
+        let start = reader.pos;
+        let id = reader.byte()?;
+        let len = reader.leb()? as usize;
+        reader.take(len)?;
+        if id != 0 {
+            stripped.extend_from_slice(&module[start..reader.pos]);
+        }
+    }
+    sections(&stripped)?;
+    Ok(stripped)
+}
+
+//collect function body, data segment and custom section sizes, largest first
+pub fn summarize_module(module: &[u8]) -> io::Result<ModuleSummary> {
+    let sections = sections(module)?;
+    let mut summary = ModuleSummary::default();
+    let mut imported_functions = 0u64;
+    let mut function_names = std::collections::HashMap::new();
+    let mut bodies = Vec::new();
+
+    for (id, payload) in sections {
+        let mut reader = Reader::new(payload);
+        match id {
+            0 => {
+                let name = reader.name()?;
+                summary.custom_sections.push((name.clone(), payload.len() as u64));
+                if name == "name" {
+                    while !reader.eof() {
+                        let subsection = reader.byte()?;
+                        let len = reader.leb()? as usize;
+                        let data = reader.take(len)?;
+                        if subsection == 1 {
+                            let mut names = Reader::new(data);
+                            for _ in 0..names.leb()? {
+                                let index = names.leb()?;
+                                function_names.insert(index, names.name()?);
+                            }
+                        }
+                    }
+                }
+            }
+            2 => {
+                for _ in 0..reader.leb()? {
+                    reader.name()?;
+                    reader.name()?;
+                    match reader.byte()? {
+                        0x00 => {
+                            reader.leb()?;
+                            imported_functions += 1;
+                        }
+                        0x01 => {
+                            reader.byte()?;
+                            reader.limits()?;
+                        }
+                        0x02 => reader.limits()?,
+                        0x03 => {
+                            reader.byte()?;
+                            reader.byte()?;
+                        }
+                        0x04 => {
+                            reader.byte()?;
+                            reader.leb()?;
+                        }
+                        kind => return Err(invalid(&format!("unknown import kind {}", kind))),
+                    }
+                }
+            }
+            10 => {
+                for index in 0..reader.leb()? {
+                    let len = reader.leb()?;
+                    reader.take(len as usize)?;
+                    bodies.push((index, len));
+                }
+            }
+            11 => {
+                for index in 0..reader.leb()? {
+                    match reader.leb()? {
+                        0 => reader.const_expr()?,
+                        1 => {}
+                        2 => {
+                            reader.leb()?;
+                            reader.const_expr()?;
+                        }
+                        flag => return Err(invalid(&format!("unknown data segment flag {}", flag))),
+                    }
+                    let len = reader.leb()?;
+                    reader.take(len as usize)?;
+                    summary.data_segments.push((format!("data[{}]", index), len));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    summary.functions = bodies
+        .into_iter()
+        .map(|(index, len)| {
+            let function_index = imported_functions + index;
+            let name = function_names
+                .remove(&function_index)
+                .unwrap_or_else(|| format!("func[{}]", function_index));
+            (name, len)
+        })
+        .collect();
+    for list in [&mut summary.functions, &mut summary.data_segments, &mut summary.custom_sections] {
+        list.sort_by_key(|entry| std::cmp::Reverse(entry.1));
+        list.truncate(REPORT_TOP_ENTRIES);
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //imports a function, memory, table and global, defines two functions, two data segments and name and producers sections
+    const MODULE: &[u8] = include_bytes!("../../tests/fixtures/wasm/summary.wasm");
+
+    fn names(entries: &[(String, u64)]) -> Vec<(&str, u64)> {
+        entries.iter().map(|(name, size)| (name.as_str(), *size)).collect()
+    }
+
+    #[test]
+    fn summarizes_a_module() {
+        let summary = summarize_module(MODULE).unwrap();
+        //function indices count the imported function, the name section only names the second body
+        assert_eq!(names(&summary.functions), [("main", 5), ("func[1]", 2)]);
+        assert_eq!(names(&summary.data_segments), [("data[0]", 4), ("data[1]", 2)]);
+        assert_eq!(names(&summary.custom_sections), [("producers", 31), ("name", 19)]);
+    }
+
+    #[test]
+    fn strips_only_custom_sections() {
+        let stripped = strip_custom_sections(MODULE).unwrap();
+        assert_eq!(stripped.len(), MODULE.len() - (2 + 19) - (2 + 31));
+        assert_eq!(stripped[..], MODULE[..stripped.len()]);
+        let summary = summarize_module(&stripped).unwrap();
+        assert!(summary.custom_sections.is_empty());
+        assert_eq!(names(&summary.functions), [("func[2]", 5), ("func[1]", 2)]);
+        assert_eq!(summary.data_segments.len(), 2);
+    }
+
+    #[test]
+    fn rejects_other_headers() {
+        assert_eq!(summarize_module(b"\0asm").unwrap_err().to_string(), "not a wasm module");
+        assert_eq!(summarize_module(b"\0ELF\x01\0\0\0").unwrap_err().to_string(), "not a wasm module");
+        let mut version_2 = MODULE.to_vec();
+        version_2[4] = 2;
+        assert_eq!(summarize_module(&version_2).unwrap_err().to_string(), "unsupported wasm version");
+        assert!(strip_custom_sections(&version_2).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_modules() {
+        for len in [9, 20, MODULE.len() - 1] {
+            let error = summarize_module(&MODULE[..len]).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "truncated to {}", len);
+            assert!(strip_custom_sections(&MODULE[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn reads_leb128() {
+        let mut reader = Reader::new(&[0xe5, 0x8e, 0x26, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+        assert_eq!(reader.leb().unwrap(), 624_485);
+        assert_eq!(reader.leb().unwrap(), 127);
+        assert_eq!(reader.leb().unwrap(), u64::MAX);
+        assert!(reader.eof());
+        //an unterminated value runs off the end
+        assert!(Reader::new(&[0x80, 0x80]).leb().is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_leb128() {
+        let mut module = b"\0asm\x01\0\0\0\x01".to_vec();
+        module.extend([0x80; 10]);
+        module.push(0x01);
+        assert_eq!(summarize_module(&module).unwrap_err().to_string(), "leb128 value too long");
+        //a section longer than the module
+        let overrun = b"\0asm\x01\0\0\0\x01\xff\x01\x00";
+        assert_eq!(summarize_module(overrun).unwrap_err().to_string(), "wasm section overruns the module");
+    }
+
+    #[test]
+    fn rejects_unknown_import_kinds() {
+        let module = b"\0asm\x01\0\0\0\x02\x04\x01\x00\x00\x09";
+        assert_eq!(summarize_module(module).unwrap_err().to_string(), "unknown import kind 9");
+    }
+
+    #[test]
+    fn compares_with_the_previous_build_of_the_same_profile() {
+        let entry = |profile: &str, brotli: u64| BundleHistoryEntry { timestamp: String::new(), profile: profile.to_string(), raw: 0, gzip: 0, brotli };
+        let history = [entry("release", 1000), entry("size", 700), entry("release", 900)];
+        assert_eq!(size_delta(&history, 0), None);
+        assert_eq!(size_delta(&history, 1), None);
+        assert_eq!(size_delta(&history, 2), Some(-100));
+        assert_eq!(size_delta(&history, 3), None);
+    }
+}
@@ -0,0 +1,367 @@
+use super::plist::ProvisioningProfile;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+//universal tags used by cms and x.509
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_CONTEXT_0: u8 = 0xa0;
+
+//1.2.840.113549.1.7.2 and 1.2.840.113549.1.7.1
+const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+const OID_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01];
+
+//x.509 name attributes, apple puts the team id in the organizational unit
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+const OID_ORGANIZATIONAL_UNIT: &[u8] = &[0x55, 0x04, 0x0b];
+
+//cms from openssl and apple nests indefinite lengths a handful of levels deep
+const MAX_INDEFINITE_DEPTH: usize = 32;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid cms data: {}", message))
+}
+
+//one ber encoded value, `content` excludes the identifier and length octets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tlv<'a> {
+    pub tag: u8,
+    pub content: &'a [u8],
+    //indefinite length values end with two zero octets that aren't part of `content`
+    pub indefinite: bool,
+}
+
+impl<'a> Tlv<'a> {
+    pub fn is_constructed(&self) -> bool {
+        self.tag & 0x20 != 0
+    }
+
+    pub fn children(&self) -> io::Result<Vec<Tlv<'a>>> {
+        read_all(self.content)
+    }
+
+    //the bytes of an octet string, joining the segments of a constructed (ber) one
+    pub fn octets(&self) -> io::Result<Vec<u8>> {
+        if !self.is_constructed() {
+            return Ok(self.content.to_vec());
+        }
+        let mut octets = Vec::new();
+        for child in self.children()? {
+            octets.extend(child.octets()?);
+        }
+        Ok(octets)
+    }
+}
+
+//read one value from the start of bytes, returns it and the remaining bytes
+pub fn read_tlv(bytes: &[u8]) -> io::Result<(Tlv<'_>, &[u8])> {
+    read_nested_tlv(bytes, 0)
+}
+
+//indefinite length values are measured by walking their children, depth bounds that recursion on hostile input
+fn read_nested_tlv(bytes: &[u8], depth: usize) -> io::Result<(Tlv<'_>, &[u8])> {
+    if depth > MAX_INDEFINITE_DEPTH {
+        return Err(invalid("indefinite length values nested too deeply"));
+    }
+    let tag = *bytes.first().ok_or_else(|| invalid("unexpected end of data"))?;
+    if tag & 0x1f == 0x1f {
+        return Err(invalid("multi-byte tags are not used by cms"));
+    }
+    let first = *bytes.get(1).ok_or_else(|| invalid("missing length"))? as usize;
+    let (length, header) = match first {
+        0x80 => {
+            if tag & 0x20 == 0 {
+                return Err(invalid("indefinite length on a primitive value"));
+            }
+            //walk the children to find the end-of-contents marker
+            let mut rest = &bytes[2..];
+            let mut consumed = 0;
+            loop {
+                if rest.len() < 2 {
+                    return Err(invalid("unterminated indefinite length value"));
+                }
+                if rest[0] == 0 && rest[1] == 0 {
+                    let content = &bytes[2..2 + consumed];
+                    return Ok((Tlv { tag, content, indefinite: true }, &bytes[2 + consumed + 2..]));
+                }
+                let (_, after) = read_nested_tlv(rest, depth + 1)?;
+                consumed += rest.len() - after.len();
+                rest = after;
+            }
+        }
+        n if n & 0x80 == 0 => (n, 2),
+        n => {
+            let count = n & 0x7f;
+            if count > 4 {
+                return Err(invalid("length too large"));
+            }
+            let octets = bytes.get(2..2 + count).ok_or_else(|| invalid("truncated length"))?;
+            (octets.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize), 2 + count)
+        }
+    };
+    let content = bytes.get(header..header + length).ok_or_else(|| invalid("value overruns its container"))?;
+    Ok((Tlv { tag, content, indefinite: false }, &bytes[header + length..]))
+}
+
+pub fn read_all(mut bytes: &[u8]) -> io::Result<Vec<Tlv<'_>>> {
+    let mut values = Vec::new();
+    while !bytes.is_empty() {
+        let (value, rest) = read_tlv(bytes)?;
+        values.push(value);
+        bytes = rest;
+    }
+    Ok(values)
+}
+
+fn expect<'a>(value: Option<&Tlv<'a>>, tag: u8, what: &str) -> io::Result<Tlv<'a>> {
+    match value {
+        Some(v) if v.tag == tag => Ok(*v),
+        _ => Err(invalid(&format!("expected {}", what))),
+    }
+}
+
+//the signed content and embedded certificates of a cms SignedData envelope
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedData {
+    pub content: Vec<u8>,
+    pub certificates: Vec<Vec<u8>>,
+}
+
+//parse ContentInfo { signedData, SignedData { version, digests, EncapsulatedContentInfo, [0] certificates, .. } }
+//the signature itself isn't verified, the device checks it when the profile is installed
+pub fn parse_signed_data(bytes: &[u8]) -> io::Result<SignedData> {
+    let (content_info, _) = read_tlv(bytes)?;
+    if content_info.tag != TAG_SEQUENCE {
+        return Err(invalid("expected ContentInfo"));
+    }
+    let fields = content_info.children()?;
+    if expect(fields.first(), TAG_OID, "content type")?.content != OID_SIGNED_DATA {
+        return Err(invalid("not a SignedData envelope"));
+    }
+    let wrapper = expect(fields.get(1), TAG_CONTEXT_0, "SignedData")?;
+    let signed_data = expect(wrapper.children()?.first(), TAG_SEQUENCE, "SignedData")?;
+    let fields = signed_data.children()?;
+    expect(fields.first(), TAG_INTEGER, "SignedData version")?;
+    expect(fields.get(1), TAG_SET, "digest algorithms")?;
+
+    let encapsulated = expect(fields.get(2), TAG_SEQUENCE, "EncapsulatedContentInfo")?.children()?;
+    if expect(encapsulated.first(), TAG_OID, "content type")?.content != OID_DATA {
+        return Err(invalid("signed content is not data"));
+    }
+    let explicit = expect(encapsulated.get(1), TAG_CONTEXT_0, "signed content")?;
+    let octets = explicit.children()?;
+    let content = match octets.first() {
+        Some(o) if o.tag & !0x20 == TAG_OCTET_STRING => o.octets()?,
+        _ => return Err(invalid("expected signed content octets")),
+    };
+
+    //[0] IMPLICIT CertificateSet follows the content when present
+    let certificates = match fields.get(3) {
+        Some(set) if set.tag == TAG_CONTEXT_0 => {
+            let mut certificates = Vec::new();
+            let mut rest = set.content;
+            while !rest.is_empty() {
+                let (_, after) = read_tlv(rest)?;
+                certificates.push(rest[..rest.len() - after.len()].to_vec());
+                rest = after;
+            }
+            certificates
+        }
+        _ => Vec::new(),
+    };
+    Ok(SignedData { content, certificates })
+}
+
+//the plist inside a .mobileprovision
+pub fn decode_mobileprovision(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    Ok(parse_signed_data(bytes)?.content)
+}
+
+pub fn read_mobileprovision(path: &Path) -> io::Result<ProvisioningProfile> {
+    let bytes = fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("Failed to read {}: {}", path.display(), e)))?;
+    let plist = decode_mobileprovision(&bytes).map_err(|e| invalid(&format!("{}: {}", path.display(), e)))?;
+    ProvisioningProfile::parse(&plist)
+}
+
+//what ramp shows about a signing certificate
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertificateSummary {
+    pub common_name: Option<String>,
+    pub organizational_unit: Option<String>,
+    pub not_before: Option<DateTime<Utc>>,
+    pub not_after: Option<DateTime<Utc>>,
+}
+
+fn parse_time(value: &Tlv) -> Option<DateTime<Utc>> {
+    let text = std::str::from_utf8(value.content).ok()?;
+    let text = text.strip_suffix('Z')?;
+    let parsed = match value.tag {
+        //two digit years 50-99 are 19xx
+        TAG_UTC_TIME => {
+            let year: u32 = text.get(..2)?.parse().ok()?;
+            let century = if year >= 50 { "19" } else { "20" };
+            NaiveDateTime::parse_from_str(&format!("{}{}", century, text), "%Y%m%d%H%M%S").ok()?
+        }
+        TAG_GENERALIZED_TIME => NaiveDateTime::parse_from_str(text, "%Y%m%d%H%M%S").ok()?,
+        _ => return None,
+    };
+    Some(parsed.and_utc())
+}
+
+fn name_attribute(name: &Tlv, oid: &[u8]) -> io::Result<Option<String>> {
+    for rdn in name.children()? {
+        for attribute in rdn.children()? {
+            let parts = attribute.children()?;
+            if parts.first().is_some_and(|p| p.tag == TAG_OID && p.content == oid) {
+                if let Some(value) = parts.get(1) {
+                    return Ok(Some(String::from_utf8_lossy(value.content).to_string()));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+//subject and validity of a der x.509 certificate
+pub fn certificate_summary(der: &[u8]) -> io::Result<CertificateSummary> {
+    let (certificate, _) = read_tlv(der)?;
+    let tbs = expect(certificate.children()?.first(), TAG_SEQUENCE, "TBSCertificate")?.children()?;
+    //the version is an optional [0] before the serial number
+    let offset = if tbs.first().is_some_and(|v| v.tag == TAG_CONTEXT_0) { 1 } else { 0 };
+    let validity = expect(tbs.get(offset + 3), TAG_SEQUENCE, "validity")?.children()?;
+    let subject = expect(tbs.get(offset + 4), TAG_SEQUENCE, "subject")?;
+    Ok(CertificateSummary {
+        common_name: name_attribute(&subject, OID_COMMON_NAME)?,
+        organizational_unit: name_attribute(&subject, OID_ORGANIZATIONAL_UNIT)?,
+        not_before: validity.first().and_then(parse_time),
+        not_after: validity.get(1).and_then(parse_time),
+    })
+}
+
+//expiry problems worth telling the user about before deploying with a profile, `window` is how far ahead to look
+pub fn profile_warnings(profile: &ProvisioningProfile, now: DateTime<Utc>, window: chrono::Duration) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let soon = now + window;
+    if let Some(expiry) = profile.expiration_date.map(DateTime::<Utc>::from) {
+        if expiry <= now {
+            warnings.push(format!("provisioning profile {} expired on {}", profile.name, expiry.format("%Y-%m-%d")));
+        } else if expiry <= soon {
+            warnings.push(format!("provisioning profile {} expires on {}", profile.name, expiry.format("%Y-%m-%d")));
+        }
+    }
+    for der in &profile.developer_certificates {
+        let Ok(certificate) = certificate_summary(der) else { continue };
+        let name = certificate.common_name.unwrap_or_else(|| "unnamed certificate".to_string());
+        match certificate.not_after {
+            Some(expiry) if expiry <= now => {
+                warnings.push(format!("signing certificate {} expired on {}", name, expiry.format("%Y-%m-%d")))
+            }
+            Some(expiry) if expiry <= soon => {
+                warnings.push(format!("signing certificate {} expires on {}", name, expiry.format("%Y-%m-%d")))
+            }
+            _ => {}
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ramp::helper::test_dir;
+    use chrono::{Duration, TimeZone};
+
+    //the same decoded profile signed by `openssl cms -sign`, once in der and once streamed as indefinite length ber
+    const DER_PROFILE: &[u8] = include_bytes!("../../tests/fixtures/ios/der.mobileprovision");
+    const BER_PROFILE: &[u8] = include_bytes!("../../tests/fixtures/ios/ber.mobileprovision");
+    const PROFILE_XML: &[u8] = include_bytes!("../../tests/fixtures/ios/profile.xml.plist");
+    //validity encoded as UTCTime, and as GeneralizedTime for dates past 2049
+    const DEVELOPMENT_CERT: &[u8] = include_bytes!("../../tests/fixtures/ios/development.der");
+    const DISTRIBUTION_CERT: &[u8] = include_bytes!("../../tests/fixtures/ios/distribution.der");
+
+    #[test]
+    fn parses_der_and_ber_signed_data() {
+        for bytes in [DER_PROFILE, BER_PROFILE] {
+            let signed = parse_signed_data(bytes).unwrap();
+            assert_eq!(signed.content, PROFILE_XML);
+            assert_eq!(signed.certificates, [DEVELOPMENT_CERT]);
+        }
+        assert!(read_tlv(BER_PROFILE).unwrap().0.indefinite);
+        assert!(!read_tlv(DER_PROFILE).unwrap().0.indefinite);
+    }
+
+    #[test]
+    fn rejects_truncated_and_foreign_envelopes() {
+        assert!(parse_signed_data(&DER_PROFILE[..DER_PROFILE.len() / 2]).is_err());
+        assert!(parse_signed_data(&BER_PROFILE[..BER_PROFILE.len() - 2]).is_err());
+        //a certificate is a sequence too, but not a ContentInfo
+        assert!(parse_signed_data(DEVELOPMENT_CERT).is_err());
+    }
+
+    #[test]
+    fn limits_indefinite_length_nesting() {
+        let nested = |depth: usize| [[0x30, 0x80].repeat(depth), [0x00, 0x00].repeat(depth)].concat();
+        assert!(read_tlv(&nested(MAX_INDEFINITE_DEPTH + 1)).is_ok());
+        assert!(read_tlv(&nested(MAX_INDEFINITE_DEPTH + 2)).is_err());
+        assert!(read_tlv(&nested(100_000)).is_err());
+    }
+
+    #[test]
+    fn reads_a_mobileprovision() {
+        let path = test_dir("cms").join("demo.mobileprovision");
+        fs::write(&path, BER_PROFILE).unwrap();
+        let profile = read_mobileprovision(&path).unwrap();
+        assert_eq!(profile.name, "Demo Development");
+        assert_eq!(profile.developer_certificates, [DEVELOPMENT_CERT]);
+    }
+
+    #[test]
+    fn summarizes_utc_time_certificates() {
+        let summary = certificate_summary(DEVELOPMENT_CERT).unwrap();
+        assert_eq!(summary.common_name.as_deref(), Some("Apple Development: Ramp Test (TEAMID1234)"));
+        assert_eq!(summary.organizational_unit.as_deref(), Some("TEAMID1234"));
+        assert_eq!(summary.not_before, Some(Utc.with_ymd_and_hms(2025, 10, 19, 0, 0, 0).unwrap()));
+        assert_eq!(summary.not_after, Some(Utc.with_ymd_and_hms(2027, 10, 19, 0, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn summarizes_generalized_time_certificates() {
+        let summary = certificate_summary(DISTRIBUTION_CERT).unwrap();
+        assert_eq!(summary.common_name.as_deref(), Some("Apple Distribution: Ramp Test (TEAMID1234)"));
+        assert_eq!(summary.not_after, Some(Utc.with_ymd_and_hms(2051, 1, 1, 12, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn warns_within_the_window() {
+        let profile = ProvisioningProfile::parse(PROFILE_XML).unwrap();
+        let profile_expiry = Utc.with_ymd_and_hms(2027, 10, 1, 12, 0, 0).unwrap();
+        let certificate_expiry = Utc.with_ymd_and_hms(2027, 10, 19, 0, 0, 0).unwrap();
+        let window = Duration::days(14);
+
+        assert!(profile_warnings(&profile, profile_expiry - window - Duration::seconds(1), window).is_empty());
+        assert_eq!(
+            profile_warnings(&profile, profile_expiry - window, window),
+            ["provisioning profile Demo Development expires on 2027-10-01"]
+        );
+        assert_eq!(
+            profile_warnings(&profile, profile_expiry, window),
+            ["provisioning profile Demo Development expired on 2027-10-01"]
+        );
+        let warnings = profile_warnings(&profile, certificate_expiry - window, window);
+        assert_eq!(warnings[1], "signing certificate Apple Development: Ramp Test (TEAMID1234) expires on 2027-10-19");
+        let warnings = profile_warnings(&profile, certificate_expiry, window);
+        assert_eq!(warnings[1], "signing certificate Apple Development: Ramp Test (TEAMID1234) expired on 2027-10-19");
+
+        //a wider window reaches the certificate too, a zero window only reports what has already expired
+        let early = profile_expiry - window;
+        assert_eq!(profile_warnings(&profile, early, Duration::days(40)).len(), 2);
+        assert!(profile_warnings(&profile, early, Duration::zero()).is_empty());
+    }
+}
@@ -1,15 +1,31 @@
 use super::helper::{is_command_available, capitalize_first};
-use super::session::{Session};
+use super::session::{Session, SigningIdentity};
+use super::web::wasm_post_build;
+use super::buildrs::integrate_build_script;
+use super::macos::{app_bundle_path, macos_post_build, package_macos, MACOS_TARGETS};
+use super::adb::{adb_command, adb_path, selected_device, AndroidDevice};
+use super::logcat::watch_app_logs;
+use super::axml::{launch_target_from_apk, launch_target_from_manifest};
+use super::manifest::{current_project_manifest, edit_manifest};
+use super::plist::{bundle_identifier, read_plist, set_plist_string};
+use super::install::has_signing_identity;
+use super::notarize::{macos_entitlements, sign_app, SystemRunner};
+use super::wizard::{run_wizard, Wizard, WizardKind, WizardOutcome};
+use super::profiles::{embed_profile, profile_issues, ProfileStore, EMBEDDED_PROFILE};
+use super::apk::{build_android_apks, size_report};
+use super::aab::{android_bundle_post_build, ANDROID_TARGETS};
+use super::keystore::{ensure_android_keystore, release_signing_env, verify_apk_signature};
+use super::windows::{package_windows, write_resource_file};
 use image::{self, imageops, DynamicImage, ImageEncoder};
 use std::env;
 use std::fs;
-use std::fs::{File, read_to_string};
-use std::io::{self, Write, BufReader, BufRead, ErrorKind};
+use std::io::{self, ErrorKind};
 use std::path::Path;
 use std::process::{Command, Stdio};
 use regex::Regex;
 use std::thread::sleep;
-use std::time::{Duration, Instant};
+use std::time::{Duration, SystemTime};
+use chrono::Utc;
 
 //sign an app build
 pub fn sign_build(session: &mut Session, target_os: &str, release: bool) -> io::Result<()> {
@@ -37,8 +53,10 @@ pub fn sign_build(session: &mut Session, target_os: &str, release: bool) -> io::
         }
         //sign the build
         let app_bundle = format!("{}/{}/ios/{}.app", session.projects_path.as_ref().unwrap(), session.current_project.as_ref().unwrap(), capitalize_first(session.current_project.as_ref().unwrap()));
+        let identity = session.certs.identity(target_os, release)?;
+        println!("signing with {}", identity);
         let output = Command::new("codesign")
-        .args(["--force", "--sign", session.certs.macos.as_ref(), "--entitlements", &format!("{}/entitlements.plist", &app_bundle),  &app_bundle])
+        .args(["--force", "--sign", identity, "--entitlements", &format!("{}/entitlements.plist", &app_bundle),  &app_bundle])
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
         .output()
@@ -56,6 +74,21 @@ pub fn sign_build(session: &mut Session, target_os: &str, release: bool) -> io::
                 ));
             }
         }
+    } else if target_os == "macos" {
+        if session.os.as_str() != "macos" {
+            println!("codesign is only available on macos, leaving the app bundle unsigned");
+            return Ok(());
+        }
+        let identity = session.certs.identity(target_os, release)?.to_string();
+        if !has_signing_identity(&identity)? {
+            let kind = SigningIdentity::for_build(target_os, release)?;
+            println!("WARNING: no {} identity in the keychain, leaving the app bundle unsigned. Create one with ramp_gui -certs setup {}", identity, kind.as_str());
+            return Ok(());
+        }
+        println!("signing with {}", identity);
+        let app_bundle = app_bundle_path(session);
+        sign_app(&SystemRunner, &identity, macos_entitlements(session).as_deref(), Path::new(&app_bundle), release)
+            .map_err(|e| io::Error::other(format!("macos post build failed to sign app bundle: {}", e)))?;
     }
     //TODO add support for other outputs
     println!("signed {} app bundle", target_os);
@@ -230,21 +263,26 @@ pub fn new_project(session: &mut Session, name: &str) -> io::Result<()> {
         }
     }
 
-    //create bundle identifier
-    let bundle_id = create_app_bundle_id(session)?;
-
     //rename everything inside of the template with the project name
-    template_naming(session, &name.to_lowercase(), bundle_id)?;
+    template_naming(session, &name.to_lowercase(), None)?;
 
     //update the current loaded project to the new project
     load_project(session, &name.to_lowercase())?;
 
+    //create bundle identifier
+    create_app_bundle_id(session)?;
+
     Ok(())
 }
 
+//ramp config key holding the project most recently loaded
+pub const LAST_PROJECT_KEY: &str = "last_project";
+
 pub fn load_project(session: &mut Session, name: &str) -> io::Result<()> {
     println!("loading project...");
     session.update_current_project(name)?;
+    //pages read the last loaded project's settings
+    session.set_config(LAST_PROJECT_KEY, name)?;
     Ok(())
 }
 
@@ -281,6 +319,10 @@ pub fn template_naming(session: &mut Session, name: &str, bundle_id: Option<Stri
         &replacements,
     )?;
     //replace bundle id if applicable
+    if let Some(bundle_id) = bundle_id.as_ref() {
+        //android application ids can't contain dashes
+        edit_manifest(&new_path, |manifest| manifest.set_android_package(&bundle_id.replace('-', "_")))?;
+    }
     if bundle_id.is_some() {
         let existing_bundle = format!("com.ramp.{}", name);
         let replacements = vec![(existing_bundle.as_str(), bundle_id.as_ref().unwrap().as_str())];
@@ -364,19 +406,9 @@ pub fn replace_strings_in_file(file_path: &str, replacements: &Vec<(&str, &str)>
 
 pub fn get_bundle_id(session: &mut Session, target_os: &str) -> io::Result<String> {
     let plist_path = format!("{}/{}/{}/{}.app/Info.plist", session.projects_path.as_ref().unwrap(), session.current_project.as_ref().unwrap(), &target_os, capitalize_first(session.current_project.as_ref().unwrap()));
-    let plist_content = fs::read_to_string(&plist_path)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to read Info.plist in get_bundle_id: {}", e)))?;
-
-    let re = Regex::new(r#"<key>CFBundleIdentifier</key>\s*<string>([^<]+)</string>"#).unwrap();
-    let captures = re.captures(&plist_content)
-        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "CFBundleIdentifier not found"))?;
-
-    let bundle_id = captures.get(1)
-        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Failed to extract CFBundleIdentifier"))?
-        .as_str()
-        .to_string();
-
-    Ok(bundle_id)
+    let info_plist = read_plist(Path::new(&plist_path))
+        .map_err(|e| io::Error::new(e.kind(), format!("Failed to read Info.plist in get_bundle_id: {}", e)))?;
+    bundle_identifier(&info_plist)
 }
 
 pub fn resize_png(input_name: &str, target_name: &str, width: u32, height: u32) -> io::Result<()> {
@@ -511,20 +543,8 @@ pub fn convert_png_to_ico(session: &Session, input_path: &str) -> io::Result<()>
             ));
         }
     }
-    //create a new app.rc using absolute path passed in
-    let ico_path = format!(
-        "{}/{}/assets/resources/icons/windows_icon.ico",
-        session.projects_path.as_ref().unwrap(),
-        session.current_project.as_ref().unwrap()
-    );
-    let rc_content = format!(r#"IDI_ICON1 ICON "{}""#, ico_path);
-    let mut rc_file = File::create(&rc)?;
-    rc_file.write_all(rc_content.as_bytes())?;
-    //ensure the file is fully written
-    rc_file.flush()?;
-    //explicitly close the file
-    drop(rc_file);
-    println!("created resource file: {}", &rc);
+    //create a new app.rc with the icon and version info from the project's Cargo.toml
+    write_resource_file(session)?;
     let res = format!(
         "{}/{}/app.res",
         session.projects_path.as_ref().unwrap(),
@@ -532,44 +552,8 @@ pub fn convert_png_to_ico(session: &Session, input_path: &str) -> io::Result<()>
     );
     println!("rc path: {}", &rc);
     println!("res path: {}", &res);
-    let build_path = format!(
-        "{}/{}/build.rs",
-        session.projects_path.as_ref().unwrap(),
-        session.current_project.as_ref().unwrap()
-    );
-    //if a build.rs file exists, first remove it.
-    if Path::new(&build_path).exists() {
-        let output = Command::new("rm")
-            .arg(&build_path)
-            .output()
-            .unwrap();
-        if !output.status.success() {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "could not remove old build.rs",
-            ));
-        }
-    }
-    //populate the build.rs content
-    let build_content = format!(
-        r#"
-        use std::io;
-
-        fn main() {{
-            if std::env::var("CARGO_CFG_TARGET_OS").unwrap() == "windows" && std::path::Path::new("{}").exists() {{
-                embed_resource::compile("app.rc", embed_resource::NONE)
-                .manifest_optional();
-            }}
-    }}
-    
-        "#,
-        &ico_path
-    );
-    //Generate a build.rs file
-    let mut build_file = fs::File::create(&build_path)?;
-    build_file.write_all(build_content.as_bytes())?;
-    build_file.flush()?;
-    println!("Created Build.rs at {}", &build_path);
+    //include ramp's generated build module from the project's build.rs without overwriting user build logic
+    integrate_build_script(session)?;
     //copy windows_icon.ico into a favicon.ico
     let output = Command::new("cp")
         .args([&win_output_path, &wasm_output_path])
@@ -683,148 +667,47 @@ pub fn update_icons(session: &Session) -> io::Result<()> {
     Ok(())
 }
 
-pub fn provision_device(session: &mut Session, udid: String, target_os: &String, release: bool) -> io::Result<()> {
+pub fn provision_device(session: &mut Session, udid: String, target_os: &str, release: bool) -> io::Result<()> {
     println!("Provisioning a new device with unique device id: {}", &udid);
-    //open apple developer portal
-    let output = Command::new("open")
-    .args(["-a", "safari", "https://developer.apple.com/account/resources/devices/list"])
-    .stdout(Stdio::null())
-    .stderr(Stdio::null())
-    .output()
-    .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to open Apple developer portal to devices list: {}", e)))?;
-    if !output.status.success() {
-        return Err(io::Error::new(io::ErrorKind::Other, "Failed to open Apple developer portal to devices list"));
-    }    
-    println!("**********************************");   
-    //TODO eventually replace these prints in the GUI
-    println!("1. Now go to the safari window and login to your developer account.");
-    println!("2. Once you have logged in, click the + button next to \"Devices\".");
-    println!("3. Give the device whatever name you would like and copy and paste in the following numbers and letters into \"Device ID (UUID)\".");
-    println!("{}", &udid);
-    println!("4. Click the continue button.");
-    println!("5. Press enter here in the terminal to continue");
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to receive user input: {}", e)))?;
-
     //parse the app bundle id from the info.plist
-    let path_string = format!("{}/{}/{}/{}.app/Info.plist", session.projects_path.as_ref().unwrap(), session.current_project.as_ref().unwrap(), &target_os, capitalize_first(session.current_project.as_ref().unwrap()));
-    let path = Path::new(&path_string);
-    let content = fs::read_to_string(path).map_err(|e| io::Error::new(io::ErrorKind::Other, format!("failed to read info.plist: {}", e)))?;
-    let key_str = "<key>CFBundleIdentifier</key>";
-    let key_pos = content.find(key_str).ok_or_else(|| io::Error::new(io::ErrorKind::Other, format!("CFBundleIdentifier key not found in Info.plist")))?;
-    let start_after_key = key_pos + key_str.len();
-    let rest_after_key = &content[start_after_key..];
-
-    let string_open = "<string>";
-    let string_pos = rest_after_key.find(string_open).ok_or_else(|| io::Error::new(io::ErrorKind::Other, "No value found for key CFBundleIdentifier"))?;
-    let start_of_value = string_pos + string_open.len();
-    let rest_after_open = &rest_after_key[start_of_value..];
-
-    let string_close = "</string>";
-    let close_pos = rest_after_open.find(string_close).ok_or_else(|| io::Error::new(io::ErrorKind::Other, "No value found for key CFBundleIdentifier"))?;
-    let bundle_id = rest_after_open[..close_pos].trim().to_string();
-
-    //create, download the .mobileprovision profile obtained from developer.apple
-    println!("Provisioning profile for device id: {} and app bundle: {}", &udid, &bundle_id);
-     //open apple developer portal
-     let output = Command::new("open")
-     .args(["-a", "safari", "https://developer.apple.com/account/resources/profiles/list"])
-     .stdout(Stdio::null())
-     .stderr(Stdio::null())
-     .output()
-     .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to open Apple developer portal profile list: {}", e)))?;
-     if !output.status.success() {
-         return Err(io::Error::new(io::ErrorKind::Other, "Failed to open Apple developer portal profile list"));
-     }    
-     println!("**********************************");   
-     //TODO eventually replace these prints in the GUI
-     println!("1. Now go to the safari window and login to your developer account if necessary.");
-     println!("2. Once you have logged in, click the + button next to \"Profiles\".");
-     println!("3. Check your development or distribution options: we reccomend choosing \"{} App Development\", then press continue", target_os.as_str());
-     println!("4. Select your App ID from the dropdown list: {}", &bundle_id);
-     println!("5. Select whether you would like offline support (choose No if you're not sure). Then press continue.");
-     println!("6. Select your Appropriate \"(Development)\" certificate. Then press continue.");
-
-     println!("7. Select the device profile corresponding to UUID: {}", &udid);
-     println!("8. Click the continue button.");
-     //TODO add support for release key here by checking release bool and changing the string
-     let profile_name = if release {"Ramp Debug"} else {"Ramp Release"};
-     println!("9. Enter your Provisioning profile name. Reccomended: \"{}\"", &profile_name);
-     println!("10. Click \"Generate\". Then click \"Download\".");
-     println!("11. Press enter to continue...");
-     io::stdin().read_line(&mut input).map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to receive user input: {}", e)))?;
-
-     //detect the .mobileprovision from the Downloads folder
-    let downloads_path_string = format!("{}/Downloads", &session.home);
-    let downloads_path = Path::new(&downloads_path_string);
-    let timeout = Duration::from_secs(60);
-    let start_time = Instant::now();
-
-    let mobileprovision_file: String;
-
-    //loop for 60 seconds to check for a .mobileprovision in Downloads
-    loop {
-        if start_time.elapsed() >= timeout {
-            return Err(io::Error::new(io::ErrorKind::TimedOut, "No .mobileprovision file found within 60 seconds"));
-        }
-
-        let entries = fs::read_dir(downloads_path)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to read directory: {}", e)))?;
-
-        let mut matching_files = Vec::new();
-        for entry in entries {
-            let entry = entry.map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to process directory entry: {}", e)))?;
-            let file_name = entry.file_name().to_string_lossy().into_owned();
-            if file_name.ends_with(".mobileprovision") {
-                matching_files.push(file_name);
-            }
-        }
-
-        match matching_files.len() {
-            0 => {
-                // Continue looping
-            }
-            1 => {
-                mobileprovision_file = matching_files[0].clone();
-                break;
-            }
-            _ => {
-                return Err(io::Error::new(io::ErrorKind::Other, "Multiple .mobileprovision files found"));
-            }
-        }
-
-        // Sleep briefly
-        std::thread::sleep(Duration::from_millis(500));
+    let bundle_id = get_bundle_id(session, target_os)?;
+    //registering the device and creating the profile happen in the developer portal, guided by the provisioning wizard
+    let wizard = Wizard::new(WizardKind::Provision)
+        .with_value("project", session.current_project.as_ref().unwrap())
+        .with_value("udid", &udid)
+        .with_value("target_os", target_os)
+        .with_value("release", &release.to_string())
+        .with_value("bundle_id", &bundle_id);
+    match run_wizard(session, wizard)? {
+        WizardOutcome::Finished => Ok(()),
+        WizardOutcome::Pending => Err(io::Error::new(
+            io::ErrorKind::Other,
+            "the device is not provisioned yet, finish the device provisioning wizard in ramp and deploy again",
+        )),
     }
-    println!("Mobile Provision File Name: {}", &mobileprovision_file);
-    let mp_origin = format!("{}/Downloads/{}", &session.home, &mobileprovision_file);
-    println!("mobile provision origin path: {}", &mp_origin);
+}
+
+//import a .mobileprovision from the developer portal, install it on the device and re-sign the app with it
+pub fn install_provisioning_profile(session: &mut Session, profile_path: &Path, udid: &str, target_os: &str, release: bool) -> io::Result<()> {
+    let bundle_id = get_bundle_id(session, target_os)?;
+    //keep the profile in the project's profile store
+    let store = ProfileStore::open(session)?;
+    store.import(profile_path)?;
+    let selected = store.best_match(&bundle_id, Some(udid))?.ok_or_else(|| io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("none of the project's provisioning profiles cover {} on device {}", &bundle_id, &udid),
+    ))?;
+    store.select(session, &selected.profile.uuid)?;
+    println!("using provisioning profile {}", selected.summary());
     let mp_destination = format!("{}/{}/{}/{}.app", session.projects_path.as_ref().unwrap(), session.current_project.as_ref().unwrap(), &target_os, capitalize_first(session.current_project.as_ref().unwrap()));
-    println!("mobile provision destination path: {}", &mp_destination);
-    //cut the mobileprovision from Downloads folder to the project's app bundle
-    if Path::new(&mp_origin).exists() {
-        //Move the .mobileprovision to the app bundle
-        let output = Command::new("mv")
-            .args([&mp_origin, &mp_destination])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null())
-            .output()
-            .unwrap();
-        if !output.status.success() {
-            return Err(io::Error::new(io::ErrorKind::Other, "Failed to move the .mobileprovision to the keychain directory"));
-        }
-        println!("Successfully moved the mobile provision!");
-    } else {
-        return Err(io::Error::new(io::ErrorKind::Other, "Failed to obtain the path to mobile provision"));
-    }
+    let mobile_provision_path = embed_profile(&selected, Path::new(&mp_destination))?.to_string_lossy().to_string();
     //install the profile to the device with the UDID and ilibmobiledevice
     println!("installing provisioning profile to the target device");
     let ilibimobile_bin = format!("{}/ideviceprovision", session.get_path("homebrew_path")?);
     println!("ideviceprovision path: {}", &ilibimobile_bin);
-    let mobile_provision_path = format!("{}/{}", &mp_destination, &mobileprovision_file);
     println!("Mobile provision path: {}", &mobile_provision_path);
     let output = Command::new(&ilibimobile_bin)
-        .args(["install", &mobile_provision_path, "--udid", &udid])
+        .args(["install", &mobile_provision_path, "--udid", udid])
         .output()?;
     if !output.status.success() {
         return Err(io::Error::new(io::ErrorKind::Other, "Failed to install .mobileprovision to the device"));
@@ -832,32 +715,16 @@ pub fn provision_device(session: &mut Session, udid: String, target_os: &String,
 
     //decode and extract entitlements from the mobile provision into an entitltements.plist
     println!("decoding and extracting the entitlements from the mobile provision");
-    let security_output = Command::new("security")
-    .args(["cms", "-D", "-i", &mobile_provision_path])
-    .output()?;
-    if !security_output.status.success() {
-        return Err(io::Error::new(io::ErrorKind::Other, "Failed to decode provisioning profile"));
-    }
     let entitlements_path = format!("{}/entitlements.plist", &mp_destination);
-
-    let mut plutil_cmd = Command::new("plutil")
-        .args(["-extract", "Entitlements", "xml1", "-o", &entitlements_path, "-"])
-        .stdin(Stdio::piped())
-        .spawn()?;
-
-    if let Some(mut stdin) = plutil_cmd.stdin.take() {
-        stdin.write_all(&security_output.stdout)?;
-    }
-
-    let plutil_output = plutil_cmd.wait_with_output()?;
-
-    if !plutil_output.status.success() {
-        return Err(io::Error::new(io::ErrorKind::Other, "Failed to extract entitlements"));
+    for issue in profile_issues(&selected, Some(udid), Utc::now()) {
+        println!("WARNING: {}", issue);
     }
+    selected.profile.write_entitlements(Path::new(&entitlements_path))
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to extract entitlements: {}", e)))?;
     println!("Successfully decoded and extracted entitlements to entitlements.plist");
     println!("Signing app bundle with new provisioning");
     //resign the build with new provisioning parameters outlined (this is normally done within the build output flow)
-    sign_build(session, &target_os, release)?;
+    sign_build(session, target_os, release)?;
     Ok(())
 }
 
@@ -1012,183 +879,54 @@ pub fn get_device_identifier() -> io::Result<String> {
 
 pub fn is_device_provisioned(session: &mut Session, app_bundle_path: &str, device_id: &str, udid: &str) -> io::Result<bool> {
     println!("checking if target device is properly provisioned");
-    //obtain the mobile provision file name
-    let mobileprovision_file: String;
-    let entries = fs::read_dir(app_bundle_path)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to read directory: {}", e)))?;
-
-        let mut matching_files = Vec::new();
-        for entry in entries {
-            let entry = entry.map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to process directory entry: {}", e)))?;
-            let file_name = entry.file_name().to_string_lossy().into_owned();
-            if file_name.ends_with(".mobileprovision") {
-                matching_files.push(file_name);
-            }
+    let store = ProfileStore::open(session)?;
+    //profiles placed in the app bundle by older ramp versions are moved into the store
+    for entry in fs::read_dir(app_bundle_path)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e == "mobileprovision") && store.import(&path).is_ok() && path.file_name().is_some_and(|f| f != EMBEDDED_PROFILE) {
+            fs::remove_file(&path)?;
         }
-
-        match matching_files.len() {
-            0 => {
-                println!("No provisioning profile found");
-                return Ok(false);
-            }
-            1 => {
-                println!("Exactly one provisioning profile found");
-                mobileprovision_file = matching_files[0].clone();
-            }
-            _ => {
-                println!("something weird happened finding the provisioning profile");
+    }
+    let bundle_id = get_bundle_id(session, "ios")?;
+    let stored = match store.active(session)? {
+        Some(active) if active.profile.matches_bundle_id(&bundle_id) => active,
+        _ => match store.best_match(&bundle_id, Some(udid))? {
+            Some(best) => store.select(session, &best.profile.uuid)?,
+            None => {
+                println!("No provisioning profile found for {}", &bundle_id);
                 return Ok(false);
             }
-        }
-    // 
-    
-    let profile_path_str = format!("{}/{}", &app_bundle_path, &mobileprovision_file);
-    let profile_path = Path::new(&profile_path_str);
-    //query the mobile provision profile
-    let output = Command::new("security")
-        .arg("cms")
-        .arg("-D")
-        .arg("-i")
-        .arg(profile_path)
-        .output()
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to execute security command: {}", e)))?;
-    if !output.status.success() {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!("security command failed: {}", String::from_utf8_lossy(&output.stderr)),
-        ));
-    }
-    //check for an existing device provision
-    let xml = String::from_utf8(output.stdout)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Invalid UTF-8 in plist: {}", e)))?;
-    let key_str = "<key>ProvisionedDevices</key>";
-    let Some(key_pos) = xml.find(key_str) else {
-        println!("provision profile does not contain valid syntax: <key>ProvisionedDevice</key>");
-        return Ok(false);
+        },
     };
-    let start_after_key = key_pos + key_str.len();
-    let rest_after_key = &xml[start_after_key..];
-    let string_open = "<array>";
-    let Some(array_pos) = rest_after_key.find(string_open) else {
-        println!("provision profile does not contain valid syntax: <array>");
-        return Ok(false);
-    };
-    let start_of_array = array_pos + string_open.len();
-    let rest_after_open = &rest_after_key[start_of_array..];
-    let string_close = "</array>";
-    let Some(close_pos) = rest_after_open.find(string_close) else {
-        println!("provision profile does not contain valid syntax: </array>");
+    println!("using provisioning profile {}", stored.summary());
+    for issue in profile_issues(&stored, Some(udid), Utc::now()) {
+        println!("WARNING: {}", issue);
+    }
+    let profile = &stored.profile;
+    if profile.is_expired(SystemTime::now()) {
+        println!("provisioning profile {} has expired and must be renewed", &profile.name);
         return Ok(false);
-    };
-    let array_content = &rest_after_open[..close_pos];
-    let device_entry = format!("<string>{}</string>", udid);
-    //check if the profile contains the device id
-    println!("checking if array content: {:?} contains device entry: {:?}", &array_content, &device_entry);
-    if array_content.contains(&device_entry) {
-        println!("provisioning profile contains the device id...checking device for installation");
-        //check that the profile is installed on the device
-        if let Some(key_pos) = xml.find("<key>Name</key>") {
-            if let Some(string_start) = xml[key_pos..].find("<string>") {
-                let start = key_pos + string_start + "<string>".len();
-                if let Some(string_end) = xml[start..].find("</string>") {
-                    let profile_name = xml[start..start + string_end].trim().to_string();
-                    if !profile_name.is_empty() {
-                        //list the installed provisions
-                        let output = Command::new(format!("{}/ideviceprovision", session.get_path("homebrew_path")?))
-                            .args(["list", "--udid", udid])
-                            .output()?;
-                        if !output.status.success() {
-                            return Err(io::Error::new(io::ErrorKind::Other, "failed to list provisioning profiles: {}",));
-                        }
-                        let profiles = String::from_utf8_lossy(&output.stdout);
-                        if profiles.contains(&profile_name) {
-                            println!("target device is already provisioned");
-                            return Ok(true)
-                        }else{
-                            println!("provisioning profile is not currently installed on the target device");
-                            return Ok(false)
-                        }
-                    }
-                }
-            }
-        }
-        return Err(io::Error::new(io::ErrorKind::Other, "Name not found in provisioning profile: {}",));
-    } else {
-        println!("target device is not provisioned");
-        return Ok(false)
-    }let profile_path_str = format!("{}/{}", &app_bundle_path, &mobileprovision_file);
-    let profile_path = Path::new(&profile_path_str);
-    //query the mobile provision profile
-    let output = Command::new("security")
-        .arg("cms")
-        .arg("-D")
-        .arg("-i")
-        .arg(profile_path)
-        .output()
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to execute security command: {}", e)))?;
-    if !output.status.success() {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!("security command failed: {}", String::from_utf8_lossy(&output.stderr)),
-        ));
     }
+    embed_profile(&stored, Path::new(app_bundle_path))?;
     //check for an existing device provision
-    let xml = String::from_utf8(output.stdout)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Invalid UTF-8 in plist: {}", e)))?;
-    let key_str = "<key>ProvisionedDevices</key>";
-    let Some(key_pos) = xml.find(key_str) else {
-        println!("provision profile does not contain valid syntax: <key>ProvisionedDevice</key>");
-        return Ok(false);
-    };
-    let start_after_key = key_pos + key_str.len();
-    let rest_after_key = &xml[start_after_key..];
-    let string_open = "<array>";
-    let Some(array_pos) = rest_after_key.find(string_open) else {
-        println!("provision profile does not contain valid syntax: <array>");
-        return Ok(false);
-    };
-    let start_of_array = array_pos + string_open.len();
-    let rest_after_open = &rest_after_key[start_of_array..];
-    let string_close = "</array>";
-    let Some(close_pos) = rest_after_open.find(string_close) else {
-        println!("provision profile does not contain valid syntax: </array>");
+    if !profile.contains_device(udid) {
+        println!("target device is not provisioned");
         return Ok(false);
-    };
-    let array_content = &rest_after_open[..close_pos];
-    let device_entry = format!("<string>{}</string>", udid);
-    //check if the profile contains the device id
-    println!("checking if array content: {:?} contains device entry: {:?}", &array_content, &device_entry);
-    if array_content.contains(&device_entry) {
-        println!("provisioning profile contains the device id...checking device for installation");
-        //check that the profile is installed on the device
-        if let Some(key_pos) = xml.find("<key>Name</key>") {
-            if let Some(string_start) = xml[key_pos..].find("<string>") {
-                let start = key_pos + string_start + "<string>".len();
-                if let Some(string_end) = xml[start..].find("</string>") {
-                    let profile_name = xml[start..start + string_end].trim().to_string();
-                    if !profile_name.is_empty() {
-                        //list the installed provisions
-                        let output = Command::new(format!("{}/ideviceprovision", session.get_path("homebrew_path")?))
-                            .args(["list", "--udid", udid])
-                            .output()?;
-                        if !output.status.success() {
-                            return Err(io::Error::new(io::ErrorKind::Other, "failed to list provisioning profiles: {}",));
-                        }
-                        let profiles = String::from_utf8_lossy(&output.stdout);
-                        if profiles.contains(&profile_name) {
-                            println!("target device is already provisioned");
-                            return Ok(true)
-                        }else{
-                            println!("provisioning profile is not currently installed on the target device");
-                            return Ok(false)
-                        }
-                    }
-                }
-            }
-        }
-        return Err(io::Error::new(io::ErrorKind::Other, "Name not found in provisioning profile: {}",));
+    }
+    println!("provisioning profile {} contains the device id...checking device for installation", &profile.name);
+    //check that the profile is installed on the device
+    let output = Command::new(format!("{}/ideviceprovision", session.get_path("homebrew_path")?))
+        .args(["list", "--udid", udid])
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(io::ErrorKind::Other, "failed to list provisioning profiles"));
+    }
+    let profiles = String::from_utf8_lossy(&output.stdout);
+    if profiles.contains(&profile.uuid) || profiles.contains(&profile.name) {
+        println!("target device is already provisioned");
+        Ok(true)
     } else {
-        println!("target device is not provisioned");
+        println!("provisioning profile is not currently installed on the target device");
         Ok(false)
     }
 }
@@ -1227,59 +965,13 @@ pub fn deploy_usb_tether(session: &mut Session, target_os: String) -> io::Result
         }
     }else if target_os == "android"{
         //android device tether deployment
-        let adb_path = format!("{}/adb", session.get_path("platform_tools_path")?);
-        if !is_android_device_connected(&adb_path){
-            return Err(io::Error::new(io::ErrorKind::Other, "no android device detected, or multiple devices connected"));
+        let device = selected_device(session)?;
+        let component = deploy_android_apk(session, &device)?;
+        //show the app's output in the android page's log panel
+        let package = component.split('/').next().unwrap_or(&component).to_string();
+        if let Err(e) = watch_app_logs(session, &device, &package) {
+            println!("WARNING: could not stream logs for {}: {}", &package, e);
         }
-        println!("one android device detected");
-        //obtain the apk_name & package value from the Cargo.toml
-        let cargo_toml = read_to_string(format!("{}/{}/Cargo.toml", session.projects_path.as_ref().unwrap(), session.current_project.as_ref().unwrap()))?;
-        let mut apk_name = String::new();
-        let mut package_name = String::new();
-        for line in cargo_toml.lines() {
-        let trim = line.trim();
-            if trim.starts_with("apk_name =") {
-                if let Some(start) = trim.find('=') {
-                    let value = &trim[start + 1..].trim();
-                    apk_name = value.trim_matches(|c| c == '"' || c == '\'').to_string();
-                }
-            }else if trim.starts_with("package =") {
-                if let Some(start) = trim.find('=') {
-                    let value = &trim[start + 1..].trim();
-                    package_name = value.trim_matches(|c| c == '"' || c == '\'').to_string();
-                }
-            }
-        }
-        if apk_name.is_empty() {
-            return Err(io::Error::new(io::ErrorKind::Other, "apk_name value not found in cargo.toml"));
-        }
-        if package_name.is_empty() {
-            return Err(io::Error::new(io::ErrorKind::Other, "package value not found in cargo.toml"));
-        }
-        println!("The APK name is: {}", apk_name);
-        println!("The package name is: {}", package_name);
-        //path to the apk
-        let apk_path = format!("{}/{}/target/debug/apk/{}.apk", session.projects_path.as_ref().unwrap(), session.current_project.as_ref().unwrap(), &apk_name);
-        println!("apk path: {}", apk_path);
-        //install the apk
-        let output = Command::new(&adb_path)
-            .args(["install", "-r", &apk_path])
-            .output()
-            .unwrap();
-        if !output.status.success() {
-            return Err(io::Error::new(io::ErrorKind::Other, "could not install the apk"));
-        }
-        println!("APK installed!");
-        //TODO obtain the abd_payload which needs the activity name?
-        // let adb_payload = get_adb_launch_payload(session, Path::new(&apk_path))?;
-        // println!("adb payload: {}", adb_payload);
-
-        //TODO launch the adb payload after valid install
-
-
-        //TODO need to determine the app bundle name and extension programmatically for auto launch
-        //we can launc the app with `adb shell am start -n "com.your.bundle.id/.MainActivity"`
-        //we can uninstall with `adb uninstall com.bundle.id`
     }
 
     println!("Successfully deployed to {} device", &target_os);
@@ -1288,170 +980,88 @@ pub fn deploy_usb_tether(session: &mut Session, target_os: String) -> io::Result
     
 }
 
-//TODO "main activity not found", might remove this LLM code
-pub fn get_adb_launch_payload(session: &mut Session, apk_path: &Path) -> Result<String, io::Error> {
-    // Run aapt to dump manifest as text tree
-    let aapt_path = format!("{}/aapt", session.get_path("build_tools_path")?);
-    let output = Command::new(&aapt_path)
-        .args(["dump", "xmltree", apk_path.to_str().unwrap(), "AndroidManifest.xml"])
-        .output()?;
-
+//install the debug apk on a device or emulator and launch it, returns the launched component
+pub fn deploy_android_apk(session: &mut Session, device: &AndroidDevice) -> io::Result<String> {
+    let adb_path = adb_path(session)?;
+    println!("deploying to android device {}", device.display_name());
+    //obtain the apk_name & package value from the Cargo.toml
+    let manifest = current_project_manifest(session)?;
+    let apk_name = manifest.android.apk_name.clone()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "apk_name value not found in [package.metadata.android]"))?;
+    let package_name = manifest.android_package()?;
+    println!("The APK name is: {}", apk_name);
+    println!("The package name is: {}", package_name);
+    //path to the apk
+    let apk_path = format!("{}/{}/target/debug/apk/{}.apk", session.projects_path.as_ref().unwrap(), session.current_project.as_ref().unwrap(), &apk_name);
+    println!("apk path: {}", apk_path);
+    //install the apk
+    let output = adb_command(&adb_path, &device.serial)
+        .args(["install", "-r", &apk_path])
+        .output()
+        .map_err(|e| io::Error::new(e.kind(), format!("Failed to run adb install: {}", e)))?;
     if !output.status.success() {
-        return Err(io::Error::new(io::ErrorKind::Other, "aapt command failed"));
+        return Err(io::Error::other(format!(
+            "could not install the apk: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
     }
-
+    println!("APK installed!");
+    //launch the installed app
+    let component = get_adb_launch_payload(session, Path::new(&apk_path))?;
+    println!("launching {}", &component);
+    let output = adb_command(&adb_path, &device.serial)
+        .args(["shell", "am", "start", "-n", &component])
+        .output()?;
+    //am start reports a missing activity on stdout with a zero exit code
     let stdout = String::from_utf8_lossy(&output.stdout);
-    let reader = BufReader::new(stdout.as_bytes());
-    let lines: Vec<String> = reader.lines().filter_map(Result::ok).collect();
-
-    let mut package = None;
-    let mut main_activity = None;
-
-    for line in lines.iter() {
-        // Extract package from root <manifest> attribute
-        if line.trim().starts_with("A: package=") {
-            if let Some(start) = line.find('\"') {
-                if let Some(end) = line[start + 1..].find('\"') {
-                    package = Some(line[start + 1..start + 1 + end].to_string());
-                }
-            }
-        }
-
-        // Find main activity: Look for <intent-filter> with MAIN and LAUNCHER under <activity>
-        if main_activity.is_none() && line.trim().starts_with("E: activity") {
-            let mut in_activity = true;
-            let mut activity_name = None;
-            let mut has_main = false;
-            let mut has_launcher = false;
-
-            // Parse sub-lines for this activity block
-            let activity_idx = lines.iter().position(|l| l == line).unwrap_or(0);
-            for sub_line in lines.iter().skip(activity_idx + 1) {
-                let trim_sub = sub_line.trim();
-                if trim_sub.starts_with("A: android:name") {
-                    if let Some(start) = sub_line.find('\"') {
-                        if let Some(end) = sub_line[start + 1..].find('\"') {
-                            activity_name = Some(sub_line[start + 1..start + 1 + end].to_string());
-                        }
-                    }
-                } else if trim_sub.starts_with("E: intent-filter") {
-                    // Check for MAIN action and LAUNCHER category in this filter
-                    let filter_idx = lines.iter().position(|l| l == sub_line).unwrap_or(0);
-                    for filter_line in lines.iter().skip(filter_idx + 1) {
-                        let trim_filter = filter_line.trim();
-                        if trim_filter.starts_with("E: ") && !trim_filter.starts_with("E: action") && !trim_filter.starts_with("E: category") {
-                            break; // End of this intent-filter
-                        }
-                        if trim_filter.contains("android.intent.action.MAIN") {
-                            has_main = true;
-                        }
-                        if trim_filter.contains("android.intent.category.LAUNCHER") {
-                            has_launcher = true;
-                        }
-                    }
-                } else if trim_sub.starts_with("E: ") {
-                    in_activity = false; // End of activity block
-                    break;
-                }
-            }
-
-            if has_main && has_launcher {
-                if let Some(name) = activity_name {
-                    let full_name = if name.starts_with('.') {
-                        format!("{}{}", package.as_ref().unwrap_or(&String::new()), name)
-                    } else {
-                        name
-                    };
-                    main_activity = Some(full_name);
-                }
-            }
-        }
-
-        if package.is_some() && main_activity.is_some() {
-            break;
-        }
+    if !output.status.success() || stdout.contains("Error:") {
+        return Err(io::Error::other(format!(
+            "could not launch {}: {}{}",
+            &component,
+            stdout,
+            String::from_utf8_lossy(&output.stderr)
+        )));
     }
-
-    let pkg = package.ok_or(io::Error::new(io::ErrorKind::NotFound, "Package not found"))?;
-    let act = main_activity.ok_or(io::Error::new(io::ErrorKind::NotFound, "Main activity not found"))?;
-
-    Ok(format!("-n \"{}/{}\"", pkg, act))
+    //the app can be removed with `ramp_gui -android-action uninstall <project>`
+    Ok(component)
 }
 
-pub fn is_android_device_connected(adb_path: &str) -> bool {
-    println!("adb path: {}", adb_path);
-    let output = match Command::new(adb_path)
-        .arg("devices")
-        .output() {
-        Ok(out) => out,
-        Err(_) => return false,
+//the `am start` component for an apk, from its compiled manifest or failing that the project's Cargo.toml
+pub fn get_adb_launch_payload(session: &Session, apk_path: &Path) -> Result<String, io::Error> {
+    let target = match launch_target_from_apk(apk_path) {
+        Ok(target) => target,
+        Err(e) => {
+            println!("could not read the launcher activity from {}: {}, falling back to Cargo.toml", apk_path.display(), e);
+            launch_target_from_manifest(&current_project_manifest(session)?)?
+        }
     };
+    Ok(target.component())
+}
 
-    if !output.status.success() {
-        println!("failed to run adb devices");
-        return false;
+//this needs to get called when creating a new project on macos/ios
+pub fn create_app_bundle_id(session: &mut Session) -> io::Result<()> {
+    if session.os.as_str() != "macos"{
+        return Ok(())
     }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let lines: Vec<&str> = stdout.lines().collect();
-
-    // Skip the header line "List of devices attached"
-    let device_lines: Vec<&str> = lines.into_iter()
-        .skip(1) // Skip header
-        .filter(|line| line.trim().ends_with("device")) // Only count authorized "device" status
-        .collect();
-
-    device_lines.len() == 1
+    //the bundle id is chosen and registered in the developer portal with the bundle id wizard, which applies it when finished
+    let wizard = Wizard::new(WizardKind::BundleId).with_value("project", session.current_project.as_ref().unwrap());
+    if let WizardOutcome::Pending = run_wizard(session, wizard)? {
+        println!("using bundle_id: com.ramp.{} until the bundle id wizard is finished", session.current_project.as_ref().unwrap());
+    }
+    Ok(())
 }
 
-//this needs to get called when creating a new project on macos/ios
-pub fn create_app_bundle_id(session: &mut Session) -> io::Result<Option<String>> {
-        if session.os.as_str() != "macos"{
-            return Ok(None)
-        }
-        //open apple developer portal
-        let output = Command::new("open")
-        .args(["-a", "safari", "https://developer.apple.com/account/resources/identifiers/list/bundleId"])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .output()
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to open Apple developer portal: {}", e)))?;
-        if !output.status.success() {
-            return Err(io::Error::new(io::ErrorKind::Other, "Failed to open Apple developer portal"));
-        }    
-
-        //take in app bundle id here
-        let mut bundle_id = String::new();
-        println!("*********************************************");
-        println!("Please enter your app bundle ID. Press enter for default (Reccomended) which will be \"com.ramp.{}\"", session.current_project.as_ref().unwrap());
-        println!("*********************************************");
-        io::stdin()
-            .read_line(&mut bundle_id)
-            .expect("failed to read line");
-        let bundle_id = bundle_id.trim();
-        let bundle_id = if bundle_id.is_empty() {
-            format!("com.ramp.{}", session.current_project.as_ref().unwrap())
-        }else {
-            bundle_id.to_string()
-        };
-        println!("using bundle_id: {}", &bundle_id);
-
-        println!("**********************************");   
-        //TODO eventually replace these prints in the GUI
-        println!("1. Now go to the safari window and login to your developer account.");
-        println!("2. Once you have logged in, click the + button next to \"Identifiers\".");
-        println!("3. Select \"App IDs\" and click the continue button.");
-        println!("4. Select \"App\" for the type and click the continue button.");
-        println!("5. Copy and paste your App Bundle ID shown below into the \"Bundle ID\" box.");
-        println!("{}", &bundle_id);
-        println!("6. Give your app whatever description you like and then press the continue button.");
-        println!("7. Click the Register button.");
-    
-        if bundle_id == format!("com.ramp.{}", session.current_project.as_ref().unwrap()){
-            Ok(None)
-        }else {
-            Ok(Some(bundle_id))
-        }
+//switch the project's ios bundle id and android application id to a registered bundle id
+pub fn apply_bundle_id(session: &mut Session, bundle_id: &str) -> io::Result<()> {
+    let project = session.current_project.as_ref().unwrap();
+    let project_path = format!("{}/{}", session.projects_path.as_ref().unwrap(), project);
+    //android application ids can't contain dashes
+    edit_manifest(&project_path, |manifest| manifest.set_android_package(&bundle_id.replace('-', "_")))?;
+    let plist_path = format!("{}/ios/{}.app/Info.plist", &project_path, capitalize_first(project));
+    let plist = set_plist_string(&fs::read_to_string(&plist_path)?, "CFBundleIdentifier", bundle_id)?;
+    fs::write(&plist_path, plist)?;
+    println!("using bundle_id: {}", bundle_id);
+    Ok(())
 }
 
 pub fn build_output(session: &mut Session, target_os: String, release: bool) -> io::Result<()> {
@@ -1501,6 +1111,13 @@ pub fn build_output(session: &mut Session, target_os: String, release: bool) ->
             "android_run" => format!(
                 "{}/target/debug/apk/{}.apk", &project_path, capitalize_first(session.current_project.as_ref().unwrap())
             ),
+            //app bundles are for play store submission
+            "android_bundle" => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "android app bundles are only built for release",
+                ))
+            },
             "ios" => if session.os.as_str() == "macos" {format!(
                 "{}/target/aarch64-apple-ios/debug/{} ...if you are looking for the full app bundle check the ramp/{}/ios directory", &project_path, session.current_project.as_ref().unwrap(), session.current_project.as_ref().unwrap()
             )} else {
@@ -1509,14 +1126,8 @@ pub fn build_output(session: &mut Session, target_os: String, release: bool) ->
                     format!("Unsupported target OS: {}", target_os),
                 ))
             },
-            "macos" => if session.os.as_str() == "macos" {format!(
-                "{}/target/debug/{}", &project_path, session.current_project.as_ref().unwrap()
-                )}  else {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidInput,
-                        format!("Unsupported target OS: {}", target_os),
-                    ))
-                },
+            //the bundle is assembled in rust, so like release builds this works wherever the apple targets are installed
+            "macos" => app_bundle_path(session),
             _ => {
                 return Err(io::Error::new(
                     io::ErrorKind::InvalidInput,
@@ -1555,13 +1166,22 @@ pub fn build_output(session: &mut Session, target_os: String, release: bool) ->
             "apk run{}",
             if release { " --release " } else { " --lib " }
         ),
+        //app bundles are for play store submission, so always a release build of every android abi
+        "android_bundle" => format!(
+            "apk build --release --target {} --target {} --target {}",
+            ANDROID_TARGETS[0],
+            ANDROID_TARGETS[1],
+            ANDROID_TARGETS[2]
+        ),
         "ios" => format!(
             "build --target aarch64-apple-ios{}",
             if release { " --release " } else { "" }
         ),
-        //TODO need to support lipo outputs for combined chipset architecture
+        //build both chipset architectures, they are combined into a universal binary in post build
         "macos" => format!(
-            "build{}",
+            "build --target {} --target {}{}",
+            MACOS_TARGETS[0],
+            MACOS_TARGETS[1],
             if release { " --release " } else { "" }
         ),
         _ => {
@@ -1577,7 +1197,26 @@ pub fn build_output(session: &mut Session, target_os: String, release: bool) ->
     let current_path = env::var("PATH").unwrap_or_default();
     let new_path = format!("{}:{}", session.get_path("homebrew_path")?, current_path);
     println!("building for {} on {}", &target_os, session.os);
-    let output = if target_os.as_str() == "android" || target_os.as_str() == "android_run"{
+    //android release builds are signed with the project's upload key
+    let is_android = matches!(target_os.as_str(), "android" | "android_run" | "android_bundle");
+    let signing_env = if release && is_android {
+        release_signing_env(&ensure_android_keystore(session)?)
+    } else {
+        Vec::new()
+    };
+    //android apks are built per selected abi and packaging layout by the apk module
+    if target_os == "android" {
+        let artifacts = build_android_apks(session, release, &signing_env)?;
+        println!(
+            "Successfully built project at {} for target {} ({} mode)\n{}",
+            project_path,
+            target_os,
+            if release { "release" } else { "debug" },
+            size_report(&artifacts)
+        );
+        return Ok(());
+    }
+    let output = if is_android {
                     //building for android
                     Command::new("bash")
                     .arg("-c")
@@ -1587,6 +1226,7 @@ pub fn build_output(session: &mut Session, target_os: String, release: bool) ->
                     .env("JAVA_HOME", &session.get_path("java_path")?)                
                     .env("ANDROID_HOME", &session.get_path("sdk_path")?)
                     .env("NDK_HOME", &session.get_path("ndk_path")?)
+                    .envs(signing_env)
                     .stdout(Stdio::inherit()) // Show build output
                     .stderr(Stdio::inherit())
                     .output()?
@@ -1650,20 +1290,50 @@ pub fn build_output(session: &mut Session, target_os: String, release: bool) ->
         }else if target_os == "android" {
             println!("TODO android debug postbuild");
         }else if target_os == "windows" {
-            println!("TODO windows debug postbuild");
-            //TODO windows post build
-            //icons & app bundle
+            println!("performing windows post build...");
+            let package_path = package_windows(session, release)?;
+            println!("post build complete; windows packages available at {}", package_path);
         }else if target_os == "macos" {
-            println!("TODO macos debug postbuild");
-            //TODO macos post build
-            //combine chipset architecture
-            //icons and app bundle 
+            println!("performing macos post build...");
+            let bundle_path = macos_post_build(session, release)?;
+            sign_build(session, &target_os, release)?;
+            println!("post build complete; universal app bundle available at {}", bundle_path);
         }else if target_os == "wasm"{
-            println!("TODO wasm debug postbuild");
+            println!("performing wasm post build...");
+            let dist_path = wasm_post_build(session, release)?;
+            println!("post build complete; web build available at {}", dist_path);
         }else if target_os == "linux"{
             println!("TODO linux debug postbuild");
         }
     //release post build
+    } else if target_os == "macos" {
+        println!("performing macos release post build...");
+        let bundle_path = macos_post_build(session, release)?;
+        sign_build(session, &target_os, release)?;
+        let artifacts = package_macos(session)?;
+        println!("post build complete; universal app bundle available at {}", bundle_path);
+        println!("distribution packages: {}", artifacts.join(", "));
+        //notarizing can take apple up to an hour, so it's a separate step rather than part of the build
+        println!("notarize and repackage with ramp_gui -notarize {}", session.current_project.as_ref().unwrap());
+    } else if target_os == "windows" {
+        println!("performing windows release post build...");
+        let package_path = package_windows(session, release)?;
+        println!("post build complete; windows packages available at {}", package_path);
+    } else if target_os == "android_run" {
+        println!("performing android release post build...");
+        let apk_path = format!(
+            "{}/target/release/apk/{}.apk", &project_path, capitalize_first(session.current_project.as_ref().unwrap())
+        );
+        let signer = verify_apk_signature(session, &apk_path)?;
+        println!("post build complete; signed apk available at {}\n{}", apk_path, signer);
+    } else if target_os == "android_bundle" {
+        println!("performing android app bundle post build...");
+        let aab_path = android_bundle_post_build(session)?;
+        println!("post build complete; signed app bundle ready for play upload at {}", aab_path);
+    } else if target_os == "wasm" {
+        println!("performing wasm release post build...");
+        let dist_path = wasm_post_build(session, release)?;
+        println!("post build complete; web build available at {}", dist_path);
     } else {
         println!("TODO post build for release");
     }
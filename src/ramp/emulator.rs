@@ -0,0 +1,312 @@
+use super::aab::android_abi;
+use super::apk::AndroidBuildOptions;
+use super::adb::{adb_command, adb_path, list_devices, AndroidDevice};
+use super::core::deploy_android_apk;
+use super::session::Session;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+//hardware profile for new avds, available in every avdmanager release
+pub const DEFAULT_DEVICE_PROFILE: &str = "pixel_6";
+
+//adb only discovers emulators on even ports in this range
+const FIRST_CONSOLE_PORT: u16 = 5554;
+const LAST_CONSOLE_PORT: u16 = 5682;
+
+//an android virtual device as listed by `avdmanager list avd`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Avd {
+    pub name: String,
+    pub device: Option<String>,
+    pub path: Option<String>,
+    pub target: Option<String>,
+    //tag/abi of the system image, e.g. google_apis/x86_64
+    pub tag_abi: Option<String>,
+}
+
+//a booted emulator, adb addresses it as emulator-<port>
+#[derive(Debug)]
+pub struct EmulatorInstance {
+    pub avd: String,
+    pub serial: String,
+    pub process: Option<Child>,
+}
+
+//system image abi matching the host, x86 images need kvm and arm images need an arm host
+pub fn host_image_abi() -> &'static str {
+    if cfg!(target_arch = "aarch64") { "arm64-v8a" } else { "x86_64" }
+}
+
+pub fn system_image_package(api_level: &str, abi: &str) -> String {
+    format!("system-images;android-{};google_apis;{}", api_level, abi)
+}
+
+pub fn default_avd_name(session: &Session) -> String {
+    format!("ramp_api_{}", session.android_platform_version)
+}
+
+fn sdk_tool(session: &mut Session, tool: &str) -> io::Result<String> {
+    let sdkmanager = session.get_path("sdkmanager_path")?;
+    Ok(Path::new(&sdkmanager).with_file_name(tool).to_string_lossy().to_string())
+}
+
+fn emulator_path(session: &mut Session) -> io::Result<String> {
+    Ok(format!("{}/emulator/emulator", session.get_path("sdk_path")?))
+}
+
+fn run_sdk_tool(session: &mut Session, tool: &str, args: &[&str], input: Option<&str>) -> io::Result<String> {
+    let mut child = Command::new(sdk_tool(session, tool)?)
+        .args(args)
+        .env("JAVA_HOME", session.get_path("java_path")?)
+        .env("ANDROID_HOME", session.get_path("sdk_path")?)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::other(format!("Failed to run {}: {}", tool, e)))?;
+    if let Some(input) = input {
+        //a closed pipe just means the tool didn't ask anything
+        let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    }
+    drop(child.stdin.take());
+    let output = child.wait_with_output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{} {} failed: {}{}",
+            tool,
+            args.first().unwrap_or(&""),
+            stdout,
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(stdout)
+}
+
+//install the emulator and a system image with sdkmanager, accepting their licenses
+pub fn install_system_image(session: &mut Session, package: &str) -> io::Result<()> {
+    let sdk_root = format!("--sdk_root={}", session.get_path("sdk_path")?);
+    println!("Installing the android emulator and {}...", package);
+    run_sdk_tool(session, "sdkmanager", &["emulator", package, &sdk_root], Some(&"y\n".repeat(20)))?;
+    Ok(())
+}
+
+//parse the output of `avdmanager list avd`
+pub fn parse_avd_list(output: &str) -> Vec<Avd> {
+    let mut avds = Vec::new();
+    let mut current: Option<Avd> = None;
+    for line in output.lines() {
+        let trim = line.trim();
+        if let Some(name) = trim.strip_prefix("Name:") {
+            avds.extend(current.take());
+            current = Some(Avd { name: name.trim().to_string(), ..Avd::default() });
+            continue;
+        }
+        let Some(avd) = current.as_mut() else { continue };
+        if let Some(device) = trim.strip_prefix("Device:") {
+            avd.device = Some(device.trim().to_string());
+        } else if let Some(path) = trim.strip_prefix("Path:") {
+            avd.path = Some(path.trim().to_string());
+        } else if let Some(target) = trim.strip_prefix("Target:") {
+            avd.target = Some(target.trim().to_string());
+        } else if let Some((_, tag_abi)) = trim.split_once("Tag/ABI:") {
+            avd.tag_abi = Some(tag_abi.trim().to_string());
+        } else if trim.starts_with("---") {
+            avds.extend(current.take());
+        }
+    }
+    avds.extend(current);
+    avds
+}
+
+pub fn list_avds(session: &mut Session) -> io::Result<Vec<Avd>> {
+    Ok(parse_avd_list(&run_sdk_tool(session, "avdmanager", &["list", "avd"], None)?))
+}
+
+//create an avd from a system image, installing the image first if needed
+pub fn create_avd(session: &mut Session, name: &str, package: &str) -> io::Result<()> {
+    let image_dir = format!("{}/{}", session.get_path("sdk_path")?, package.replace(';', "/"));
+    if !Path::new(&image_dir).exists() || !Path::new(&emulator_path(session)?).exists() {
+        install_system_image(session, package)?;
+    }
+    //avdmanager asks whether to create a custom hardware profile
+    run_sdk_tool(
+        session,
+        "avdmanager",
+        &["create", "avd", "-n", name, "-k", package, "-d", DEFAULT_DEVICE_PROFILE, "--force"],
+        Some("no\n"),
+    )?;
+    println!("created avd {}", name);
+    Ok(())
+}
+
+pub fn delete_avd(session: &mut Session, name: &str) -> io::Result<()> {
+    run_sdk_tool(session, "avdmanager", &["delete", "avd", "-n", name], None)?;
+    println!("deleted avd {}", name);
+    Ok(())
+}
+
+//the next console port not used by a running emulator
+pub fn free_console_port(devices: &[AndroidDevice]) -> Option<u16> {
+    (FIRST_CONSOLE_PORT..=LAST_CONSOLE_PORT)
+        .step_by(2)
+        .find(|port| !devices.iter().any(|d| d.serial == format!("emulator-{}", port)))
+}
+
+//hardware acceleration is only available with kvm on linux, without it the emulator falls back to slow software cpu emulation
+pub fn has_hardware_acceleration(os: &str) -> bool {
+    os != "linux" || Path::new("/dev/kvm").exists()
+}
+
+//the avd a running emulator was started from
+fn running_avd_name(adb_path: &str, serial: &str) -> Option<String> {
+    let output = adb_command(adb_path, serial).args(["emu", "avd", "name"]).output().ok()?;
+    String::from_utf8_lossy(&output.stdout).lines().next().map(|l| l.trim().to_string())
+}
+
+//the serial of an already running emulator for this avd
+pub fn find_running_emulator(session: &mut Session, avd: &str) -> io::Result<Option<String>> {
+    let adb_path = adb_path(session)?;
+    Ok(list_devices(&adb_path)?
+        .into_iter()
+        .filter(|d| d.is_emulator())
+        .find(|d| running_avd_name(&adb_path, &d.serial).as_deref() == Some(avd))
+        .map(|d| d.serial))
+}
+
+//start an avd without a window using software rendering so it runs on machines without a gpu
+pub fn boot_emulator(session: &mut Session, avd: &str) -> io::Result<EmulatorInstance> {
+    if let Some(serial) = find_running_emulator(session, avd)? {
+        println!("{} is already running as {}", avd, serial);
+        return Ok(EmulatorInstance { avd: avd.to_string(), serial, process: None });
+    }
+    let adb_path = adb_path(session)?;
+    let port = free_console_port(&list_devices(&adb_path)?)
+        .ok_or_else(|| io::Error::other("no free emulator console port, stop a running emulator first"))?;
+    let port_arg = port.to_string();
+    let mut args = vec![
+        "-avd", avd, "-port", &port_arg, "-no-window", "-no-audio", "-no-boot-anim",
+        "-gpu", "swiftshader_indirect", "-no-snapshot-save",
+    ];
+    if !has_hardware_acceleration(&session.os) {
+        println!("WARNING: /dev/kvm not found, booting without hardware acceleration will be slow");
+        args.extend(["-accel", "off"]);
+    }
+    let process = Command::new(emulator_path(session)?)
+        .args(&args)
+        .env("ANDROID_HOME", session.get_path("sdk_path")?)
+        .env("ANDROID_SDK_ROOT", session.get_path("sdk_path")?)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| io::Error::other(format!("Failed to start the emulator: {}", e)))?;
+    println!("booting {} on port {}", avd, port);
+    Ok(EmulatorInstance { avd: avd.to_string(), serial: format!("emulator-{}", port), process: Some(process) })
+}
+
+//poll sys.boot_completed until the emulator is usable
+pub fn wait_for_boot(session: &mut Session, instance: &mut EmulatorInstance, timeout: Duration) -> io::Result<()> {
+    let adb_path = adb_path(session)?;
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if let Some(process) = instance.process.as_mut() {
+            if let Some(status) = process.try_wait()? {
+                return Err(io::Error::other(format!("the emulator for {} exited during boot ({})", instance.avd, status)));
+            }
+        }
+        let booted = adb_command(&adb_path, &instance.serial)
+            .args(["shell", "getprop", "sys.boot_completed"])
+            .output()
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim() == "1")
+            .unwrap_or(false);
+        if booted {
+            println!("{} booted in {}s", instance.avd, start.elapsed().as_secs());
+            return Ok(());
+        }
+        sleep(Duration::from_secs(2));
+    }
+    Err(io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!("{} did not finish booting within {}s", instance.avd, timeout.as_secs()),
+    ))
+}
+
+pub fn stop_emulator(session: &mut Session, serial: &str) -> io::Result<()> {
+    adb_command(&adb_path(session)?, serial).args(["emu", "kill"]).output()?;
+    Ok(())
+}
+
+//boot the avd (creating the default one if it doesn't exist) and deploy the debug apk to it
+pub fn deploy_to_emulator(session: &mut Session, avd: Option<&str>) -> io::Result<EmulatorInstance> {
+    let name = avd.map(|a| a.to_string()).unwrap_or_else(|| default_avd_name(session));
+    if !list_avds(session)?.iter().any(|a| a.name == name) {
+        if avd.is_some() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no avd named {}", name)));
+        }
+        let package = system_image_package(&session.android_platform_version, host_image_abi());
+        create_avd(session, &name, &package)?;
+    }
+    //the debug apk only runs if it contains libraries for the image's abi
+    let abi = host_image_abi();
+    if !AndroidBuildOptions::load(session)?.targets.iter().any(|t| android_abi(t) == Some(abi)) {
+        println!("WARNING: the {} abi is not selected on the Android page, the app will not start on the emulator", abi);
+    }
+    let mut instance = boot_emulator(session, &name)?;
+    let timeout = if has_hardware_acceleration(&session.os) { 300 } else { 1200 };
+    wait_for_boot(session, &mut instance, Duration::from_secs(timeout))?;
+
+    let device = list_devices(&adb_path(session)?)?
+        .into_iter()
+        .find(|d| d.serial == instance.serial)
+        .ok_or_else(|| io::Error::other(format!("{} is not visible to adb", instance.serial)))?;
+    deploy_android_apk(session, &device)?;
+    Ok(instance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ramp::adb::parse_devices;
+
+    #[test]
+    fn parses_avdmanager_output() {
+        let avds = parse_avd_list(include_str!("../../tests/fixtures/android/avd_list.txt"));
+        assert_eq!(
+            avds[0],
+            Avd {
+                name: "Pixel_6_API_34".to_string(),
+                device: Some("pixel_6 (Google)".to_string()),
+                path: Some("/home/dev/.android/avd/Pixel_6_API_34.avd".to_string()),
+                target: Some("Google APIs (Google Inc.)".to_string()),
+                tag_abi: Some("google_apis/x86_64".to_string()),
+            }
+        );
+        //the device line is missing for avds created without a hardware profile
+        assert_eq!(avds[1].name, "ramp_arm");
+        assert_eq!(avds[1].device, None);
+        assert_eq!(avds[1].tag_abi.as_deref(), Some("default/arm64-v8a"));
+        //avds that failed to load are still listed so they can be deleted
+        assert_eq!(avds[2].name, "broken");
+        assert_eq!(avds[2].target, None);
+        assert_eq!(avds.len(), 3);
+        assert!(parse_avd_list("Available Android Virtual Devices:\n").is_empty());
+    }
+
+    #[test]
+    fn picks_the_next_free_console_port() {
+        assert_eq!(free_console_port(&[]), Some(5554));
+        let running = parse_devices("List of devices attached\nemulator-5554\tdevice\nemulator-5556\toffline\n");
+        assert_eq!(free_console_port(&running), Some(5558));
+        //physical devices and a gap in the emulator ports
+        let running = parse_devices("List of devices attached\nR58M123ABC\tdevice\nemulator-5556\tdevice\n");
+        assert_eq!(free_console_port(&running), Some(5554));
+        let full: Vec<AndroidDevice> = (FIRST_CONSOLE_PORT..=LAST_CONSOLE_PORT)
+            .step_by(2)
+            .flat_map(|port| parse_devices(&format!("emulator-{}\tdevice\n", port)))
+            .collect();
+        assert_eq!(free_console_port(&full), None);
+    }
+}
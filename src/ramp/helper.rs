@@ -49,4 +49,20 @@ pub fn capitalize_first(s: &str) -> String {
         None => String::new(),
         Some(first) => first.to_uppercase() + &s[1..],
     }
-}
\ No newline at end of file
+}
+//an empty scratch directory for a test, unique to the test name and process
+#[cfg(test)]
+pub fn test_dir(name: &str) -> std::path::PathBuf {
+    let dir = env::temp_dir().join(format!("ramp-test-{}-{}", name, std::process::id()));
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+//a session whose .ramp config lives in a scratch home directory
+#[cfg(test)]
+pub fn test_session(name: &str) -> super::session::Session {
+    super::session::Session { home: test_dir(name).display().to_string(), ..Default::default() }
+}
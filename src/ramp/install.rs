@@ -1,5 +1,7 @@
 use super::helper::{is_command_available, is_xcode_tools_installed, get_user_home};
-use super::session::{Session};
+use super::session::{Session, SigningIdentity};
+use super::aab::BUNDLETOOL_VERSION;
+use super::wizard::{run_wizard, Wizard, WizardKind, WizardOutcome};
 use std::env;
 use std::fs;
 use std::fs::File;
@@ -65,8 +67,17 @@ pub fn install() -> io::Result<()> {
     //install android toolchains
     install_android_toolchains(&mut session)?;
 
+    //install wasm web toolchain
+    install_wasm_toolchain(&mut session)?;
+
+    //install windows cross compilation and packaging tools
+    install_windows_toolchain(&mut session)?;
+
+    //secure credential store for android release keystores
+    install_secret_store(&session)?;
+
     //setup keychain
-    setup_keychain(&mut session)?;
+    setup_keychain(&mut session, SigningIdentity::Development)?;
 
     //TODO install and configure simulators
     // install_simulators(session)?;
@@ -663,239 +674,194 @@ pub fn install_macos_ios_toolchains(session: &mut Session) -> io::Result<()> {
 // }
 
 //configure a keychain profile for signing apps for distribution
-pub fn setup_keychain(session: &mut Session) -> io::Result<()>{
-    //TODO this currently creates a debug signing certificate only, different certificate properties must be configured for release on apple's developer website
+//create the development, distribution or Developer ID certificate, each one is set up on its own
+pub fn setup_keychain(session: &mut Session, identity: SigningIdentity) -> io::Result<()>{
     println!("keychain installer");
     if session.os.as_str() == "macos"{
-        //check if keychain is locked, if so, unlock
-        loop{
-            let output = Command::new("security")
-                .args(["show-keychain-info", &format!("{}/login.keychain-db", session.get_path("keystore_path")?)])
-                .output()
-                .unwrap();
-            
-            if output.status.success() && !String::from_utf8_lossy(&output.stdout).contains("locked") {
-                break;
-            }else{
-                // wait 3 seconds
-                sleep(Duration::from_secs(3));
-            }
+        if has_signing_identity(identity.common_name())? {
+            println!("{} identity already in the keychain", identity.common_name());
+            return Ok(());
         }
-
-        //TODO check if identity exists, if so check if certificate is trusted, if so return Ok(())?
-        //security find-identity?
-
-        //TODO take in the users email and pass it into this function
-        //FOR now use this terminal input placeholder
-        let mut email = String::new();
-        println!("*********************************************");
-        println!("Please enter your apple developer email. This email must be associated with an apple developer account.");
-        println!("*********************************************");
-        io::stdin()
-            .read_line(&mut email)
-            .expect("failed to read line");
-        let email = email.trim();
-        println!("using email: {}", &email);
-
-        let mut full_name = String::new();
-        println!("*********************************************");
-        println!("Please enter your full name. This must be the legal name associated with your apple developer account.");
-        println!("*********************************************");
-        io::stdin()
-            .read_line(&mut full_name)
-            .expect("failed to read line");
-        let full_name = full_name.trim();
-        println!("using name: {}", &full_name);
-
-        let mut org = String::new();
-        println!("*********************************************");
-        println!("Please enter your organization's name. This must be the organization's name associated with your apple developer account. (You may leave this blank if indvidual)");
-        println!("*********************************************");
-        io::stdin()
-            .read_line(&mut org)
-            .expect("failed to read line");
-        let org = org.trim();
-        println!("using name: {}", &org);
-
-        println!("setting up keychain for macos");
-        //check if the key already exists and if it does do not generate a new one
-        match &session.paths.keystore_path {
-            Some(val) => {
-                println!("keystore path is already set");
-            },
-            None => {
-                println!("keystore path not set");
-                session.set_path("keystore_path", format!("{}/Library/Keychains", session.home))?;
-            },
+        //the developer details and portal steps run as a wizard, in the terminal or on the wizard page
+        //a saved wizard for another certificate is replaced, the signing key and CSR are shared
+        let wizard = Wizard::load(session, WizardKind::Keychain)?
+            .filter(|w| w.value("identity") == Some(identity.as_str()))
+            .unwrap_or_else(|| Wizard::new(WizardKind::Keychain).with_value("identity", identity.as_str()));
+        if let WizardOutcome::Finished = run_wizard(session, wizard)? {
+            println!("Successfully set up the {} identity in the keychain!", identity.common_name())
         }
-        //check if the keypath exists, if not generate a new key
-        let key_path = format!("{}/ramp.pem", session.get_path("keystore_path")?);
-        if !Path::new(&key_path).exists() {
-            println!("no private key found, generating new key");
-             //generate the signing key
-            let output = Command::new("openssl")
-            .args([
-                "genrsa",
-                "-out",
-                &key_path,
-                "2048"
-            ])
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .output()
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to generate private key: {}", e)))?;
-            if !output.status.success() {
-                return Err(io::Error::new(io::ErrorKind::Other, "OpenSSL key generation failed"));
-            }
-            //security import the private key into the keychain
-            let output = Command::new("security")
-            .args(["import", &format!("{}/ramp.pem", session.get_path("keystore_path")?), "-k", &format!("{}/login.keychain-db", session.get_path("keystore_path")?), "-T", "/usr/bin/codesign"])
-            .output()
-            .unwrap();
-            if !output.status.success() {
-                return Err(io::Error::new(io::ErrorKind::Other, "Failed to import the ramp.pem to the keychain-db"));
-            }
-        }else {println!("ramp.pem already exists");}
-        //check if the CSR exists, if not generate a new CSR
-        let csr_path = format!("{}/ramp.csr", session.get_path("keystore_path")?);
-        if !Path::new(&csr_path).exists() {
-            let mut subject = String::new();
-            if org == "" {
-                subject = format!("/CN={} /emailAddress={}", full_name, email);
-            } else {
-                subject = format!("/CN={} /O={} /emailAddress={}", full_name, org, email);
-            }
-            // Generate a CSR
-            let output = Command::new("openssl")
-            .args([
-                "req",
-                "-new",
-                "-key",
-                &key_path,
-                "-out",
-                &csr_path,
-                "-subj",
-                &subject,
-            ])
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .output()
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to generate CSR: {}", e)))?;
-            if !output.status.success() {
-                return Err(io::Error::new(io::ErrorKind::Other, "OpenSSL CSR generation failed"));
-            }
-        }else{println!("ramp.csr already exists");}
+    }
+    //TODO add support for all other target builds
+    Ok(())
+}
 
-        //open apple developer portal
-        let output = Command::new("open")
-            .args(["-a", "safari", "https://developer.apple.com/account/resources/certificates/list"])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .output()
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to open Apple developer portal: {}", e)))?;
-        if !output.status.success() {
-            return Err(io::Error::new(io::ErrorKind::Other, "Failed to open Apple developer portal"));
-        }       
-        //open the file explorer to show the CSR
-        let output = Command::new("open")
-            .arg(session.get_path("keystore_path")?)
-            .output()
-            .unwrap();
+//true once the login keychain is unlocked
+pub fn keychain_unlocked(session: &Session) -> io::Result<bool> {
+    //the keystore path is only set once the signing key is generated
+    let keystore_path = session.paths.keystore_path.clone().unwrap_or_else(|| format!("{}/Library/Keychains", session.home));
+    let output = Command::new("security")
+        .args(["show-keychain-info", &format!("{}/login.keychain-db", keystore_path)])
+        .output()?;
+    Ok(output.status.success() && !String::from_utf8_lossy(&output.stdout).contains("locked"))
+}
+
+//the names of the valid codesigning identities in the keychains
+pub fn signing_identities() -> io::Result<Vec<String>> {
+    let output = Command::new("security").args(["find-identity", "-v", "-p", "codesigning"]).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("Failed to list signing identities: {}", String::from_utf8_lossy(&output.stderr))));
+    }
+    //lines look like: 1) <sha1> "Apple Development: Name (TEAMID)"
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once('"').and_then(|(_, rest)| rest.rsplit_once('"')).map(|(name, _)| name.to_string()))
+        .collect())
+}
+
+//true if an identity codesign would accept for the name is installed
+pub fn has_signing_identity(name: &str) -> io::Result<bool> {
+    Ok(signing_identities()?.iter().any(|identity| identity.contains(name)))
+}
+
+//generate the ramp signing key and the certificate signing request uploaded to the developer portal
+pub fn create_signing_request(session: &mut Session, email: &str, full_name: &str, org: &str) -> io::Result<()> {
+    println!("setting up keychain for macos");
+    //check if the key already exists and if it does do not generate a new one
+    match &session.paths.keystore_path {
+        Some(val) => {
+            println!("keystore path is already set");
+        },
+        None => {
+            println!("keystore path not set");
+            session.set_path("keystore_path", format!("{}/Library/Keychains", session.home))?;
+        },
+    }
+    //check if the keypath exists, if not generate a new key
+    let key_path = format!("{}/ramp.pem", session.get_path("keystore_path")?);
+    if !Path::new(&key_path).exists() {
+        println!("no private key found, generating new key");
+         //generate the signing key
+        let output = Command::new("openssl")
+        .args([
+            "genrsa",
+            "-out",
+            &key_path,
+            "2048"
+        ])
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to generate private key: {}", e)))?;
         if !output.status.success() {
-            return Err(io::Error::new(io::ErrorKind::Other, format!("Failed to open CSR in file explorer at path {}", &csr_path)));
+            return Err(io::Error::new(io::ErrorKind::Other, "OpenSSL key generation failed"));
         }
-        //TODO eventually replace these prints in the GUI
-        println!("1. Now go to the safari window and login to your developer account.");
-        println!("2. Once you have logged in, click the + button next to \"Certificates\".");
-        println!("3. Select the \"Apple Development\" checkbox and then hit next.");
-        println!("4. Next drag and drop the \"ramp.csr\" file from the file finder window into Safari. Click the next button.");
-        println!("5. Click the download button");
-
-        //continuously checks for "development.cer" in the Downloads folder and moves it once found
-        let cert_download = format!("{}/Downloads/development.cer", session.home);
-        loop {
-            if Path::new(&cert_download).exists() {
-                //Copy the cert to the keychain
-                let output = Command::new("mv")
-                    .args([&cert_download, &session.get_path("keystore_path")?])
-                    .stdout(Stdio::piped())
-                    .stderr(Stdio::null())
-                    .output()
-                    .unwrap();
-                if !output.status.success() {
-                    return Err(io::Error::new(io::ErrorKind::Other, "Failed to move the development.cer to the keychain directory"));
-                }
-                println!("Successfully downloaded signing certificate!");
-                break;
-            }else {
-                // wait 3 seconds
-                sleep(Duration::from_secs(3));
-            }
-        }
-        //security import the cert into the keychain
-        let output = Command::new("security")
-            .args(["import", &format!("{}/development.cer", session.get_path("keystore_path")?), "-k", &format!("{}/login.keychain-db", session.get_path("keystore_path")?)])
-            .output()
-            .unwrap();
-        if !output.status.success() {
-            return Err(io::Error::new(io::ErrorKind::Other, "Failed to import the development.pem to the keychain-db"));
-        }   
-        //get the App Developer Worldwide Developer Relations Ceritifcation Authority certificate
-        let output = Command::new("curl")
-            .args(["-o", &format!("{}/AppleWWDRCA.cer", session.get_path("keystore_path")?), "https://www.apple.com/certificateauthority/AppleWWDRCAG3.cer"])
-            .output()
-            .unwrap();
-        if !output.status.success() {
-            return Err(io::Error::new(io::ErrorKind::Other, "Failed to obtain Apple WWDRCA cert with curl"));
-        }  
-        //add the apple Developer worldwide relations cert to the security chain
+        //security import the private key into the keychain
         let output = Command::new("security")
-        .args(["import", &format!("{}/AppleWWDRCA.cer", session.get_path("keystore_path")?), "-k", &format!("{}/login.keychain-db", session.get_path("keystore_path")?)])
+        .args(["import", &format!("{}/ramp.pem", session.get_path("keystore_path")?), "-k", &format!("{}/login.keychain-db", session.get_path("keystore_path")?), "-T", "/usr/bin/codesign"])
         .output()
         .unwrap();
-        println!("AppleWWDRCA.cer import output: {:?}", output);
-        if !output.status.success() && !String::from_utf8_lossy(&output.stderr).contains("already exists") {
-            return Err(io::Error::new(io::ErrorKind::Other, "Failed to import the AppleWWDRCA.cer to the keychain-db"));
+        if !output.status.success() {
+            return Err(io::Error::new(io::ErrorKind::Other, "Failed to import the ramp.pem to the keychain-db"));
         }
-        //get the App Developer Worldwide Developer Relations Ceritifcation Authority certificate
-        let output = Command::new("curl")
-        .args(["-o", &format!("{}/AppleRootCA.cer", session.get_path("keystore_path")?), "https://www.apple.com/certificateauthority/AppleRootCA-G3.cer"])
+    }else {println!("ramp.pem already exists");}
+    //check if the CSR exists, if not generate a new CSR
+    let csr_path = format!("{}/ramp.csr", session.get_path("keystore_path")?);
+    if !Path::new(&csr_path).exists() {
+        let mut subject = String::new();
+        if org == "" {
+            subject = format!("/CN={} /emailAddress={}", full_name, email);
+        } else {
+            subject = format!("/CN={} /O={} /emailAddress={}", full_name, org, email);
+        }
+        // Generate a CSR
+        let output = Command::new("openssl")
+        .args([
+            "req",
+            "-new",
+            "-key",
+            &key_path,
+            "-out",
+            &csr_path,
+            "-subj",
+            &subject,
+        ])
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
         .output()
-        .unwrap();
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to generate CSR: {}", e)))?;
         if !output.status.success() {
-            return Err(io::Error::new(io::ErrorKind::Other, "Failed to obtain AppleRootCA cert with curl"));
-        }  
-        //add the apple Root CA cert to the security chain
-        let output = Command::new("security")
-        .args(["import", &format!("{}/AppleRootCA.cer", session.get_path("keystore_path")?), "-k", &format!("{}/login.keychain-db", session.get_path("keystore_path")?)])
-        .output()
-        .unwrap();
-        println!("AppleRootCA.cer import output: {:?}", output);
-        if !output.status.success() && !String::from_utf8_lossy(&output.stderr).contains("already exists") {
-            return Err(io::Error::new(io::ErrorKind::Other, "Failed to import the AppleRootCA.cer to the keychain-db"));
+            return Err(io::Error::new(io::ErrorKind::Other, "OpenSSL CSR generation failed"));
         }
-        //Get the Developer ID CA
-        let output = Command::new("curl")
-        .args(["-o", &format!("{}/AppleDevIDCA.cer", session.get_path("keystore_path")?), "https://www.apple.com/certificateauthority/DeveloperIDG2CA.cer"])
+    }else{println!("ramp.csr already exists");}
+    Ok(())
+}
+
+//copy a certificate from the portal into the keystore and trust it with apple's authorities
+pub fn import_signing_certificate(session: &mut Session, certificate: &Path, identity: SigningIdentity) -> io::Result<()> {
+    let certificate_file = identity.certificate_file();
+    //Copy the cert to the keychain
+    fs::copy(certificate, format!("{}/{}", session.get_path("keystore_path")?, certificate_file))
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to copy {} to the keychain directory: {}", certificate.display(), e)))?;
+    println!("Successfully downloaded signing certificate!");
+    //security import the cert into the keychain
+    let output = Command::new("security")
+        .args(["import", &format!("{}/{}", session.get_path("keystore_path")?, certificate_file), "-k", &format!("{}/login.keychain-db", session.get_path("keystore_path")?)])
         .output()
         .unwrap();
-        if !output.status.success() {
-            return Err(io::Error::new(io::ErrorKind::Other, "Failed to obtain AppleDevIDCA.cer cert with curl"));
-        }  
-        //add the apple Root CA cert to the security chain
-        let output = Command::new("security")
-        .args(["import", &format!("{}/AppleDevIDCA.cer", session.get_path("keystore_path")?), "-k", &format!("{}/login.keychain-db", session.get_path("keystore_path")?)])
+    if !output.status.success() {
+        return Err(io::Error::new(io::ErrorKind::Other, format!("Failed to import the {} to the keychain-db", certificate_file)));
+    }   
+    //get the App Developer Worldwide Developer Relations Ceritifcation Authority certificate
+    let output = Command::new("curl")
+        .args(["-o", &format!("{}/AppleWWDRCA.cer", session.get_path("keystore_path")?), "https://www.apple.com/certificateauthority/AppleWWDRCAG3.cer"])
         .output()
         .unwrap();
-        println!("AppleDevIDCA.cer import output: {:?}", output);
-        if !output.status.success() && !String::from_utf8_lossy(&output.stderr).contains("already exists") {
-            return Err(io::Error::new(io::ErrorKind::Other, "Failed to import the AppleDevIDCA.cer to the keychain-db"));
-        }
-
-        println!("Successfully set up the keychain for macos!")
-
+    if !output.status.success() {
+        return Err(io::Error::new(io::ErrorKind::Other, "Failed to obtain Apple WWDRCA cert with curl"));
+    }  
+    //add the apple Developer worldwide relations cert to the security chain
+    let output = Command::new("security")
+    .args(["import", &format!("{}/AppleWWDRCA.cer", session.get_path("keystore_path")?), "-k", &format!("{}/login.keychain-db", session.get_path("keystore_path")?)])
+    .output()
+    .unwrap();
+    println!("AppleWWDRCA.cer import output: {:?}", output);
+    if !output.status.success() && !String::from_utf8_lossy(&output.stderr).contains("already exists") {
+        return Err(io::Error::new(io::ErrorKind::Other, "Failed to import the AppleWWDRCA.cer to the keychain-db"));
+    }
+    //get the App Developer Worldwide Developer Relations Ceritifcation Authority certificate
+    let output = Command::new("curl")
+    .args(["-o", &format!("{}/AppleRootCA.cer", session.get_path("keystore_path")?), "https://www.apple.com/certificateauthority/AppleRootCA-G3.cer"])
+    .output()
+    .unwrap();
+    if !output.status.success() {
+        return Err(io::Error::new(io::ErrorKind::Other, "Failed to obtain AppleRootCA cert with curl"));
+    }  
+    //add the apple Root CA cert to the security chain
+    let output = Command::new("security")
+    .args(["import", &format!("{}/AppleRootCA.cer", session.get_path("keystore_path")?), "-k", &format!("{}/login.keychain-db", session.get_path("keystore_path")?)])
+    .output()
+    .unwrap();
+    println!("AppleRootCA.cer import output: {:?}", output);
+    if !output.status.success() && !String::from_utf8_lossy(&output.stderr).contains("already exists") {
+        return Err(io::Error::new(io::ErrorKind::Other, "Failed to import the AppleRootCA.cer to the keychain-db"));
+    }
+    //Get the Developer ID CA
+    let output = Command::new("curl")
+    .args(["-o", &format!("{}/AppleDevIDCA.cer", session.get_path("keystore_path")?), "https://www.apple.com/certificateauthority/DeveloperIDG2CA.cer"])
+    .output()
+    .unwrap();
+    if !output.status.success() {
+        return Err(io::Error::new(io::ErrorKind::Other, "Failed to obtain AppleDevIDCA.cer cert with curl"));
+    }  
+    //add the apple Root CA cert to the security chain
+    let output = Command::new("security")
+    .args(["import", &format!("{}/AppleDevIDCA.cer", session.get_path("keystore_path")?), "-k", &format!("{}/login.keychain-db", session.get_path("keystore_path")?)])
+    .output()
+    .unwrap();
+    println!("AppleDevIDCA.cer import output: {:?}", output);
+    if !output.status.success() && !String::from_utf8_lossy(&output.stderr).contains("already exists") {
+        return Err(io::Error::new(io::ErrorKind::Other, "Failed to import the AppleDevIDCA.cer to the keychain-db"));
     }
-    //TODO add support for all other target builds
     Ok(())
 }
 
@@ -1111,6 +1077,134 @@ pub fn install_android_toolchains(session: &mut Session) -> io::Result<()> {
         }
         println!("cargo-apk installed successfully.");
     }
+    // Install bundletool for play store app bundles
+    let bundletool_dir = format!("{}/bundletool", session.get_path("sdk_path")?);
+    session.set_path("bundletool_path", format!("{}/bundletool-all-{}.jar", &bundletool_dir, BUNDLETOOL_VERSION))?;
+    if Path::new(&session.get_path("bundletool_path")?).exists() {
+        println!("bundletool is already installed. Skipping installation.");
+    } else {
+        println!("Installing bundletool {}...", BUNDLETOOL_VERSION);
+        fs::create_dir_all(&bundletool_dir)?;
+        let download_output = Command::new("curl")
+            .args([
+                "-fL",
+                "-o",
+                &session.get_path("bundletool_path")?,
+                &format!(
+                    "https://github.com/google/bundletool/releases/download/{0}/bundletool-all-{0}.jar",
+                    BUNDLETOOL_VERSION
+                ),
+            ])
+            .output()?;
+        if !download_output.status.success() {
+            println!("curl stderr: {}", String::from_utf8_lossy(&download_output.stderr));
+            return Err(io::Error::other("Failed to download bundletool"));
+        }
+        println!("bundletool installed successfully.");
+    }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+//install wasm-bindgen-cli for the wasm web build pipeline
+pub fn install_wasm_toolchain(session: &mut Session) -> io::Result<()> {
+    session.set_path("wasm_bindgen_path", format!("{}/.cargo/bin/wasm-bindgen", session.home))?;
+    let wasm_bindgen_ok = Command::new(&session.get_path("wasm_bindgen_path")?)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    if wasm_bindgen_ok {
+        println!("wasm-bindgen-cli is already installed. Skipping installation.");
+    } else {
+        println!("Installing wasm-bindgen-cli...");
+        let install_output = Command::new(session.get_path("cargo_path")?)
+            .args(["install", "--locked", "wasm-bindgen-cli"])
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .output()?;
+        if !install_output.status.success() {
+            println!("cargo install stderr: {}", String::from_utf8_lossy(&install_output.stderr));
+            return Err(io::Error::other("Failed to install wasm-bindgen-cli"));
+        }
+        println!("wasm-bindgen-cli installed successfully.");
+    }
+
+    //wasm-opt (binaryen) is optional, release builds skip the optimisation pass without it
+    let (wasm_opt_path, install_command) = match session.os.as_str() {
+        "macos" => (
+            format!("{}/wasm-opt", session.get_path("homebrew_path")?),
+            format!("{}/brew install binaryen", session.get_path("homebrew_path")?),
+        ),
+        _ => ("/usr/bin/wasm-opt".to_string(), "sudo apt install -y binaryen".to_string()),
+    };
+    session.set_path("wasm_opt_path", wasm_opt_path)?;
+    if Path::new(&session.get_path("wasm_opt_path")?).exists() {
+        println!("wasm-opt is already installed. Skipping installation.");
+        return Ok(());
+    }
+    println!("Installing binaryen for wasm-opt...");
+    let install_output = match session.os.as_str() {
+        "macos" => {
+            let sudo_user = env::var("SUDO_USER").map_err(|_| io::Error::new(io::ErrorKind::NotFound, "SUDO USER NOT FOUND"))?;
+            Command::new("su").args([&sudo_user, "-c", &install_command]).output()?
+        }
+        _ => Command::new("bash").args(["-c", &install_command]).output()?,
+    };
+    if !install_output.status.success() {
+        println!(
+            "WARNING: failed to install binaryen, release wasm builds will not be optimised: {}",
+            String::from_utf8_lossy(&install_output.stderr)
+        );
+    } else {
+        println!("wasm-opt installed successfully.");
+    }
+    Ok(())
+}
+
+//install the mingw-w64 linker for x86_64-pc-windows-gnu and msitools (wixl) for building msi installers
+pub fn install_windows_toolchain(session: &mut Session) -> io::Result<()> {
+    if is_command_available("x86_64-w64-mingw32-gcc") && is_command_available("wixl") {
+        println!("mingw-w64 and msitools are already installed. Skipping installation.");
+        return Ok(());
+    }
+    println!("Installing mingw-w64 and msitools...");
+    let install_output = match session.os.as_str() {
+        "linux" => Command::new("bash")
+            .args(["-c", "sudo apt install -y mingw-w64 msitools"])
+            .output()?,
+        "macos" => {
+            let sudo_user = env::var("SUDO_USER").map_err(|_| io::Error::new(io::ErrorKind::NotFound, "SUDO USER NOT FOUND"))?;
+            Command::new("su")
+                .args([&sudo_user, "-c", &format!("{}/brew install mingw-w64 msitools", session.get_path("homebrew_path")?)])
+                .output()?
+        }
+        _ => return Err(io::Error::other(format!("Unsupported OS: {}", session.os))),
+    };
+    if !install_output.status.success() {
+        println!("install stderr: {}", String::from_utf8_lossy(&install_output.stderr));
+        return Err(io::Error::other("Failed to install mingw-w64 and msitools"));
+    }
+    println!("mingw-w64 and msitools installed successfully.");
+    Ok(())
+}
+
+//android keystore passwords are kept in the keychain on macos and in libsecret on linux
+pub fn install_secret_store(session: &Session) -> io::Result<()> {
+    if session.os != "linux" || is_command_available("secret-tool") {
+        println!("secure credential store is available.");
+        return Ok(());
+    }
+    println!("Installing libsecret-tools...");
+    let install_output = Command::new("bash")
+        .args(["-c", "sudo apt install -y libsecret-tools"])
+        .output()?;
+    if !install_output.status.success() {
+        println!("install stderr: {}", String::from_utf8_lossy(&install_output.stderr));
+        return Err(io::Error::other("Failed to install libsecret-tools"));
+    }
+    println!("libsecret-tools installed successfully.");
+    Ok(())
+}
@@ -0,0 +1,290 @@
+use super::helper::{capitalize_first, is_command_available};
+use super::session::Session;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+//secure store service names, the account is the project name
+const PASSWORD_SERVICE: &str = "ramp-android-keystore";
+const ALIAS_SERVICE: &str = "ramp-android-keystore-alias";
+
+pub const DEFAULT_KEY_ALIAS: &str = "upload";
+
+//play requires signing keys to stay valid well past 2033
+const KEY_VALIDITY_DAYS: &str = "10000";
+
+//the environment variables cargo-apk reads in place of the manifest's [package.metadata.android.signing.release]
+pub const CARGO_APK_KEYSTORE_ENV: &str = "CARGO_APK_RELEASE_KEYSTORE";
+pub const CARGO_APK_KEYSTORE_PASSWORD_ENV: &str = "CARGO_APK_RELEASE_KEYSTORE_PASSWORD";
+
+#[derive(Debug, Clone)]
+pub struct AndroidKeystore {
+    pub path: String,
+    pub alias: String,
+    pub password: String,
+}
+
+//where credentials are kept, never in a plain text file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SecretStore {
+    //the login keychain via `security`
+    MacKeychain,
+    //gnome keyring / kwallet via libsecret's `secret-tool`
+    SecretService,
+}
+
+impl SecretStore {
+    pub fn detect(os: &str) -> io::Result<Self> {
+        match os {
+            "macos" => Ok(SecretStore::MacKeychain),
+            _ if is_command_available("secret-tool") => Ok(SecretStore::SecretService),
+            _ => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no secure credential store found, install libsecret-tools (secret-tool) to manage android keystores",
+            )),
+        }
+    }
+
+    pub fn store(&self, service: &str, account: &str, secret: &str) -> io::Result<()> {
+        let (mut child, input) = match self {
+            //security -i reads commands from stdin so the secret never shows up in the process list
+            SecretStore::MacKeychain => (
+                Command::new("security").arg("-i").stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::piped()).spawn()?,
+                format!("add-generic-password -U -a \"{}\" -s \"{}\" -w \"{}\"\n", account, service, secret),
+            ),
+            SecretStore::SecretService => (
+                Command::new("secret-tool")
+                    .args(["store", &format!("--label=ramp {} {}", service, account), "service", service, "account", account])
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::piped())
+                    .spawn()?,
+                secret.to_string(),
+            ),
+        };
+        child.stdin.take().unwrap().write_all(input.as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "Failed to store {} in the {:?}: {}",
+                service,
+                self,
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        Ok(())
+    }
+
+    //returns None when there is no entry for this service and account
+    pub fn load(&self, service: &str, account: &str) -> io::Result<Option<String>> {
+        let output = match self {
+            SecretStore::MacKeychain => Command::new("security")
+                .args(["find-generic-password", "-a", account, "-s", service, "-w"])
+                .output()?,
+            SecretStore::SecretService => Command::new("secret-tool")
+                .args(["lookup", "service", service, "account", account])
+                .output()?,
+        };
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let secret = String::from_utf8_lossy(&output.stdout).trim_end_matches('\n').to_string();
+        Ok(if secret.is_empty() { None } else { Some(secret) })
+    }
+}
+
+//a random alphanumeric password, keytool rejects passwords under 6 characters
+pub fn generate_password(length: usize) -> io::Result<String> {
+    const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+    let mut bytes = vec![0u8; length * 2];
+    File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    //reject bytes past the largest multiple of the charset length to avoid modulo bias
+    let limit = 256 - (256 % CHARSET.len());
+    let password: String = bytes
+        .iter()
+        .filter(|b| (**b as usize) < limit)
+        .take(length)
+        .map(|b| CHARSET[*b as usize % CHARSET.len()] as char)
+        .collect();
+    if password.len() < length {
+        return generate_password(length);
+    }
+    Ok(password)
+}
+
+//keystores live outside the project so they are never committed with it
+pub fn keystore_file(session: &Session) -> String {
+    format!(
+        "{}/.ramp_keystores/{}-upload.jks",
+        session.home,
+        session.current_project.as_ref().unwrap()
+    )
+}
+
+fn keytool_path(session: &mut Session) -> String {
+    session
+        .get_path("java_path")
+        .map(|java| format!("{}/bin/keytool", java))
+        .ok()
+        .filter(|keytool| Path::new(keytool).exists())
+        .unwrap_or_else(|| "keytool".to_string())
+}
+
+//generate a new upload key with keytool, passing secrets through the environment rather than argv
+pub fn generate_keystore(session: &mut Session, alias: &str, password: &str) -> io::Result<String> {
+    let path = keystore_file(session);
+    let dir = Path::new(&path).parent().unwrap();
+    fs::create_dir_all(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    let dname = format!("CN={}, OU=ramp", capitalize_first(session.current_project.as_ref().unwrap()));
+    let output = Command::new(keytool_path(session))
+        .args([
+            "-genkeypair", "-keystore", &path, "-storetype", "PKCS12", "-alias", alias, "-keyalg", "RSA",
+            "-keysize", "2048", "-validity", KEY_VALIDITY_DAYS, "-dname", &dname,
+            "-storepass:env", "RAMP_KEYSTORE_PASSWORD", "-keypass:env", "RAMP_KEYSTORE_PASSWORD",
+        ])
+        .env("RAMP_KEYSTORE_PASSWORD", password)
+        .output()
+        .map_err(|e| io::Error::other(format!("Failed to run keytool: {}", e)))?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "keytool failed to generate the keystore: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(path)
+}
+
+//load the project's upload key, creating it and saving its credentials on first use
+pub fn ensure_android_keystore(session: &mut Session) -> io::Result<AndroidKeystore> {
+    let project = session.current_project.as_ref().unwrap().to_string();
+    let store = SecretStore::detect(&session.os)?;
+    let path = keystore_file(session);
+    if Path::new(&path).exists() {
+        let password = store.load(PASSWORD_SERVICE, &project)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("keystore {} exists but its password is missing from the {:?}", &path, store),
+            )
+        })?;
+        let alias = store.load(ALIAS_SERVICE, &project)?.unwrap_or_else(|| DEFAULT_KEY_ALIAS.to_string());
+        return Ok(AndroidKeystore { path, alias, password });
+    }
+
+    println!("no android keystore found for {}, generating one", &project);
+    let password = generate_password(32)?;
+    //save the credentials first so a keystore never exists without a way to unlock it
+    store.store(PASSWORD_SERVICE, &project, &password)?;
+    store.store(ALIAS_SERVICE, &project, DEFAULT_KEY_ALIAS)?;
+    let path = generate_keystore(session, DEFAULT_KEY_ALIAS, &password)?;
+    println!("created android keystore at {}, back this file up: play store updates must be signed with it", &path);
+    Ok(AndroidKeystore { path, alias: DEFAULT_KEY_ALIAS.to_string(), password })
+}
+
+//environment for `cargo apk build --release`, cargo-apk prefers these over the manifest signing config
+//so the password never has to be written into Cargo.toml
+pub fn release_signing_env(keystore: &AndroidKeystore) -> Vec<(&'static str, String)> {
+    vec![
+        (CARGO_APK_KEYSTORE_ENV, keystore.path.clone()),
+        (CARGO_APK_KEYSTORE_PASSWORD_ENV, keystore.password.clone()),
+    ]
+}
+
+//check the apk signature with apksigner, returns the signer certificate summary
+pub fn verify_apk_signature(session: &mut Session, apk_path: &str) -> io::Result<String> {
+    let apksigner = format!("{}/apksigner", session.get_path("build_tools_path")?);
+    let output = Command::new(&apksigner)
+        .args(["verify", "--verbose", "--print-certs", apk_path])
+        .env("JAVA_HOME", session.get_path("java_path")?)
+        .output()
+        .map_err(|e| io::Error::other(format!("Failed to run apksigner: {}", e)))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "apk signature verification failed for {}: {}{}",
+            apk_path,
+            stdout,
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(stdout
+        .lines()
+        .filter(|l| l.starts_with("Verified using") || l.starts_with("Signer #1 certificate"))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ramp::helper::test_session;
+
+    #[test]
+    fn generates_alphanumeric_passwords() {
+        for length in [6, 32, 100] {
+            let password = generate_password(length).unwrap();
+            assert_eq!(password.len(), length);
+            assert!(password.bytes().all(|b| b.is_ascii_alphanumeric()), "{}", password);
+        }
+        assert_eq!(generate_password(0).unwrap(), "");
+        assert_ne!(generate_password(32).unwrap(), generate_password(32).unwrap());
+        //every character of the charset shows up given enough samples
+        let sample = generate_password(4000).unwrap();
+        assert!(('a'..='z').chain('A'..='Z').chain('0'..='9').all(|c| sample.contains(c)));
+    }
+
+    #[test]
+    fn keeps_keystores_outside_the_project() {
+        let mut session = test_session("keystore-path");
+        session.current_project = Some("demo".to_string());
+        assert_eq!(keystore_file(&session), format!("{}/.ramp_keystores/demo-upload.jks", session.home));
+        assert_eq!(SecretStore::detect("macos").unwrap(), SecretStore::MacKeychain);
+        let keystore = AndroidKeystore {
+            path: keystore_file(&session),
+            alias: DEFAULT_KEY_ALIAS.to_string(),
+            password: "secret".to_string(),
+        };
+        assert_eq!(
+            release_signing_env(&keystore),
+            [(CARGO_APK_KEYSTORE_ENV, keystore.path.clone()), (CARGO_APK_KEYSTORE_PASSWORD_ENV, "secret".to_string())]
+        );
+    }
+
+    #[test]
+    fn generates_a_private_keystore() {
+        if !is_command_available("keytool") {
+            return;
+        }
+        let mut session = test_session("keystore-generate");
+        session.current_project = Some("demo".to_string());
+        let password = generate_password(32).unwrap();
+        let path = generate_keystore(&mut session, DEFAULT_KEY_ALIAS, &password).unwrap();
+        assert_eq!(path, keystore_file(&session));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        let list = Command::new("keytool")
+            .args(["-list", "-keystore", &path, "-storepass:env", "RAMP_KEYSTORE_PASSWORD", "-alias", DEFAULT_KEY_ALIAS])
+            .env("RAMP_KEYSTORE_PASSWORD", &password)
+            .output()
+            .unwrap();
+        assert!(list.status.success(), "{}", String::from_utf8_lossy(&list.stdout));
+        let wrong = Command::new("keytool")
+            .args(["-list", "-keystore", &path, "-storepass", "not-the-password"])
+            .output()
+            .unwrap();
+        assert!(!wrong.status.success());
+    }
+}
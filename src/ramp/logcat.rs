@@ -0,0 +1,655 @@
+use super::adb::{adb_command, adb_path, AndroidDevice};
+use super::session::Session;
+use regex::Regex;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+//lines kept for the log panel, older lines are dropped
+pub const LOG_BUFFER_CAPACITY: usize = 2000;
+
+//how often the app's pid is checked, `logcat --pid` keeps running after the app dies
+const PID_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+//time the crash buffer gets to deliver the tombstone of an app that just died
+const CRASH_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Verbose,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl LogLevel {
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'V' => Some(LogLevel::Verbose),
+            'D' => Some(LogLevel::Debug),
+            'I' => Some(LogLevel::Info),
+            'W' => Some(LogLevel::Warn),
+            'E' => Some(LogLevel::Error),
+            'F' | 'A' => Some(LogLevel::Fatal),
+            _ => None,
+        }
+    }
+
+    pub fn as_char(&self) -> char {
+        match self {
+            LogLevel::Verbose => 'V',
+            LogLevel::Debug => 'D',
+            LogLevel::Info => 'I',
+            LogLevel::Warn => 'W',
+            LogLevel::Error => 'E',
+            LogLevel::Fatal => 'F',
+        }
+    }
+}
+
+//one line of `adb logcat -v threadtime`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine {
+    pub timestamp: String,
+    pub pid: u32,
+    pub tid: u32,
+    pub level: LogLevel,
+    pub tag: String,
+    pub message: String,
+}
+
+impl LogLine {
+    pub fn to_text(&self) -> String {
+        format!("{} {} {}: {}", self.timestamp, self.level.as_char(), self.tag, self.message)
+    }
+}
+
+fn threadtime_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^(\d\d-\d\d\s+\d\d:\d\d:\d\d\.\d+)\s+(\d+)\s+(\d+)\s+([VDIWEFA])\s+(.*?)\s*:\s?(.*)$").unwrap()
+    })
+}
+
+//parse a threadtime formatted line, None for logcat's own "--------- beginning of" markers
+pub fn parse_logcat_line(line: &str) -> Option<LogLine> {
+    let captures = threadtime_regex().captures(line.trim_end())?;
+    Some(LogLine {
+        timestamp: captures[1].split_whitespace().collect::<Vec<_>>().join(" "),
+        pid: captures[2].parse().ok()?,
+        tid: captures[3].parse().ok()?,
+        level: LogLevel::from_char(captures[4].chars().next()?)?,
+        tag: captures[5].to_string(),
+        message: captures[6].to_string(),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFilter {
+    pub min_level: LogLevel,
+    //case insensitive match against the tag and message
+    pub search: Option<String>,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        LogFilter { min_level: LogLevel::Verbose, search: None }
+    }
+}
+
+impl LogFilter {
+    pub fn matches(&self, line: &LogLine) -> bool {
+        if line.level < self.min_level {
+            return false;
+        }
+        match self.search.as_deref().map(|s| s.trim().to_lowercase()) {
+            Some(search) if !search.is_empty() => {
+                line.tag.to_lowercase().contains(&search) || line.message.to_lowercase().contains(&search)
+            }
+            _ => true,
+        }
+    }
+}
+
+//a frame from a tombstone backtrace, e.g. `#00 pc 000000000004a1c8  /data/app/.../lib/arm64/libmain.so (...)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrashFrame {
+    pub index: u32,
+    pub pc: String,
+    pub library: String,
+    pub symbol: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrashReport {
+    //the process that logged the line starting the crash, crash_dump's own pid for tombstones
+    pub pid: u32,
+    //the "Fatal signal" or panic message that started the crash
+    pub summary: String,
+    pub lines: Vec<String>,
+    pub frames: Vec<CrashFrame>,
+}
+
+fn frame_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"#(\d+)\s+pc\s+([0-9a-fA-F]+)\s+(\S+)(?:\s+\((.*)\))?").unwrap())
+}
+
+pub fn parse_crash_frame(message: &str) -> Option<CrashFrame> {
+    let captures = frame_regex().captures(message)?;
+    Some(CrashFrame {
+        index: captures[1].parse().ok()?,
+        pc: captures[2].to_string(),
+        library: captures[3].to_string(),
+        //newer tombstones annotate frames without symbols with just the build id
+        symbol: captures.get(4).map(|m| m.as_str().to_string()).filter(|s| !s.starts_with("BuildId:")),
+    })
+}
+
+fn is_crash_tag(tag: &str) -> bool {
+    matches!(tag, "DEBUG" | "libc" | "crash_dump64" | "crash_dump32" | "AndroidRuntime")
+}
+
+//collects tombstone and rust panic lines into crash reports
+#[derive(Debug, Default)]
+pub struct CrashDetector {
+    current: Option<CrashReport>,
+}
+
+impl CrashDetector {
+    //feed every line, returns a report once the crash output that started it has ended
+    pub fn feed(&mut self, line: &LogLine) -> Option<CrashReport> {
+        let starts_crash = (line.tag == "libc" && line.message.contains("Fatal signal"))
+            || (line.tag == "DEBUG" && line.message.contains("*** *** ***"))
+            || line.message.contains("panicked at");
+        if let Some(report) = self.current.as_mut() {
+            if is_crash_tag(&line.tag) || line.tag.starts_with("Rust") {
+                if let Some(frame) = parse_crash_frame(&line.message) {
+                    report.frames.push(frame);
+                }
+                report.lines.push(line.message.clone());
+                return None;
+            }
+            let finished = self.current.take();
+            if starts_crash {
+                self.start(line);
+            }
+            return finished;
+        }
+        if starts_crash {
+            self.start(line);
+        }
+        None
+    }
+
+    fn start(&mut self, line: &LogLine) {
+        self.current = Some(CrashReport {
+            pid: line.pid,
+            summary: line.message.clone(),
+            lines: vec![line.message.clone()],
+            frames: Vec::new(),
+        });
+    }
+
+    //the report in progress when the stream ends
+    pub fn finish(&mut self) -> Option<CrashReport> {
+        self.current.take()
+    }
+}
+
+impl CrashReport {
+    pub fn is_panic(&self) -> bool {
+        self.summary.contains("panicked at")
+    }
+
+    //true if the crash is the app's, the crash buffer also holds every other process's tombstones
+    //tombstones name the process in a `pid: N, tid: N, name: ...  >>> package <<<` header
+    pub fn belongs_to(&self, pid: u32, package: &str) -> bool {
+        let header = format!("pid: {},", pid);
+        let signal = format!("pid {} (", pid);
+        let process = format!(">>> {} <<<", package);
+        self.pid == pid || self.lines.iter().any(|l| l.contains(&header) || l.contains(&signal) || l.contains(&process))
+    }
+}
+
+//the rust target a tombstone frame's library was built for, from its /lib/<abi>/ directory
+pub fn frame_target(library: &str) -> Option<&'static str> {
+    if library.contains("/lib/arm64/") {
+        Some("aarch64-linux-android")
+    } else if library.contains("/lib/x86_64/") {
+        Some("x86_64-linux-android")
+    } else if library.contains("/lib/x86/") {
+        Some("i686-linux-android")
+    } else if library.contains("/lib/arm/") {
+        Some("armv7-linux-androideabi")
+    } else {
+        None
+    }
+}
+
+fn addr2line_path(session: &mut Session) -> io::Result<String> {
+    let host = if session.os == "macos" { "darwin-x86_64" } else { "linux-x86_64" };
+    Ok(format!("{}/toolchains/llvm/prebuilt/{}/bin/llvm-addr2line", session.get_path("ndk_path")?, host))
+}
+
+//resolve frames in the project's own libraries against the unstripped .so files cargo leaves in target/
+pub fn symbolicate(session: &mut Session, report: &CrashReport) -> io::Result<Vec<String>> {
+    let project_path = format!(
+        "{}/{}",
+        session.projects_path.as_ref().unwrap(),
+        session.current_project.as_ref().unwrap()
+    );
+    let addr2line = addr2line_path(session)?;
+    let mut symbolicated = Vec::new();
+    for frame in &report.frames {
+        let file_name = Path::new(&frame.library).file_name().map(|f| f.to_string_lossy().to_string());
+        let unstripped = frame_target(&frame.library).zip(file_name).and_then(|(target, file_name)| {
+            ["debug", "release"]
+                .iter()
+                .map(|profile| format!("{}/target/{}/{}/{}", &project_path, target, profile, file_name))
+                .find(|path| Path::new(path).exists())
+        });
+        let resolved = match unstripped {
+            Some(so_path) => Command::new(&addr2line)
+                .args(["-C", "-f", "-p", "-e", &so_path, &format!("0x{}", frame.pc)])
+                .output()
+                .ok()
+                .filter(|o| o.status.success())
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+                .filter(|s| !s.is_empty() && !s.starts_with("??")),
+            None => None,
+        };
+        symbolicated.push(format!(
+            "#{:02} {}",
+            frame.index,
+            resolved.or_else(|| frame.symbol.clone()).unwrap_or_else(|| format!("{} + 0x{}", frame.library, frame.pc))
+        ));
+    }
+    Ok(symbolicated)
+}
+
+//bounded, filterable log history shared between the stream threads and the log panel
+#[derive(Debug)]
+pub struct LogBuffer {
+    lines: VecDeque<LogLine>,
+    pub filter: LogFilter,
+    pub crashes: Vec<(CrashReport, Vec<String>)>,
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        LogBuffer { lines: VecDeque::with_capacity(LOG_BUFFER_CAPACITY), filter: LogFilter::default(), crashes: Vec::new() }
+    }
+}
+
+impl LogBuffer {
+    pub fn push(&mut self, line: LogLine) {
+        if self.lines.len() == LOG_BUFFER_CAPACITY {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.crashes.clear();
+    }
+
+    //the last `max_lines` lines passing the filter, oldest first
+    pub fn render(&self, max_lines: usize) -> String {
+        let mut visible: Vec<String> = self.lines.iter().rev().filter(|l| self.filter.matches(l)).take(max_lines).map(|l| l.to_text()).collect();
+        visible.reverse();
+        let mut text = visible.join("\n");
+        if let Some((report, frames)) = self.crashes.last() {
+            text.push_str(&format!("\n\nCRASH: {}\n{}", report.summary, frames.join("\n")));
+        }
+        text
+    }
+}
+
+//the log buffer shown by the android page's log panel
+pub fn shared_log_buffer() -> Arc<Mutex<LogBuffer>> {
+    static BUFFER: OnceLock<Arc<Mutex<LogBuffer>>> = OnceLock::new();
+    BUFFER.get_or_init(|| Arc::new(Mutex::new(LogBuffer::default()))).clone()
+}
+
+//the app's pid, None while it isn't running
+pub fn resolve_pid(adb_path: &str, serial: &str, package: &str) -> Option<u32> {
+    let output = adb_command(adb_path, serial).args(["shell", "pidof", "-s", package]).output().ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+pub enum LogEvent {
+    Line(LogLine),
+    Crash(CrashReport),
+    //the app process went away, the stream stops
+    Exited,
+}
+
+//which logcat output a reader follows
+enum StreamKind {
+    //the app's own lines, forwarded as they come and scanned for rust panics
+    App,
+    //the shared crash buffer, only the app's own tombstones are reported
+    Crashes { pid: u32, package: String },
+}
+
+impl StreamKind {
+    fn reports(&self, report: &CrashReport) -> bool {
+        match self {
+            //native crashes are reported once, from the tombstone in the crash buffer
+            StreamKind::App => report.is_panic(),
+            StreamKind::Crashes { pid, package } => report.belongs_to(*pid, package),
+        }
+    }
+}
+
+//the adb logcat processes behind a log stream, stopping or dropping it kills them and ends the stream
+pub struct LogStream {
+    children: Vec<Arc<Mutex<Child>>>,
+    stopped: Arc<AtomicBool>,
+    //readers still running, the last one to finish reports the app exited
+    running: Arc<AtomicUsize>,
+}
+
+impl LogStream {
+    fn new() -> Self {
+        LogStream { children: Vec::new(), stopped: Arc::new(AtomicBool::new(false)), running: Arc::new(AtomicUsize::new(0)) }
+    }
+
+    fn read(&mut self, child: Child, sender: mpsc::Sender<LogEvent>, kind: StreamKind) {
+        self.running.fetch_add(1, Ordering::SeqCst);
+        self.children.push(spawn_reader(child, sender, self.stopped.clone(), self.running.clone(), kind));
+    }
+
+    //kill every logcat once the app is gone or one of them exits, so none outlives the app
+    fn watch_exit(&self, poll_interval: Duration, grace_period: Duration, app_running: impl Fn() -> bool + Send + 'static) {
+        let children = self.children.clone();
+        let stopped = self.stopped.clone();
+        thread::spawn(move || {
+            loop {
+                thread::sleep(poll_interval);
+                if stopped.load(Ordering::SeqCst) {
+                    return;
+                }
+                let logcat_exited = children.iter().any(|child| !matches!(child.lock().unwrap().try_wait(), Ok(None)));
+                if logcat_exited || !app_running() {
+                    break;
+                }
+            }
+            thread::sleep(grace_period);
+            //the readers see their output end, flush what they have and report the exit
+            for child in &children {
+                let _ = child.lock().unwrap().kill();
+            }
+        });
+    }
+
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        for child in &self.children {
+            let mut child = child.lock().unwrap();
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Drop for LogStream {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn spawn_reader(
+    mut child: Child,
+    sender: mpsc::Sender<LogEvent>,
+    stopped: Arc<AtomicBool>,
+    running: Arc<AtomicUsize>,
+    kind: StreamKind,
+) -> Arc<Mutex<Child>> {
+    let stdout = child.stdout.take();
+    let child = Arc::new(Mutex::new(child));
+    let handle = child.clone();
+    thread::spawn(move || {
+        let Some(stdout) = stdout else { return };
+        let mut detector = CrashDetector::default();
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if stopped.load(Ordering::SeqCst) {
+                return;
+            }
+            let Some(line) = parse_logcat_line(&line) else { continue };
+            if let Some(report) = detector.feed(&line).filter(|r| kind.reports(r)) {
+                let _ = sender.send(LogEvent::Crash(report));
+            }
+            if matches!(kind, StreamKind::App) && sender.send(LogEvent::Line(line)).is_err() {
+                break;
+            }
+        }
+        if stopped.load(Ordering::SeqCst) {
+            return;
+        }
+        if let Some(report) = detector.finish().filter(|r| kind.reports(r)) {
+            let _ = sender.send(LogEvent::Crash(report));
+        }
+        {
+            let mut child = child.lock().unwrap();
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        if running.fetch_sub(1, Ordering::SeqCst) == 1 {
+            let _ = sender.send(LogEvent::Exited);
+        }
+    });
+    handle
+}
+
+//stream the app's logs with `logcat --pid` and watch the crash buffer for tombstones and panics
+pub fn stream_app_logs(session: &mut Session, device: &AndroidDevice, package: &str) -> io::Result<(LogStream, Receiver<LogEvent>)> {
+    let adb_path = adb_path(session)?;
+    //the app may still be starting right after `am start`
+    let mut pid = None;
+    for _ in 0..10 {
+        pid = resolve_pid(&adb_path, &device.serial, package);
+        if pid.is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+    let pid = pid.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} is not running on {}", package, device.serial)))?;
+    println!("streaming logs for {} (pid {})", package, pid);
+
+    let (sender, receiver) = mpsc::channel();
+    let mut stream = LogStream::new();
+    let app_logs = adb_command(&adb_path, &device.serial)
+        .args(["logcat", "-v", "threadtime", "--pid", &pid.to_string()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    stream.read(app_logs, sender.clone(), StreamKind::App);
+    //tombstones are written by crash_dump, not the app, so they never match --pid
+    let crashes = adb_command(&adb_path, &device.serial)
+        .args(["logcat", "-v", "threadtime", "-b", "crash", "-T", "1"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let kind = StreamKind::Crashes { pid, package: package.to_string() };
+    stream.read(crashes, sender, kind);
+    //a restarted app has a new pid, so it counts as gone too
+    let (serial, package) = (device.serial.clone(), package.to_string());
+    stream.watch_exit(PID_POLL_INTERVAL, CRASH_GRACE_PERIOD, move || resolve_pid(&adb_path, &serial, &package) == Some(pid));
+    Ok((stream, receiver))
+}
+
+//feed a log stream into the shared buffer, symbolicating crashes as they arrive
+pub fn pump_logs(session: &mut Session, receiver: Receiver<LogEvent>, echo: bool) -> io::Result<()> {
+    let buffer = shared_log_buffer();
+    for event in receiver {
+        match event {
+            LogEvent::Line(line) => {
+                let mut buffer = buffer.lock().unwrap();
+                if echo && buffer.filter.matches(&line) {
+                    println!("{}", line.to_text());
+                }
+                buffer.push(line);
+            }
+            LogEvent::Crash(report) => {
+                let frames = symbolicate(session, &report).unwrap_or_default();
+                println!("native crash detected: {}\n{}", report.summary, frames.join("\n"));
+                buffer.lock().unwrap().crashes.push((report, frames));
+            }
+            LogEvent::Exited => {
+                println!("app process exited, log stream closed");
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+//the stream feeding the gui's log panel, replaced on every deploy
+fn watched_stream() -> &'static Mutex<Option<LogStream>> {
+    static STREAM: OnceLock<Mutex<Option<LogStream>>> = OnceLock::new();
+    STREAM.get_or_init(|| Mutex::new(None))
+}
+
+//stream the app's logs into the shared buffer on a background thread, used by the gui after deploying
+pub fn watch_app_logs(session: &mut Session, device: &AndroidDevice, package: &str) -> io::Result<()> {
+    //the previous deploy's adb processes are killed so its lines never mix with the new ones
+    if let Some(previous) = watched_stream().lock().unwrap().take() {
+        previous.stop();
+    }
+    let (stream, receiver) = stream_app_logs(session, device, package)?;
+    *watched_stream().lock().unwrap() = Some(stream);
+    let project = session.current_project.clone();
+    shared_log_buffer().lock().unwrap().clear();
+    thread::spawn(move || {
+        let result = Session::new().and_then(|mut session| {
+            session.get_all_paths()?;
+            session.current_project = project;
+            pump_logs(&mut session, receiver, false)
+        });
+        if let Err(e) = result {
+            println!("android log stream stopped: {}", e);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CRASH_BUFFER: &str = include_str!("../../tests/fixtures/android/crash_buffer.txt");
+    const APP_PANIC: &str = include_str!("../../tests/fixtures/android/app_panic.txt");
+
+    fn reports(log: &str, kind: &StreamKind) -> Vec<CrashReport> {
+        let mut detector = CrashDetector::default();
+        let mut reports: Vec<CrashReport> = log.lines().filter_map(parse_logcat_line).filter_map(|l| detector.feed(&l)).collect();
+        reports.extend(detector.finish());
+        reports.into_iter().filter(|r| kind.reports(r)).collect()
+    }
+
+    #[test]
+    fn reports_only_the_apps_tombstones() {
+        let kind = StreamKind::Crashes { pid: 3377, package: "com.example.demo".to_string() };
+        let crashes = reports(CRASH_BUFFER, &kind);
+        assert_eq!(crashes.len(), 1);
+        assert_eq!(crashes[0].frames[0].library, "/data/app/com.example.demo/lib/arm64/libmain.so");
+        assert_eq!(crashes[0].frames[0].symbol.as_deref(), Some("main::crash+16"));
+
+        let other = StreamKind::Crashes { pid: 4410, package: "com.other.app".to_string() };
+        let crashes = reports(CRASH_BUFFER, &other);
+        assert_eq!(crashes.len(), 1);
+        assert!(crashes[0].summary.starts_with("Fatal signal 11"));
+    }
+
+    #[test]
+    fn reports_panics_from_the_app_stream() {
+        let panics = reports(APP_PANIC, &StreamKind::App);
+        assert_eq!(panics.len(), 1);
+        assert_eq!(panics[0].summary, "thread 'main' panicked at src/lib.rs:12:5:");
+        assert_eq!(panics[0].lines.len(), 2);
+        //native crashes on the app stream are left to the crash buffer's tombstone
+        assert!(reports(CRASH_BUFFER, &StreamKind::App).is_empty());
+    }
+
+    //a stand-in for adb logcat printing the lines it's given, then following the log until it's killed if `follow` is set
+    fn logcat(lines: &str, follow: bool) -> Child {
+        let script = if follow { "printf '%s' \"$0\"; exec sleep 30" } else { "printf '%s' \"$0\"" };
+        Command::new("sh").args(["-c", script, lines]).stdout(Stdio::piped()).stderr(Stdio::null()).spawn().unwrap()
+    }
+
+    fn crash_kind() -> StreamKind {
+        StreamKind::Crashes { pid: 3377, package: "com.example.demo".to_string() }
+    }
+
+    fn events(receiver: &Receiver<LogEvent>) -> Vec<String> {
+        receiver
+            .iter()
+            .map(|event| match event {
+                LogEvent::Line(line) => format!("line {}", line.message),
+                LogEvent::Crash(report) => format!("crash {}", report.summary),
+                LogEvent::Exited => "exited".to_string(),
+            })
+            .collect()
+    }
+
+    fn all_exited(stream: &LogStream) -> bool {
+        stream.children.iter().all(|child| matches!(child.lock().unwrap().try_wait(), Ok(Some(_))))
+    }
+
+    #[test]
+    fn ends_both_streams_when_the_app_dies() {
+        let (sender, receiver) = mpsc::channel();
+        let mut stream = LogStream::new();
+        stream.read(logcat(APP_PANIC, true), sender.clone(), StreamKind::App);
+        stream.read(logcat(CRASH_BUFFER, true), sender, crash_kind());
+        let app_running = Arc::new(AtomicBool::new(true));
+        let running = app_running.clone();
+        stream.watch_exit(Duration::from_millis(20), Duration::from_millis(20), move || running.load(Ordering::SeqCst));
+        thread::sleep(Duration::from_millis(100));
+        assert!(!all_exited(&stream));
+
+        app_running.store(false, Ordering::SeqCst);
+        let events = events(&receiver);
+        assert!(events.contains(&"line loading assets".to_string()));
+        //the panic and the app's tombstone from the crash buffer arrive before the stream ends
+        assert!(events.contains(&"crash thread 'main' panicked at src/lib.rs:12:5:".to_string()));
+        assert_eq!(events.iter().filter(|e| e.starts_with("crash")).count(), 2);
+        assert_eq!(events.iter().filter(|e| *e == "exited").count(), 1);
+        assert_eq!(events.last().map(|e| e.as_str()), Some("exited"));
+        assert!(all_exited(&stream));
+    }
+
+    #[test]
+    fn ends_the_crash_stream_when_the_app_stream_ends() {
+        let (sender, receiver) = mpsc::channel();
+        let mut stream = LogStream::new();
+        stream.read(logcat(APP_PANIC, false), sender.clone(), StreamKind::App);
+        stream.read(logcat("", true), sender, crash_kind());
+        stream.watch_exit(Duration::from_millis(20), Duration::ZERO, || true);
+        assert_eq!(events(&receiver).last().map(|e| e.as_str()), Some("exited"));
+        assert!(all_exited(&stream));
+    }
+
+    #[test]
+    fn stopping_ends_the_stream_without_an_exit() {
+        let (sender, receiver) = mpsc::channel();
+        let mut stream = LogStream::new();
+        stream.read(logcat("", true), sender.clone(), StreamKind::App);
+        stream.read(logcat("", true), sender, crash_kind());
+        stream.watch_exit(Duration::from_millis(20), Duration::ZERO, || true);
+        stream.stop();
+        assert!(all_exited(&stream));
+        assert!(events(&receiver).is_empty());
+    }
+}
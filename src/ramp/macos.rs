@@ -0,0 +1,525 @@
+use super::helper::{capitalize_first, is_command_available};
+use super::manifest::ProjectManifest;
+use super::plist::set_plist_string;
+use super::session::Session;
+use image::{ImageBuffer, Rgba};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+//finder window size used for the dmg layout and generated background
+const DMG_WINDOW: (u32, u32) = (640, 400);
+
+//the two slices combined into a universal macos binary
+pub const MACOS_TARGETS: [&str; 2] = ["aarch64-apple-darwin", "x86_64-apple-darwin"];
+
+const FAT_MAGIC: u32 = 0xcafe_babe;
+const MH_MAGIC_64: u32 = 0xfeed_facf;
+const MH_MAGIC: u32 = 0xfeed_face;
+
+pub const CPU_TYPE_X86_64: u32 = 0x0100_0007;
+pub const CPU_TYPE_ARM64: u32 = 0x0100_000c;
+
+//a single architecture slice of a mach-o file
+#[derive(Debug, Clone, PartialEq)]
+pub struct MachSlice {
+    pub cpu_type: u32,
+    pub cpu_subtype: u32,
+    pub data: Vec<u8>,
+}
+
+impl MachSlice {
+    pub fn arch_name(&self) -> &'static str {
+        match self.cpu_type {
+            CPU_TYPE_ARM64 => "arm64",
+            CPU_TYPE_X86_64 => "x86_64",
+            _ => "unknown",
+        }
+    }
+
+    //page alignment used by lipo, 16k for arm64 and 4k for everything else
+    fn align_bits(&self) -> u32 {
+        if self.cpu_type == CPU_TYPE_ARM64 { 14 } else { 12 }
+    }
+}
+
+fn be_u32(bytes: &[u8], offset: usize) -> io::Result<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "truncated mach-o header"))
+}
+
+fn le_u32(bytes: &[u8], offset: usize) -> io::Result<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "truncated mach-o header"))
+}
+
+//split a thin or fat mach-o file into its architecture slices
+pub fn mach_slices(bytes: &[u8]) -> io::Result<Vec<MachSlice>> {
+    match be_u32(bytes, 0)? {
+        FAT_MAGIC => {
+            let count = be_u32(bytes, 4)? as usize;
+            //nfat_arch is untrusted, every fat_arch entry has to be in the file before anything is allocated for it
+            if count > (bytes.len() - 8) / 20 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "fat header lists more slices than the file holds"));
+            }
+            let mut slices = Vec::with_capacity(count);
+            for i in 0..count {
+                let entry = 8 + i * 20;
+                let offset = be_u32(bytes, entry + 8)? as usize;
+                let size = be_u32(bytes, entry + 12)? as usize;
+                let data = bytes
+                    .get(offset..offset + size)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "fat slice overruns the file"))?;
+                slices.push(MachSlice {
+                    cpu_type: be_u32(bytes, entry)?,
+                    cpu_subtype: be_u32(bytes, entry + 4)?,
+                    data: data.to_vec(),
+                });
+            }
+            Ok(slices)
+        }
+        _ => match le_u32(bytes, 0)? {
+            MH_MAGIC_64 | MH_MAGIC => Ok(vec![MachSlice {
+                cpu_type: le_u32(bytes, 4)?,
+                cpu_subtype: le_u32(bytes, 8)?,
+                data: bytes.to_vec(),
+            }]),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "not a mach-o binary")),
+        },
+    }
+}
+
+//combine thin or fat mach-o binaries into one universal binary, the equivalent of `lipo -create`
+pub fn create_universal(binaries: &[Vec<u8>]) -> io::Result<Vec<u8>> {
+    let mut slices: Vec<MachSlice> = Vec::new();
+    for binary in binaries {
+        for slice in mach_slices(binary)? {
+            if slices.iter().any(|s| s.cpu_type == slice.cpu_type) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("duplicate {} slice in universal binary inputs", slice.arch_name()),
+                ));
+            }
+            slices.push(slice);
+        }
+    }
+    if slices.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no binaries to combine"));
+    }
+    //lipo orders slices by alignment so the smaller 4k aligned slices come first
+    slices.sort_by_key(|s| (s.align_bits(), s.cpu_type));
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&FAT_MAGIC.to_be_bytes());
+    header.extend_from_slice(&(slices.len() as u32).to_be_bytes());
+    let mut offset = 8 + 20 * slices.len();
+    let mut placements = Vec::new();
+    for slice in &slices {
+        let align = 1usize << slice.align_bits();
+        offset = offset.div_ceil(align) * align;
+        let size = slice.data.len();
+        if offset + size > u32::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "universal binary exceeds 4GB"));
+        }
+        header.extend_from_slice(&slice.cpu_type.to_be_bytes());
+        header.extend_from_slice(&slice.cpu_subtype.to_be_bytes());
+        header.extend_from_slice(&(offset as u32).to_be_bytes());
+        header.extend_from_slice(&(size as u32).to_be_bytes());
+        header.extend_from_slice(&slice.align_bits().to_be_bytes());
+        placements.push(offset);
+        offset += size;
+    }
+    let mut universal = header;
+    for (slice, placement) in slices.iter().zip(placements) {
+        universal.resize(placement, 0);
+        universal.extend_from_slice(&slice.data);
+    }
+    Ok(universal)
+}
+
+//assemble a .app bundle: Contents/MacOS/<exe>, Contents/Resources/<icon>.icns and Contents/Info.plist
+pub fn assemble_app_bundle(
+    bundle_path: &Path,
+    executable_name: &str,
+    binary: &[u8],
+    info_plist: &str,
+    icns: Option<&[u8]>,
+) -> io::Result<()> {
+    if bundle_path.exists() {
+        fs::remove_dir_all(bundle_path)?;
+    }
+    let contents = bundle_path.join("Contents");
+    let macos_dir = contents.join("MacOS");
+    let resources_dir = contents.join("Resources");
+    fs::create_dir_all(&macos_dir)?;
+    fs::create_dir_all(&resources_dir)?;
+
+    let executable = macos_dir.join(executable_name);
+    fs::write(&executable, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&executable, fs::Permissions::from_mode(0o755))?;
+    }
+
+    let mut plist = set_plist_string(info_plist, "CFBundleExecutable", executable_name)?;
+    if let Some(icns) = icns {
+        fs::write(resources_dir.join(format!("{}.icns", executable_name)), icns)?;
+        plist = set_plist_string(&plist, "CFBundleIconFile", executable_name)?;
+    }
+    fs::write(contents.join("Info.plist"), plist)?;
+    fs::write(contents.join("PkgInfo"), "APPL????")?;
+    Ok(())
+}
+
+//path of the assembled bundle inside the project's packages directory
+pub fn app_bundle_path(session: &Session) -> String {
+    format!(
+        "{}/{}/packages/macos/{}.app",
+        session.projects_path.as_ref().unwrap(),
+        session.current_project.as_ref().unwrap(),
+        capitalize_first(session.current_project.as_ref().unwrap())
+    )
+}
+
+//lipo the per architecture builds together and assemble the .app from the project template
+pub fn macos_post_build(session: &mut Session, release: bool) -> io::Result<String> {
+    let project = session.current_project.as_ref().unwrap().to_string();
+    let project_path = format!("{}/{}", session.projects_path.as_ref().unwrap(), &project);
+    let profile = if release { "release" } else { "debug" };
+
+    let mut binaries = Vec::new();
+    for target in MACOS_TARGETS {
+        let path = format!("{}/target/{}/{}/{}", &project_path, target, profile, &project);
+        let binary = fs::read(&path).map_err(|e| {
+            io::Error::new(io::ErrorKind::NotFound, format!("Failed to read {} build at {}: {}", target, path, e))
+        })?;
+        binaries.push(binary);
+    }
+    let universal = create_universal(&binaries)?;
+    let archs: Vec<&str> = mach_slices(&universal)?.iter().map(|s| s.arch_name()).collect();
+    println!("created universal binary with architectures: {}", archs.join(" "));
+
+    let template_plist = format!("{}/macos/{}.app/Contents/Info.plist", &project_path, capitalize_first(&project));
+    let info_plist = fs::read_to_string(&template_plist)
+        .map_err(|e| io::Error::other(format!("Failed to read template Info.plist {}: {}", template_plist, e)))?;
+    let icns_path = format!("{}/assets/resources/icons/macos_icon.icns", &project_path);
+    let icns = fs::read(&icns_path).ok();
+    if icns.is_none() {
+        println!("no icns found at {}, run update_icons on macos to generate one", &icns_path);
+    }
+
+    let bundle_path = app_bundle_path(session);
+    assemble_app_bundle(Path::new(&bundle_path), &project, &universal, &info_plist, icns.as_deref())?;
+    println!("assembled app bundle at {}", &bundle_path);
+    Ok(bundle_path)
+}
+
+//a plain gradient background for projects that don't provide assets/resources/dmg_background.png
+pub fn default_dmg_background() -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let (width, height) = DMG_WINDOW;
+    ImageBuffer::from_fn(width, height, |_, y| {
+        let shade = 235 - (y * 40 / height) as u8;
+        Rgba([shade, shade, shade, 255])
+    })
+}
+
+//lay out the dmg contents: the .app, an Applications symlink and a hidden background image
+pub fn stage_distribution(project_path: &str, bundle_path: &Path, stage_path: &Path) -> io::Result<()> {
+    if stage_path.exists() {
+        fs::remove_dir_all(stage_path)?;
+    }
+    fs::create_dir_all(stage_path.join(".background"))?;
+    let app_name = bundle_path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "app bundle path has no file name"))?;
+    copy_tree(bundle_path, &stage_path.join(app_name))?;
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("/Applications", stage_path.join("Applications"))?;
+
+    let background = stage_path.join(".background/background.png");
+    let custom = format!("{}/assets/resources/dmg_background.png", project_path);
+    if Path::new(&custom).exists() {
+        fs::copy(&custom, &background)?;
+    } else {
+        default_dmg_background()
+            .save(&background)
+            .map_err(|e| io::Error::other(format!("Failed to save dmg background: {}", e)))?;
+    }
+    Ok(())
+}
+
+//copy a directory tree keeping permissions and symlinks intact
+fn copy_tree(source: &Path, destination: &Path) -> io::Result<()> {
+    fs::create_dir_all(destination)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let target = destination.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(fs::read_link(entry.path())?, &target)?;
+        } else if file_type.is_dir() {
+            copy_tree(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+//zip a staged directory, storing unix permissions and symlinks so the .app stays executable once extracted
+pub fn zip_tree(source: &Path, zip_path: &Path) -> io::Result<()> {
+    fn walk(zip: &mut ZipWriter<File>, root: &Path, dir: &Path) -> io::Result<()> {
+        let mut entries: Vec<PathBuf> = fs::read_dir(dir)?.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        entries.sort();
+        for path in entries {
+            let name = path.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/");
+            let metadata = fs::symlink_metadata(&path)?;
+            #[cfg(unix)]
+            let mode = {
+                use std::os::unix::fs::PermissionsExt;
+                metadata.permissions().mode() & 0o777
+            };
+            #[cfg(not(unix))]
+            let mode = 0o644;
+            let options = SimpleFileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .unix_permissions(mode);
+            let zip_error = |e: zip::result::ZipError| io::Error::other(format!("Failed to add {} to zip: {}", name, e));
+            if metadata.file_type().is_symlink() {
+                let target = fs::read_link(&path)?;
+                zip.add_symlink(name.clone(), target.to_string_lossy(), options).map_err(zip_error)?;
+            } else if metadata.is_dir() {
+                zip.add_directory(name.clone(), options).map_err(zip_error)?;
+                walk(zip, root, &path)?;
+            } else {
+                zip.start_file(name.clone(), options).map_err(zip_error)?;
+                zip.write_all(&fs::read(&path)?)?;
+            }
+        }
+        Ok(())
+    }
+    let mut zip = ZipWriter::new(File::create(zip_path)?);
+    walk(&mut zip, source, source)?;
+    zip.finish().map_err(|e| io::Error::other(format!("Failed to finish zip: {}", e)))?;
+    Ok(())
+}
+
+//applescript that arranges the mounted dmg window around the background image
+pub fn dmg_layout_script(volume_name: &str, app_name: &str) -> String {
+    let (width, height) = DMG_WINDOW;
+    format!(
+        r#"tell application "Finder"
+    tell disk "{volume}"
+        open
+        set current view of container window to icon view
+        set toolbar visible of container window to false
+        set statusbar visible of container window to false
+        set the bounds of container window to {{100, 100, {right}, {bottom}}}
+        set viewOptions to the icon view options of container window
+        set arrangement of viewOptions to not arranged
+        set icon size of viewOptions to 128
+        set background picture of viewOptions to file ".background:background.png"
+        set position of item "{app}" of container window to {{160, {middle}}}
+        set position of item "Applications" of container window to {{480, {middle}}}
+        close
+        open
+        update without registering applications
+        delay 1
+        close
+    end tell
+end tell
+"#,
+        volume = volume_name,
+        app = app_name,
+        right = 100 + width,
+        bottom = 100 + height,
+        middle = height / 2,
+    )
+}
+
+fn run_hdiutil(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("hdiutil")
+        .args(args)
+        .output()
+        .map_err(|e| io::Error::other(format!("Failed to run hdiutil: {}", e)))?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "hdiutil {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+//build a compressed dmg from the staged folder, styling the window with finder when possible
+pub fn create_dmg(stage_path: &Path, volume_name: &str, app_name: &str, dmg_path: &Path) -> io::Result<()> {
+    let stage = stage_path.to_string_lossy().to_string();
+    let rw_path = dmg_path.with_extension("rw.dmg");
+    let rw = rw_path.to_string_lossy().to_string();
+    let _ = fs::remove_file(&rw_path);
+    run_hdiutil(&["create", "-volname", volume_name, "-srcfolder", &stage, "-ov", "-format", "UDRW", &rw])?;
+
+    //styling is cosmetic, a failure here still produces a working dmg
+    match run_hdiutil(&["attach", "-readwrite", "-noverify", "-noautoopen", &rw]) {
+        Ok(attached) => {
+            let device = attached.lines().find_map(|l| l.split_whitespace().next().filter(|d| d.starts_with("/dev/")));
+            let styled = Command::new("osascript")
+                .arg("-e")
+                .arg(dmg_layout_script(volume_name, app_name))
+                .output();
+            if !matches!(styled, Ok(ref output) if output.status.success()) {
+                println!("WARNING: could not style the dmg window, continuing with the default layout");
+            }
+            if let Some(device) = device {
+                run_hdiutil(&["detach", device])?;
+            }
+        }
+        Err(e) => println!("WARNING: {}", e),
+    }
+
+    let dmg = dmg_path.to_string_lossy().to_string();
+    run_hdiutil(&["convert", &rw, "-format", "UDZO", "-imagekey", "zlib-level=9", "-ov", "-o", &dmg])?;
+    fs::remove_file(&rw_path)?;
+    Ok(())
+}
+
+//package the assembled .app into a distributable zip, and a dmg when hdiutil is available
+pub fn package_macos(session: &mut Session) -> io::Result<Vec<String>> {
+    let project = session.current_project.as_ref().unwrap().to_string();
+    let project_path = format!("{}/{}", session.projects_path.as_ref().unwrap(), &project);
+    let bundle_path = app_bundle_path(session);
+    if !Path::new(&bundle_path).exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("app bundle not found at {}, build for macos first", bundle_path),
+        ));
+    }
+    let version = ProjectManifest::load(&project_path)?.version;
+    let volume_name = capitalize_first(&project);
+    let app_name = format!("{}.app", &volume_name);
+    let packages_path = format!("{}/packages/macos", &project_path);
+    let stage_path = PathBuf::from(format!("{}/dmg", &packages_path));
+    stage_distribution(&project_path, Path::new(&bundle_path), &stage_path)?;
+
+    let mut artifacts = Vec::new();
+    let zip_path = format!("{}/{}-{}-macos.zip", &packages_path, &project, &version);
+    zip_tree(&stage_path, Path::new(&zip_path))?;
+    println!("created distribution zip: {}", &zip_path);
+    artifacts.push(zip_path);
+
+    if is_command_available("hdiutil") {
+        let dmg_path = format!("{}/{}-{}.dmg", &packages_path, &project, &version);
+        create_dmg(&stage_path, &volume_name, &app_name, Path::new(&dmg_path))?;
+        println!("created dmg: {}", &dmg_path);
+        artifacts.push(dmg_path);
+    } else {
+        println!("hdiutil not available, skipping dmg (the zip contains the same layout)");
+    }
+    Ok(artifacts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ramp::helper::test_dir;
+    use crate::ramp::plist::{read_plist, root_dict, string_value};
+
+    const ARM64: &[u8] = include_bytes!("../../tests/fixtures/macos/ramp-arm64");
+    const X86_64: &[u8] = include_bytes!("../../tests/fixtures/macos/ramp-x86_64");
+    const INFO_PLIST: &str = include_str!("../../tests/fixtures/macos/Info.plist");
+
+    #[test]
+    fn reads_thin_slices() {
+        let slices = mach_slices(ARM64).unwrap();
+        assert_eq!(slices.len(), 1);
+        assert_eq!(slices[0].cpu_type, CPU_TYPE_ARM64);
+        assert_eq!(slices[0].data, ARM64);
+        assert!(mach_slices(b"not a binary").is_err());
+    }
+
+    #[test]
+    fn writes_the_fat_header_with_aligned_offsets() {
+        let universal = create_universal(&[ARM64.to_vec(), X86_64.to_vec()]).unwrap();
+        assert_eq!(be_u32(&universal, 0).unwrap(), FAT_MAGIC);
+        assert_eq!(be_u32(&universal, 4).unwrap(), 2);
+        //the 4k aligned x86_64 slice comes first, then arm64 on a 16k boundary
+        let x86 = 8;
+        assert_eq!(be_u32(&universal, x86).unwrap(), CPU_TYPE_X86_64);
+        assert_eq!(be_u32(&universal, x86 + 4).unwrap(), 3);
+        assert_eq!(be_u32(&universal, x86 + 8).unwrap(), 4096);
+        assert_eq!(be_u32(&universal, x86 + 12).unwrap(), X86_64.len() as u32);
+        assert_eq!(be_u32(&universal, x86 + 16).unwrap(), 12);
+        let arm = 28;
+        assert_eq!(be_u32(&universal, arm).unwrap(), CPU_TYPE_ARM64);
+        assert_eq!(be_u32(&universal, arm + 8).unwrap(), 16384);
+        assert_eq!(be_u32(&universal, arm + 12).unwrap(), ARM64.len() as u32);
+        assert_eq!(be_u32(&universal, arm + 16).unwrap(), 14);
+        assert_eq!(&universal[4096..4096 + X86_64.len()], X86_64);
+        assert_eq!(&universal[16384..], ARM64);
+        assert!(universal[8 + 40..4096].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn round_trips_a_universal_binary() {
+        let universal = create_universal(&[ARM64.to_vec(), X86_64.to_vec()]).unwrap();
+        let slices = mach_slices(&universal).unwrap();
+        assert_eq!(slices.iter().map(|s| s.arch_name()).collect::<Vec<_>>(), ["x86_64", "arm64"]);
+        assert_eq!(slices[1].data, ARM64);
+        //an existing universal binary can be combined again
+        assert_eq!(create_universal(std::slice::from_ref(&universal)).unwrap(), universal);
+    }
+
+    #[test]
+    fn rejects_fat_headers_larger_than_the_file() {
+        let mut universal = create_universal(&[ARM64.to_vec(), X86_64.to_vec()]).unwrap();
+        universal[4..8].copy_from_slice(&u32::MAX.to_be_bytes());
+        let error = mach_slices(&universal).unwrap_err();
+        assert_eq!(error.to_string(), "fat header lists more slices than the file holds");
+        //a slice offset past the end
+        let mut universal = create_universal(&[ARM64.to_vec(), X86_64.to_vec()]).unwrap();
+        universal[16..20].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(mach_slices(&universal).unwrap_err().to_string(), "fat slice overruns the file");
+    }
+
+    #[test]
+    fn rejects_duplicate_architectures() {
+        let error = create_universal(&[ARM64.to_vec(), ARM64.to_vec()]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(create_universal(&[]).is_err());
+    }
+
+    #[test]
+    fn assembles_the_bundle_layout() {
+        let bundle = test_dir("macos-bundle").join("Demo.app");
+        assemble_app_bundle(&bundle, "demo", ARM64, INFO_PLIST, Some(b"icns")).unwrap();
+        assert_eq!(fs::read(bundle.join("Contents/MacOS/demo")).unwrap(), ARM64);
+        assert_eq!(fs::read(bundle.join("Contents/Resources/demo.icns")).unwrap(), b"icns");
+        assert_eq!(fs::read_to_string(bundle.join("Contents/PkgInfo")).unwrap(), "APPL????");
+        let plist = read_plist(&bundle.join("Contents/Info.plist")).unwrap();
+        let dict = root_dict(&plist).unwrap();
+        assert_eq!(string_value(dict, "CFBundleExecutable"), Some("demo"));
+        assert_eq!(string_value(dict, "CFBundleIconFile"), Some("demo"));
+        assert_eq!(string_value(dict, "CFBundleName"), Some("Ramp"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(bundle.join("Contents/MacOS/demo")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+        //reassembling replaces the old bundle
+        assemble_app_bundle(&bundle, "other", X86_64, INFO_PLIST, None).unwrap();
+        assert!(!bundle.join("Contents/MacOS/demo").exists());
+        assert!(!bundle.join("Contents/Resources/demo.icns").exists());
+        fs::remove_dir_all(bundle.parent().unwrap()).unwrap();
+    }
+}
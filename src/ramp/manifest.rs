@@ -0,0 +1,405 @@
+use super::session::Session;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use toml_edit::{value, DocumentMut, Item, Table};
+
+//the section cargo-apk reads its settings from
+pub const ANDROID_SECTION: [&str; 3] = ["package", "metadata", "android"];
+
+//where cargo-apk reads AndroidManifest.xml attributes from
+pub const ANDROID_MANIFEST_SECTION: [&str; 4] = ["package", "metadata", "android", "manifest"];
+
+//settings from [package.metadata.android] used for deploying
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AndroidMetadata {
+    pub package: Option<String>,
+    pub apk_name: Option<String>,
+    pub version_code: Option<u32>,
+}
+
+//the parts of a project's Cargo.toml ramp reads, with workspace inherited fields resolved
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectManifest {
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    pub authors: Vec<String>,
+    //[lib] name, or the package name with dashes replaced as cargo does
+    pub lib_name: String,
+    //[package.metadata.ramp] company
+    pub company: Option<String>,
+    //[package.metadata.ramp] theme_color, the browser ui colour of pwa builds
+    pub theme_color: Option<String>,
+    pub android: AndroidMetadata,
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub fn parse_document(content: &str) -> io::Result<DocumentMut> {
+    content.parse::<DocumentMut>().map_err(|e| invalid(format!("invalid Cargo.toml: {}", e)))
+}
+
+//an item at a dotted path, works for [a.b] tables, inline tables and dotted keys alike
+fn item_at<'a>(document: &'a DocumentMut, path: &[&str]) -> Option<&'a Item> {
+    let (first, rest) = path.split_first()?;
+    let mut item = document.get(first)?;
+    for key in rest {
+        item = item.as_table_like()?.get(key)?;
+    }
+    Some(item)
+}
+
+fn string_at(document: &DocumentMut, path: &[&str]) -> Option<String> {
+    item_at(document, path).and_then(|i| i.as_str()).map(|s| s.to_string())
+}
+
+//true for `key.workspace = true` and `key = { workspace = true }`
+fn inherits_from_workspace(item: &Item) -> bool {
+    item.as_table_like()
+        .and_then(|t| t.get("workspace"))
+        .and_then(|w| w.as_bool())
+        .unwrap_or(false)
+}
+
+//a [package] field, looked up in [workspace.package] when the package inherits it
+fn package_field(document: &DocumentMut, workspace: Option<&DocumentMut>, key: &str) -> io::Result<Option<Item>> {
+    match item_at(document, &["package", key]) {
+        Some(item) if inherits_from_workspace(item) => {
+            let inherited = workspace.and_then(|w| item_at(w, &["workspace", "package", key])).cloned();
+            match inherited {
+                Some(item) => Ok(Some(item)),
+                None => Err(invalid(format!("package.{} is inherited but the workspace doesn't define it", key))),
+            }
+        }
+        item => Ok(item.cloned()),
+    }
+}
+
+impl ProjectManifest {
+    //parse a Cargo.toml, `workspace` is the workspace root's Cargo.toml for resolving inherited fields
+    pub fn parse(content: &str, workspace: Option<&str>) -> io::Result<Self> {
+        let document = parse_document(content)?;
+        let workspace = workspace.map(parse_document).transpose()?;
+        let field = |key: &str| package_field(&document, workspace.as_ref(), key);
+        let name = field("name")?
+            .and_then(|i| i.as_str().map(|s| s.to_string()))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "package name not found in Cargo.toml"))?;
+        let authors = field("authors")?
+            .and_then(|i| {
+                i.as_array().map(|a| a.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
+            })
+            .unwrap_or_default();
+        let lib_name = string_at(&document, &["lib", "name"]).unwrap_or_else(|| name.replace('-', "_"));
+        Ok(ProjectManifest {
+            version: field("version")?.and_then(|i| i.as_str().map(|s| s.to_string())).unwrap_or_else(|| "0.1.0".to_string()),
+            description: field("description")?.and_then(|i| i.as_str().map(|s| s.to_string())),
+            authors,
+            lib_name,
+            company: string_at(&document, &["package", "metadata", "ramp", "company"]),
+            theme_color: string_at(&document, &["package", "metadata", "ramp", "theme_color"]),
+            android: AndroidMetadata {
+                package: string_at(&document, &[&ANDROID_SECTION[..], &["package"]].concat()),
+                apk_name: string_at(&document, &[&ANDROID_SECTION[..], &["apk_name"]].concat()),
+                version_code: item_at(&document, &[&ANDROID_MANIFEST_SECTION[..], &["version_code"]].concat())
+                    .and_then(|i| i.as_integer())
+                    .map(|c| u32::try_from(c).map_err(|_| invalid(format!("invalid version_code: {}", c))))
+                    .transpose()?,
+            },
+            name,
+        })
+    }
+
+    //read the Cargo.toml in project_path, resolving workspace inheritance from the nearest enclosing workspace
+    pub fn load(project_path: &str) -> io::Result<Self> {
+        let content = fs::read_to_string(format!("{}/Cargo.toml", project_path))?;
+        let workspace = match find_workspace_root(Path::new(project_path)) {
+            Some(root) => Some(fs::read_to_string(root.join("Cargo.toml"))?),
+            None => None,
+        };
+        ProjectManifest::parse(&content, workspace.as_deref())
+    }
+
+    //the application id to deploy and launch on android
+    pub fn android_package(&self) -> io::Result<&str> {
+        self.android
+            .package
+            .as_deref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "package value not found in [package.metadata.android]"))
+    }
+}
+
+//the closest directory at or above the project whose Cargo.toml has a [workspace] table
+pub fn find_workspace_root(project_path: &Path) -> Option<PathBuf> {
+    project_path.ancestors().find_map(|dir| {
+        let content = fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+        let document = content.parse::<DocumentMut>().ok()?;
+        document.contains_key("workspace").then(|| dir.to_path_buf())
+    })
+}
+
+pub fn current_project_manifest(session: &Session) -> io::Result<ProjectManifest> {
+    ProjectManifest::load(&format!(
+        "{}/{}",
+        session.projects_path.as_ref().unwrap(),
+        session.current_project.as_ref().unwrap()
+    ))
+}
+
+//true if a dependency table such as build-dependencies declares the crate
+pub fn declares_dependency(content: &str, table: &str, krate: &str) -> io::Result<bool> {
+    let document = parse_document(content)?;
+    Ok(item_at(&document, &[table]).and_then(|t| t.as_table_like()).is_some_and(|t| t.contains_key(krate)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VersionPart {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl VersionPart {
+    pub fn parse(part: &str) -> Option<Self> {
+        match part {
+            "major" => Some(VersionPart::Major),
+            "minor" => Some(VersionPart::Minor),
+            "patch" => Some(VersionPart::Patch),
+            _ => None,
+        }
+    }
+}
+
+//the next semver version, pre-release and build metadata are dropped
+pub fn bump_version(version: &str, part: VersionPart) -> io::Result<String> {
+    let core = version.split(['-', '+']).next().unwrap_or("");
+    let numbers: Vec<u64> = core
+        .split('.')
+        .map(|p| p.parse().map_err(|_| invalid(format!("invalid version: {}", version))))
+        .collect::<io::Result<_>>()?;
+    let [major, minor, patch] = numbers[..] else {
+        return Err(invalid(format!("expected major.minor.patch, got {}", version)));
+    };
+    Ok(match part {
+        VersionPart::Major => format!("{}.0.0", major + 1),
+        VersionPart::Minor => format!("{}.{}.0", major, minor + 1),
+        VersionPart::Patch => format!("{}.{}.{}", major, minor, patch + 1),
+    })
+}
+
+//format preserving edits to a Cargo.toml, comments, ordering and untouched values are kept as written
+#[derive(Debug, Clone)]
+pub struct ManifestEditor {
+    document: DocumentMut,
+}
+
+impl ManifestEditor {
+    pub fn parse(content: &str) -> io::Result<Self> {
+        Ok(ManifestEditor { document: parse_document(content)? })
+    }
+
+    //the table at path, creating missing tables as [a.b.c] headers
+    fn table_mut(&mut self, path: &[&str]) -> io::Result<&mut Table> {
+        let mut table = self.document.as_table_mut();
+        for key in path {
+            if let Some(inline) = table.get(key).and_then(|i| i.as_inline_table()) {
+                //promote `key = { .. }` to a [section] so new keys go on their own lines
+                let comment = inline.decor().suffix().cloned();
+                let mut promoted = inline.clone().into_table();
+                //a trailing comment moves to the end of the new header
+                if let Some(comment) = comment {
+                    promoted.decor_mut().set_suffix(comment);
+                }
+                table.insert(key, Item::Table(promoted));
+                if let Some(mut header) = table.key_mut(key) {
+                    header.leaf_decor_mut().clear();
+                }
+            }
+            let item = table.entry(key).or_insert_with(|| {
+                let mut new = Table::new();
+                new.set_implicit(true);
+                new.decor_mut().set_prefix("\n");
+                Item::Table(new)
+            });
+            table = item.as_table_mut().ok_or_else(|| invalid(format!("{} in Cargo.toml is not a table", key)))?;
+        }
+        table.set_implicit(false);
+        Ok(table)
+    }
+
+    pub fn set_string(&mut self, path: &[&str], key: &str, new_value: &str) -> io::Result<()> {
+        let table = self.table_mut(path)?;
+        match table.get_mut(key).and_then(|i| i.as_value_mut()) {
+            //keep the decor (spacing and trailing comment) of the existing value
+            Some(existing) => {
+                let decor = existing.decor().clone();
+                *existing = new_value.into();
+                *existing.decor_mut() = decor;
+            }
+            None => {
+                table.insert(key, value(new_value));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_integer(&mut self, path: &[&str], key: &str, new_value: i64) -> io::Result<()> {
+        let table = self.table_mut(path)?;
+        match table.get_mut(key).and_then(|i| i.as_value_mut()) {
+            Some(existing) => {
+                let decor = existing.decor().clone();
+                *existing = new_value.into();
+                *existing.decor_mut() = decor;
+            }
+            None => {
+                table.insert(key, value(new_value));
+            }
+        }
+        Ok(())
+    }
+
+    //set [package] version, refusing packages that inherit it from the workspace
+    pub fn set_version(&mut self, version: &str) -> io::Result<()> {
+        if item_at(&self.document, &["package", "version"]).is_some_and(inherits_from_workspace) {
+            return Err(invalid("the version is inherited from the workspace, bump it in the workspace Cargo.toml".to_string()));
+        }
+        self.set_string(&["package"], "version", version)
+    }
+
+    pub fn set_android_package(&mut self, package: &str) -> io::Result<()> {
+        self.set_string(&ANDROID_SECTION, "package", package)
+    }
+
+    pub fn set_version_code(&mut self, version_code: u32) -> io::Result<()> {
+        self.set_integer(&ANDROID_MANIFEST_SECTION, "version_code", version_code as i64)
+    }
+}
+
+impl std::fmt::Display for ManifestEditor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.document)
+    }
+}
+
+//apply edits to the Cargo.toml in project_path and write it back
+pub fn edit_manifest(project_path: &str, edit: impl FnOnce(&mut ManifestEditor) -> io::Result<()>) -> io::Result<()> {
+    let path = format!("{}/Cargo.toml", project_path);
+    let mut editor = ManifestEditor::parse(&fs::read_to_string(&path)?)?;
+    edit(&mut editor)?;
+    fs::write(&path, editor.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ramp::apk::base_version_code;
+    use crate::ramp::axml::launch_target_from_manifest;
+    use crate::ramp::helper::test_dir;
+    use crate::ramp::windows::WindowsVersionInfo;
+
+    const WORKSPACE: &str = include_str!("../../tests/fixtures/manifest/workspace.toml");
+    const MEMBER: &str = include_str!("../../tests/fixtures/manifest/member.toml");
+
+    #[test]
+    fn loads_fields_inherited_from_the_workspace() {
+        let root = test_dir("manifest");
+        fs::create_dir_all(root.join("demo")).unwrap();
+        fs::write(root.join("Cargo.toml"), WORKSPACE).unwrap();
+        fs::write(root.join("demo/Cargo.toml"), MEMBER).unwrap();
+
+        let manifest = ProjectManifest::load(&root.join("demo").display().to_string()).unwrap();
+        assert_eq!(manifest.version, "2.3.4");
+        assert_eq!(manifest.description.as_deref(), Some("a workspace app"));
+        assert_eq!(manifest.theme_color.as_deref(), Some("#1b6ef3"));
+        assert_eq!(base_version_code(&manifest), 20304);
+        let info = WindowsVersionInfo::from_manifest(&manifest);
+        assert_eq!(info.company, "Ramp Team");
+        assert_eq!(info.version, "2.3.4");
+        assert_eq!(launch_target_from_manifest(&manifest).unwrap().component(), "com.example.demo/android.app.NativeActivity");
+    }
+
+    #[test]
+    fn rejects_inherited_fields_without_a_workspace() {
+        assert!(ProjectManifest::parse(MEMBER, None).is_err());
+    }
+
+    const COMMENTED: &str = include_str!("../../tests/fixtures/manifest/commented.toml");
+
+    #[test]
+    fn keeps_comments_when_setting_values() {
+        let mut editor = ManifestEditor::parse(COMMENTED).unwrap();
+        editor.set_version("0.4.0").unwrap();
+        editor.set_android_package("com.example.renamed").unwrap();
+        assert_eq!(
+            editor.to_string(),
+            COMMENTED
+                .replace("version = \"0.3.1\" # bumped by ramp", "version = \"0.4.0\" # bumped by ramp")
+                .replace("package = \"com.example.demo\"", "package = \"com.example.renamed\"")
+        );
+    }
+
+    #[test]
+    fn promotes_inline_tables_to_sections() {
+        let mut editor = ManifestEditor::parse(COMMENTED).unwrap();
+        editor.set_version_code(5).unwrap();
+        assert_eq!(
+            editor.to_string(),
+            r#"# the demo app
+[package]
+name = "demo"     # also the apk name
+version = "0.3.1" # bumped by ramp
+edition = "2021"
+
+[package.metadata.android]
+package = "com.example.demo"
+
+[package.metadata.android.manifest] # kept in sync by ramp
+version_code = 5
+target_sdk_version = 34
+
+[dependencies]
+pelican_ui = "2.0.6"
+"#
+        );
+        let reparsed = ProjectManifest::parse(&editor.to_string(), None).unwrap();
+        assert_eq!(reparsed.android.version_code, Some(5));
+    }
+
+    #[test]
+    fn creates_missing_sections() {
+        let mut editor = ManifestEditor::parse("[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\n").unwrap();
+        editor.set_android_package("com.example.demo").unwrap();
+        editor.set_version_code(7).unwrap();
+        assert_eq!(
+            editor.to_string(),
+            "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[package.metadata.android]\npackage = \"com.example.demo\"\n\n[package.metadata.android.manifest]\nversion_code = 7\n\n[dependencies]\n"
+        );
+    }
+
+    #[test]
+    fn refuses_to_set_an_inherited_version() {
+        let mut editor = ManifestEditor::parse(MEMBER).unwrap();
+        assert!(editor.set_version("3.0.0").unwrap_err().to_string().contains("inherited from the workspace"));
+        assert_eq!(editor.to_string(), MEMBER);
+        //values that aren't tables can't be written into
+        let mut editor = ManifestEditor::parse("package = \"demo\"\n").unwrap();
+        assert!(editor.set_version("1.0.0").is_err());
+    }
+
+    #[test]
+    fn bumps_each_version_part() {
+        assert_eq!(bump_version("1.2.3", VersionPart::Major).unwrap(), "2.0.0");
+        assert_eq!(bump_version("1.2.3", VersionPart::Minor).unwrap(), "1.3.0");
+        assert_eq!(bump_version("1.2.3", VersionPart::Patch).unwrap(), "1.2.4");
+        assert_eq!(bump_version("0.9.9", VersionPart::Minor).unwrap(), "0.10.0");
+        //pre-release and build metadata are dropped
+        assert_eq!(bump_version("1.2.3-beta.1", VersionPart::Patch).unwrap(), "1.2.4");
+        assert_eq!(bump_version("1.2.3+build.5", VersionPart::Major).unwrap(), "2.0.0");
+        assert_eq!(bump_version("1.2.3-rc.1+5", VersionPart::Minor).unwrap(), "1.3.0");
+        for version in ["1.2", "1.2.3.4", "v1.2.3", "", "1.x.3"] {
+            assert!(bump_version(version, VersionPart::Patch).is_err(), "{}", version);
+        }
+    }
+}
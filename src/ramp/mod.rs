@@ -1,4 +1,25 @@
 pub mod core;
 pub mod install;
 pub mod helper;
-pub mod session;
\ No newline at end of file
+pub mod session;
+pub mod web;
+pub mod bundle;
+pub mod pwa;
+pub mod windows;
+pub mod buildrs;
+pub mod macos;
+pub mod keystore;
+pub mod aab;
+pub mod apk;
+pub mod axml;
+pub mod adb;
+pub mod emulator;
+pub mod logcat;
+pub mod manifest;
+pub mod plist;
+pub mod cms;
+pub mod profiles;
+pub mod wizard;
+pub mod watcher;
+pub mod appstore;
+pub mod notarize;
@@ -0,0 +1,424 @@
+use super::appstore::{API_ISSUER_ID_KEY, API_KEY_ID_KEY, API_KEY_PATH_KEY};
+use super::install::has_signing_identity;
+use super::session::{Session, SigningIdentity};
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+//ramp config key for credentials saved with `xcrun notarytool store-credentials`
+pub const NOTARY_PROFILE_KEY: &str = "notary_keychain_profile";
+
+//apple usually answers within minutes but busy days take much longer
+pub const DEFAULT_NOTARY_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+pub const DEFAULT_NOTARY_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+//what a finished command printed and whether it exited successfully
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CommandOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+//runs the signing and notarization tools, swapped for a fake to exercise the flow without apple's servers
+pub trait CommandRunner {
+    fn run(&self, program: &str, args: &[String]) -> io::Result<CommandOutput>;
+}
+
+//runs commands on this machine
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, program: &str, args: &[String]) -> io::Result<CommandOutput> {
+        let output = Command::new(program)
+            .args(args)
+            .output()
+            .map_err(|e| io::Error::new(e.kind(), format!("Failed to run {}: {}", program, e)))?;
+        Ok(CommandOutput {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+}
+
+//how notarytool authenticates with apple
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotaryAuth {
+    //credentials stored in the keychain by `xcrun notarytool store-credentials <profile>`
+    KeychainProfile(String),
+    //the App Store Connect API key set up with -appstore-key
+    ApiKey { key_path: String, key_id: String, issuer_id: String },
+}
+
+impl NotaryAuth {
+    //the keychain profile when one is set, otherwise the App Store Connect API key
+    pub fn from_config(session: &Session) -> io::Result<Option<Self>> {
+        let setting = |key: &str| -> io::Result<Option<String>> { Ok(session.get_config(key)?.filter(|v| !v.is_empty())) };
+        if let Some(profile) = setting(NOTARY_PROFILE_KEY)? {
+            return Ok(Some(NotaryAuth::KeychainProfile(profile)));
+        }
+        Ok(match (setting(API_KEY_PATH_KEY)?, setting(API_KEY_ID_KEY)?, setting(API_ISSUER_ID_KEY)?) {
+            (Some(key_path), Some(key_id), Some(issuer_id)) => Some(NotaryAuth::ApiKey { key_path, key_id, issuer_id }),
+            _ => None,
+        })
+    }
+
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            NotaryAuth::KeychainProfile(profile) => vec!["--keychain-profile".to_string(), profile.clone()],
+            NotaryAuth::ApiKey { key_path, key_id, issuer_id } => vec![
+                "--key".to_string(),
+                key_path.clone(),
+                "--key-id".to_string(),
+                key_id.clone(),
+                "--issuer".to_string(),
+                issuer_id.clone(),
+            ],
+        }
+    }
+}
+
+fn strings(args: &[&str]) -> Vec<String> {
+    args.iter().map(|a| a.to_string()).collect()
+}
+
+//codesign arguments for an app bundle, releases get the hardened runtime and a secure timestamp notarization requires
+pub fn codesign_args(identity: &str, entitlements: Option<&Path>, bundle: &Path, release: bool) -> Vec<String> {
+    let mut args = strings(&["--force", "--sign", identity]);
+    if release {
+        args.extend(strings(&["--options", "runtime", "--timestamp"]));
+    }
+    if let Some(entitlements) = entitlements {
+        args.extend(["--entitlements".to_string(), entitlements.display().to_string()]);
+    }
+    args.push(bundle.display().to_string());
+    args
+}
+
+//notarytool takes zips, ditto keeps the bundle's symlinks and signature intact
+pub fn archive_args(bundle: &Path, archive: &Path) -> Vec<String> {
+    vec!["-c".to_string(), "-k".to_string(), "--keepParent".to_string(), bundle.display().to_string(), archive.display().to_string()]
+}
+
+//the xcrun arguments of a notarytool subcommand
+pub fn notarytool_args(auth: &NotaryAuth, subcommand: &str, target: &str, json: bool) -> Vec<String> {
+    let mut args = strings(&["notarytool", subcommand, target]);
+    args.extend(auth.args());
+    if json {
+        args.extend(strings(&["--output-format", "json"]));
+    }
+    args
+}
+
+pub fn staple_args(bundle: &Path) -> Vec<String> {
+    vec!["stapler".to_string(), "staple".to_string(), bundle.display().to_string()]
+}
+
+//the state of a submission as notarytool info reports it
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotaryStatus {
+    InProgress,
+    Accepted,
+    Invalid,
+    Rejected,
+}
+
+impl NotaryStatus {
+    pub fn parse(status: &str) -> io::Result<Self> {
+        match status {
+            "In Progress" => Ok(NotaryStatus::InProgress),
+            "Accepted" => Ok(NotaryStatus::Accepted),
+            "Invalid" => Ok(NotaryStatus::Invalid),
+            "Rejected" => Ok(NotaryStatus::Rejected),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown notarization status: {}", status))),
+        }
+    }
+}
+
+fn checked(program: &str, output: CommandOutput) -> io::Result<CommandOutput> {
+    if !output.success {
+        let detail = if output.stderr.trim().is_empty() { &output.stdout } else { &output.stderr };
+        return Err(io::Error::other(format!("{} failed: {}", program, detail.trim())));
+    }
+    Ok(output)
+}
+
+fn json_field(output: &CommandOutput, field: &str) -> io::Result<String> {
+    let value: Value = serde_json::from_str(output.stdout.trim())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("invalid notarytool output: {}", e)))?;
+    value[field]
+        .as_str()
+        .map(|v| v.to_string())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("notarytool output has no {}", field)))
+}
+
+//sign an app bundle, with the project's entitlements when it has them
+pub fn sign_app(runner: &impl CommandRunner, identity: &str, entitlements: Option<&Path>, bundle: &Path, release: bool) -> io::Result<()> {
+    checked("codesign", runner.run("codesign", &codesign_args(identity, entitlements, bundle, release))?)?;
+    Ok(())
+}
+
+//submits, waits on and staples macos builds through a command runner
+pub struct Notarizer<R: CommandRunner> {
+    pub runner: R,
+    pub auth: NotaryAuth,
+    pub timeout: Duration,
+    pub poll_interval: Duration,
+}
+
+impl<R: CommandRunner> Notarizer<R> {
+    pub fn new(runner: R, auth: NotaryAuth) -> Self {
+        Notarizer { runner, auth, timeout: DEFAULT_NOTARY_TIMEOUT, poll_interval: DEFAULT_NOTARY_POLL_INTERVAL }
+    }
+
+    pub fn with_polling(mut self, poll_interval: Duration, timeout: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self.timeout = timeout;
+        self
+    }
+
+    fn xcrun(&self, args: Vec<String>) -> io::Result<CommandOutput> {
+        checked("xcrun", self.runner.run("xcrun", &args)?)
+    }
+
+    //upload the archive, returning the submission id
+    pub fn submit(&self, archive: &Path) -> io::Result<String> {
+        let output = self.xcrun(notarytool_args(&self.auth, "submit", &archive.display().to_string(), true))?;
+        json_field(&output, "id")
+    }
+
+    pub fn status(&self, id: &str) -> io::Result<NotaryStatus> {
+        let output = self.xcrun(notarytool_args(&self.auth, "info", id, true))?;
+        NotaryStatus::parse(&json_field(&output, "status")?)
+    }
+
+    //apple's explanation of a rejected submission
+    pub fn log(&self, id: &str) -> io::Result<String> {
+        Ok(self.xcrun(notarytool_args(&self.auth, "log", id, false))?.stdout)
+    }
+
+    //poll until the submission is accepted, failing with its log when apple rejects it or TimedOut after the timeout
+    pub fn wait(&self, id: &str) -> io::Result<()> {
+        let start_time = Instant::now();
+        loop {
+            match self.status(id)? {
+                NotaryStatus::Accepted => return Ok(()),
+                NotaryStatus::InProgress => {}
+                status => {
+                    let log = self.log(id).unwrap_or_else(|e| format!("no log: {}", e));
+                    return Err(io::Error::other(format!("notarization {} was {:?}:\n{}", id, status, log.trim())));
+                }
+            }
+            if start_time.elapsed() >= self.timeout {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("notarization {} still in progress after {} seconds, check it with xcrun notarytool info {}", id, self.timeout.as_secs(), id),
+                ));
+            }
+            println!("waiting on notarization {}", id);
+            sleep(self.poll_interval);
+        }
+    }
+
+    pub fn staple(&self, bundle: &Path) -> io::Result<()> {
+        self.xcrun(staple_args(bundle))?;
+        Ok(())
+    }
+
+    //archive the signed bundle, notarize it and staple the ticket so gatekeeper accepts it offline
+    pub fn notarize(&self, bundle: &Path, archive: &Path) -> io::Result<()> {
+        checked("ditto", self.runner.run("ditto", &archive_args(bundle, archive))?)?;
+        let submitted = self.submit(archive);
+        let _ = fs::remove_file(archive);
+        let id = submitted?;
+        println!("submitted {} for notarization as {}", bundle.display(), id);
+        self.wait(&id)?;
+        println!("notarization {} accepted", id);
+        self.staple(bundle)
+    }
+}
+
+//notarize and staple the project's signed release bundle, skipped until notary credentials are set up
+pub fn notarize_build(session: &Session, bundle: &Path) -> io::Result<()> {
+    //sign_build leaves the bundle unsigned off macos or without the identity
+    if session.os.as_str() != "macos" || !has_signing_identity(&session.certs.macos_release)? {
+        return Ok(());
+    }
+    if !session.certs.macos_release.contains(SigningIdentity::DeveloperId.common_name()) {
+        println!("{} is not a Developer ID identity, skipping notarization", session.certs.macos_release);
+        return Ok(());
+    }
+    let Some(auth) = NotaryAuth::from_config(session)? else {
+        println!("no notary credentials, skipping notarization. Set them up with ramp_gui -notary-profile <profile> or ramp_gui -appstore-key");
+        return Ok(());
+    };
+    let name = bundle.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let archive = std::env::temp_dir().join(format!("{}-notarize.zip", name));
+    Notarizer::new(SystemRunner, auth).notarize(bundle, &archive)
+}
+
+//the project's macos entitlements, if it has any
+pub fn macos_entitlements(session: &Session) -> Option<PathBuf> {
+    let path = Path::new(session.projects_path.as_ref()?)
+        .join(session.current_project.as_ref()?)
+        .join("macos/entitlements.plist");
+    path.exists().then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+
+    //answers each command with the next scripted output and records what was run
+    #[derive(Default)]
+    struct ScriptedRunner {
+        outputs: RefCell<VecDeque<CommandOutput>>,
+        calls: RefCell<Vec<(String, Vec<String>)>>,
+    }
+
+    impl ScriptedRunner {
+        fn new(outputs: Vec<CommandOutput>) -> Self {
+            ScriptedRunner { outputs: RefCell::new(outputs.into()), calls: RefCell::default() }
+        }
+
+        fn programs(&self) -> Vec<String> {
+            self.calls.borrow().iter().map(|(program, _)| program.clone()).collect()
+        }
+    }
+
+    impl CommandRunner for ScriptedRunner {
+        fn run(&self, program: &str, args: &[String]) -> io::Result<CommandOutput> {
+            self.calls.borrow_mut().push((program.to_string(), args.to_vec()));
+            self.outputs.borrow_mut().pop_front().ok_or_else(|| io::Error::other(format!("unexpected {} {:?}", program, args)))
+        }
+    }
+
+    fn ok(stdout: &str) -> CommandOutput {
+        CommandOutput { success: true, stdout: stdout.to_string(), stderr: String::new() }
+    }
+
+    fn failed(stderr: &str) -> CommandOutput {
+        CommandOutput { success: false, stdout: String::new(), stderr: stderr.to_string() }
+    }
+
+    fn status(status: &str) -> CommandOutput {
+        ok(&format!(r#"{{"id":"sub-1","status":"{}"}}"#, status))
+    }
+
+    const SUBMITTED: &str = r#"{"id":"sub-1","message":"Successfully uploaded file"}"#;
+
+    fn notarizer(outputs: Vec<CommandOutput>) -> Notarizer<ScriptedRunner> {
+        Notarizer::new(ScriptedRunner::new(outputs), NotaryAuth::KeychainProfile("ramp".to_string()))
+            .with_polling(Duration::ZERO, Duration::from_secs(60))
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        strings(args)
+    }
+
+    #[test]
+    fn signs_releases_with_the_hardened_runtime() {
+        let runner = ScriptedRunner::new(vec![ok("")]);
+        let identity = "Developer ID Application: Example (ABCDE12345)";
+        sign_app(&runner, identity, Some(Path::new("/demo/macos/entitlements.plist")), Path::new("/demo/Demo.app"), true).unwrap();
+        assert_eq!(
+            runner.calls.borrow()[0],
+            (
+                "codesign".to_string(),
+                args(&[
+                    "--force",
+                    "--sign",
+                    identity,
+                    "--options",
+                    "runtime",
+                    "--timestamp",
+                    "--entitlements",
+                    "/demo/macos/entitlements.plist",
+                    "/demo/Demo.app"
+                ])
+            )
+        );
+        assert_eq!(codesign_args("Apple Development: Example", None, Path::new("/demo/Demo.app"), false), args(&["--force", "--sign", "Apple Development: Example", "/demo/Demo.app"]));
+    }
+
+    #[test]
+    fn reports_codesign_failures() {
+        let runner = ScriptedRunner::new(vec![failed("errSecInternalComponent\n")]);
+        let error = sign_app(&runner, "Developer ID Application: Example", None, Path::new("/demo/Demo.app"), true).unwrap_err();
+        assert_eq!(error.to_string(), "codesign failed: errSecInternalComponent");
+    }
+
+    #[test]
+    fn waits_for_acceptance_then_staples() {
+        let notarizer = notarizer(vec![ok(""), ok(SUBMITTED), status("In Progress"), status("In Progress"), status("Accepted"), ok("The staple and validate action worked!")]);
+        notarizer.notarize(Path::new("/demo/Demo.app"), Path::new("/tmp/Demo-notarize.zip")).unwrap();
+        let calls = notarizer.runner.calls.borrow();
+        let keychain = ["--keychain-profile", "ramp"];
+        let json = ["--output-format", "json"];
+        let info = [&["notarytool", "info", "sub-1"][..], &keychain, &json].concat();
+        assert_eq!(
+            *calls,
+            [
+                ("ditto".to_string(), args(&["-c", "-k", "--keepParent", "/demo/Demo.app", "/tmp/Demo-notarize.zip"])),
+                ("xcrun".to_string(), args(&[&["notarytool", "submit", "/tmp/Demo-notarize.zip"][..], &keychain, &json].concat())),
+                ("xcrun".to_string(), args(&info)),
+                ("xcrun".to_string(), args(&info)),
+                ("xcrun".to_string(), args(&info)),
+                ("xcrun".to_string(), args(&["stapler", "staple", "/demo/Demo.app"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn authenticates_with_the_api_key() {
+        let auth = NotaryAuth::ApiKey { key_path: "/keys/AuthKey_TEST1234.p8".to_string(), key_id: "TEST1234".to_string(), issuer_id: "issuer".to_string() };
+        assert_eq!(
+            notarytool_args(&auth, "log", "sub-1", false),
+            args(&["notarytool", "log", "sub-1", "--key", "/keys/AuthKey_TEST1234.p8", "--key-id", "TEST1234", "--issuer", "issuer"])
+        );
+    }
+
+    #[test]
+    fn fails_with_the_log_of_an_invalid_submission() {
+        let log = r#"{"status":"Invalid","issues":[{"message":"The binary is not signed with a valid Developer ID certificate."}]}"#;
+        let notarizer = notarizer(vec![ok(""), ok(SUBMITTED), status("In Progress"), status("Invalid"), ok(log)]);
+        let error = notarizer.notarize(Path::new("/demo/Demo.app"), Path::new("/tmp/Demo-notarize.zip")).unwrap_err();
+        assert_eq!(error.to_string(), format!("notarization sub-1 was Invalid:\n{}", log));
+        let calls = notarizer.runner.calls.borrow();
+        assert_eq!(calls.last().unwrap().1, args(&["notarytool", "log", "sub-1", "--keychain-profile", "ramp"]));
+        //nothing gets stapled
+        assert_eq!(calls.len(), 5);
+    }
+
+    #[test]
+    fn times_out_while_in_progress() {
+        let notarizer = notarizer(vec![status("In Progress"), status("In Progress")]).with_polling(Duration::from_millis(20), Duration::from_millis(10));
+        let error = notarizer.wait("sub-1").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(error.to_string().contains("xcrun notarytool info sub-1"));
+        assert_eq!(notarizer.runner.programs().len(), 2);
+    }
+
+    #[test]
+    fn stops_when_the_upload_fails() {
+        let notarizer = notarizer(vec![ok(""), failed("Error: HTTP status code: 401. Unable to authenticate.")]);
+        let error = notarizer.notarize(Path::new("/demo/Demo.app"), Path::new("/tmp/Demo-notarize.zip")).unwrap_err();
+        assert_eq!(error.to_string(), "xcrun failed: Error: HTTP status code: 401. Unable to authenticate.");
+        assert_eq!(notarizer.runner.programs(), ["ditto", "xcrun"]);
+    }
+
+    #[test]
+    fn rejects_unreadable_notarytool_output() {
+        let notarizer = notarizer(vec![ok("Conducting pre-submission checks..."), status("Pending")]);
+        assert_eq!(notarizer.submit(Path::new("/tmp/Demo-notarize.zip")).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(notarizer.status("sub-1").unwrap_err().to_string(), "unknown notarization status: Pending");
+    }
+}
@@ -0,0 +1,264 @@
+use ::plist::{Dictionary, Value};
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::Path;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlistFormat {
+    Xml,
+    Binary,
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+//parse an xml or binary plist, the format is detected from the content
+pub fn parse_plist(bytes: &[u8]) -> io::Result<Value> {
+    Value::from_reader(Cursor::new(bytes)).map_err(|e| invalid(format!("invalid plist: {}", e)))
+}
+
+pub fn read_plist(path: &Path) -> io::Result<Value> {
+    let bytes = fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("Failed to read {}: {}", path.display(), e)))?;
+    parse_plist(&bytes).map_err(|e| invalid(format!("{}: {}", path.display(), e)))
+}
+
+pub fn serialize_plist(value: &Value, format: PlistFormat) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let result = match format {
+        PlistFormat::Xml => value.to_writer_xml(&mut bytes),
+        PlistFormat::Binary => value.to_writer_binary(&mut bytes),
+    };
+    result.map_err(|e| io::Error::other(format!("Failed to write plist: {}", e)))?;
+    Ok(bytes)
+}
+
+pub fn write_plist(path: &Path, value: &Value, format: PlistFormat) -> io::Result<()> {
+    fs::write(path, serialize_plist(value, format)?)
+}
+
+//the top level dictionary every Info.plist, entitlements file and profile has
+pub fn root_dict(value: &Value) -> io::Result<&Dictionary> {
+    value.as_dictionary().ok_or_else(|| invalid("plist has no top level dict".to_string()))
+}
+
+pub fn string_value<'a>(dict: &'a Dictionary, key: &str) -> Option<&'a str> {
+    dict.get(key).and_then(|v| v.as_string())
+}
+
+//replace or insert a string value for a key in an xml Info.plist
+pub fn set_plist_string(plist: &str, key: &str, value: &str) -> io::Result<String> {
+    let mut root = parse_plist(plist.as_bytes())?;
+    root.as_dictionary_mut()
+        .ok_or_else(|| invalid("Info.plist has no top level dict".to_string()))?
+        .insert(key.to_string(), Value::String(value.to_string()));
+    String::from_utf8(serialize_plist(&root, PlistFormat::Xml)?).map_err(|e| invalid(e.to_string()))
+}
+
+pub fn bundle_identifier(info_plist: &Value) -> io::Result<String> {
+    string_value(root_dict(info_plist)?, "CFBundleIdentifier")
+        .map(|s| s.trim().to_string())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "CFBundleIdentifier not found"))
+}
+
+//the decoded contents of a .mobileprovision (the plist inside its cms signature)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProvisioningProfile {
+    pub name: String,
+    pub uuid: String,
+    pub app_id_name: Option<String>,
+    pub team_identifiers: Vec<String>,
+    //udids the profile can be installed on, empty for distribution profiles
+    pub provisioned_devices: Vec<String>,
+    //enterprise profiles run on any device
+    pub provisions_all_devices: bool,
+    pub creation_date: Option<SystemTime>,
+    pub expiration_date: Option<SystemTime>,
+    pub entitlements: Dictionary,
+    pub developer_certificates: Vec<Vec<u8>>,
+}
+
+impl ProvisioningProfile {
+    pub fn from_plist(value: &Value) -> io::Result<Self> {
+        let dict = root_dict(value)?;
+        let required = |key: &str| {
+            string_value(dict, key)
+                .map(|s| s.trim().to_string())
+                .ok_or_else(|| invalid(format!("{} not found in provisioning profile", key)))
+        };
+        let strings = |key: &str| -> Vec<String> {
+            dict.get(key)
+                .and_then(|v| v.as_array())
+                .map(|a| a.iter().filter_map(|v| v.as_string()).map(|s| s.trim().to_string()).collect())
+                .unwrap_or_default()
+        };
+        let date = |key: &str| dict.get(key).and_then(|v| v.as_date()).map(SystemTime::from);
+        Ok(ProvisioningProfile {
+            name: required("Name")?,
+            uuid: required("UUID")?,
+            app_id_name: string_value(dict, "AppIDName").map(|s| s.to_string()),
+            team_identifiers: strings("TeamIdentifier"),
+            provisioned_devices: strings("ProvisionedDevices"),
+            provisions_all_devices: dict.get("ProvisionsAllDevices").and_then(|v| v.as_boolean()).unwrap_or(false),
+            creation_date: date("CreationDate"),
+            expiration_date: date("ExpirationDate"),
+            entitlements: dict.get("Entitlements").and_then(|v| v.as_dictionary()).cloned().unwrap_or_default(),
+            developer_certificates: dict
+                .get("DeveloperCertificates")
+                .and_then(|v| v.as_array())
+                .map(|a| a.iter().filter_map(|v| v.as_data()).map(|d| d.to_vec()).collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    //parse the xml `security cms -D` prints for a .mobileprovision
+    pub fn parse(bytes: &[u8]) -> io::Result<Self> {
+        ProvisioningProfile::from_plist(&parse_plist(bytes)?)
+    }
+
+    pub fn contains_device(&self, udid: &str) -> bool {
+        self.provisions_all_devices || self.provisioned_devices.iter().any(|d| d.eq_ignore_ascii_case(udid))
+    }
+
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expiration_date.is_some_and(|expiry| expiry <= now)
+    }
+
+    //TEAMID.bundle.id, or TEAMID.* for wildcard profiles
+    pub fn application_identifier(&self) -> Option<&str> {
+        string_value(&self.entitlements, "application-identifier")
+    }
+
+    //true if the profile's app id covers the bundle id, wildcard ids match by prefix
+    pub fn matches_bundle_id(&self, bundle_id: &str) -> bool {
+        let Some(app_id) = self.application_identifier() else { return false };
+        let Some((_, pattern)) = app_id.split_once('.') else { return false };
+        match pattern.strip_suffix('*') {
+            Some(prefix) => bundle_id.starts_with(prefix),
+            None => pattern == bundle_id,
+        }
+    }
+
+    //write the profile's entitlements as the xml plist codesign --entitlements expects
+    pub fn write_entitlements(&self, path: &Path) -> io::Result<()> {
+        write_plist(path, &Value::Dictionary(self.entitlements.clone()), PlistFormat::Xml)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const INFO_XML: &[u8] = include_bytes!("../../tests/fixtures/ios/Info.xml.plist");
+    const INFO_BINARY: &[u8] = include_bytes!("../../tests/fixtures/ios/Info.binary.plist");
+    const ENTITLEMENTS_XML: &[u8] = include_bytes!("../../tests/fixtures/ios/entitlements.xml.plist");
+    const ENTITLEMENTS_BINARY: &[u8] = include_bytes!("../../tests/fixtures/ios/entitlements.binary.plist");
+    //the plist `security cms -D` prints for a development .mobileprovision
+    const PROFILE_XML: &[u8] = include_bytes!("../../tests/fixtures/ios/profile.xml.plist");
+    const PROFILE_BINARY: &[u8] = include_bytes!("../../tests/fixtures/ios/profile.binary.plist");
+
+    fn with_app_id(mut profile: ProvisioningProfile, app_id: &str) -> ProvisioningProfile {
+        profile.entitlements.insert("application-identifier".to_string(), Value::String(app_id.to_string()));
+        profile
+    }
+
+    #[test]
+    fn reads_xml_and_binary_plists_alike() {
+        for (xml, binary) in [(INFO_XML, INFO_BINARY), (ENTITLEMENTS_XML, ENTITLEMENTS_BINARY), (PROFILE_XML, PROFILE_BINARY)] {
+            assert_eq!(parse_plist(xml).unwrap(), parse_plist(binary).unwrap());
+        }
+        assert_eq!(bundle_identifier(&parse_plist(INFO_BINARY).unwrap()).unwrap(), "com.example.demo");
+        let entitlements = parse_plist(ENTITLEMENTS_BINARY).unwrap();
+        assert_eq!(string_value(root_dict(&entitlements).unwrap(), "application-identifier"), Some("TEAMID1234.com.example.demo"));
+        assert!(parse_plist(&INFO_BINARY[..INFO_BINARY.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn round_trips_through_both_formats() {
+        let profile = parse_plist(PROFILE_XML).unwrap();
+        for format in [PlistFormat::Xml, PlistFormat::Binary] {
+            assert_eq!(parse_plist(&serialize_plist(&profile, format).unwrap()).unwrap(), profile);
+        }
+    }
+
+    #[test]
+    fn sets_plist_strings() {
+        let info = std::str::from_utf8(INFO_XML).unwrap();
+        let replaced = set_plist_string(info, "CFBundleIdentifier", "com.example.other").unwrap();
+        let inserted = set_plist_string(&replaced, "CFBundleDisplayName", "Other").unwrap();
+        let value = parse_plist(inserted.as_bytes()).unwrap();
+        let dict = root_dict(&value).unwrap();
+        assert_eq!(bundle_identifier(&value).unwrap(), "com.example.other");
+        assert_eq!(string_value(dict, "CFBundleDisplayName"), Some("Other"));
+        //everything else survives the round trip
+        let original = parse_plist(INFO_XML).unwrap();
+        let original = root_dict(&original).unwrap();
+        assert_eq!(dict.len(), original.len() + 1);
+        assert_eq!(dict.get("UISupportedInterfaceOrientations"), original.get("UISupportedInterfaceOrientations"));
+        assert_eq!(dict.get("LSRequiresIPhoneOS"), Some(&Value::Boolean(true)));
+        assert!(set_plist_string("<plist><array/></plist>", "CFBundleIdentifier", "x").is_err());
+    }
+
+    #[test]
+    fn reads_a_decoded_profile() {
+        let profile = ProvisioningProfile::parse(PROFILE_BINARY).unwrap();
+        assert_eq!(profile, ProvisioningProfile::parse(PROFILE_XML).unwrap());
+        assert_eq!(profile.name, "Demo Development");
+        assert_eq!(profile.uuid, "6f1c2d3e-4a5b-4c6d-8e9f-0a1b2c3d4e5f");
+        assert_eq!(profile.app_id_name.as_deref(), Some("Demo"));
+        assert_eq!(profile.team_identifiers, ["TEAMID1234"]);
+        assert_eq!(profile.provisioned_devices.len(), 2);
+        assert!(!profile.provisions_all_devices);
+        assert_eq!(profile.developer_certificates.len(), 1);
+        assert_eq!(profile.application_identifier(), Some("TEAMID1234.com.example.demo"));
+        //2027-10-01T12:00:00Z
+        let expiry = SystemTime::UNIX_EPOCH + Duration::from_secs(1_822_392_000);
+        assert_eq!(profile.expiration_date, Some(expiry));
+        assert!(!profile.is_expired(expiry - Duration::from_secs(1)));
+        assert!(profile.is_expired(expiry));
+    }
+
+    #[test]
+    fn rejects_a_profile_without_a_uuid() {
+        let mut value = parse_plist(PROFILE_XML).unwrap();
+        value.as_dictionary_mut().unwrap().remove("UUID");
+        assert!(ProvisioningProfile::from_plist(&value).is_err());
+    }
+
+    #[test]
+    fn matches_provisioned_devices() {
+        let mut profile = ProvisioningProfile::parse(PROFILE_XML).unwrap();
+        assert!(profile.contains_device("00008030-001A2B3C4D5E802E"));
+        assert!(profile.contains_device("00008030-001a2b3c4d5e802e"));
+        assert!(!profile.contains_device("00008030-001A2B3C4D5E8000"));
+        profile.provisioned_devices.clear();
+        profile.provisions_all_devices = true;
+        assert!(profile.contains_device("00008030-001A2B3C4D5E8000"));
+    }
+
+    #[test]
+    fn matches_exact_and_wildcard_bundle_ids() {
+        let profile = ProvisioningProfile::parse(PROFILE_XML).unwrap();
+        assert!(profile.matches_bundle_id("com.example.demo"));
+        assert!(!profile.matches_bundle_id("com.example.demo2"));
+        assert!(!profile.matches_bundle_id("com.example"));
+
+        let wildcard = with_app_id(profile.clone(), "TEAMID1234.com.example.*");
+        assert!(wildcard.matches_bundle_id("com.example.demo"));
+        assert!(wildcard.matches_bundle_id("com.example.other"));
+        assert!(!wildcard.matches_bundle_id("org.example.demo"));
+        assert!(with_app_id(profile.clone(), "TEAMID1234.*").matches_bundle_id("anything.at.all"));
+        assert!(!with_app_id(profile, "TEAMID1234").matches_bundle_id("TEAMID1234"));
+    }
+
+    #[test]
+    fn writes_entitlements_codesign_can_read() {
+        let profile = ProvisioningProfile::parse(PROFILE_BINARY).unwrap();
+        let path = crate::ramp::helper::test_dir("plist").join("entitlements.plist");
+        profile.write_entitlements(&path).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().starts_with("<?xml"));
+        assert_eq!(read_plist(&path).unwrap(), parse_plist(ENTITLEMENTS_XML).unwrap());
+    }
+}
@@ -0,0 +1,338 @@
+use super::cms::{profile_warnings, read_mobileprovision};
+use super::plist::ProvisioningProfile;
+use super::session::Session;
+use chrono::{DateTime, Duration, Utc};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+//directory inside the project holding imported profiles as <UUID>.mobileprovision
+pub const PROFILES_DIR: &str = "profiles";
+
+//name ios and macos expect the profile to have inside an app bundle
+pub const EMBEDDED_PROFILE: &str = "embedded.mobileprovision";
+
+//profiles expiring within this many days are flagged on the ios page
+pub const EXPIRY_WARNING_DAYS: i64 = 14;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProfileKind {
+    Development,
+    AdHoc,
+    AppStore,
+    Enterprise,
+}
+
+impl ProfileKind {
+    //development profiles allow debugging (get-task-allow), even before any device is added to them
+    //ad-hoc and app store ones don't and only ad-hoc lists devices
+    pub fn classify(profile: &ProvisioningProfile) -> Self {
+        let debuggable = profile.entitlements.get("get-task-allow").and_then(|v| v.as_boolean()).unwrap_or(false);
+        if profile.provisions_all_devices {
+            ProfileKind::Enterprise
+        } else if debuggable {
+            ProfileKind::Development
+        } else if profile.provisioned_devices.is_empty() {
+            ProfileKind::AppStore
+        } else {
+            ProfileKind::AdHoc
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProfileKind::Development => "development",
+            ProfileKind::AdHoc => "ad-hoc",
+            ProfileKind::AppStore => "App Store",
+            ProfileKind::Enterprise => "enterprise",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredProfile {
+    pub path: PathBuf,
+    pub profile: ProvisioningProfile,
+}
+
+impl StoredProfile {
+    pub fn kind(&self) -> ProfileKind {
+        ProfileKind::classify(&self.profile)
+    }
+
+    pub fn expires(&self) -> Option<DateTime<Utc>> {
+        self.profile.expiration_date.map(DateTime::<Utc>::from)
+    }
+
+    //one line description for the ios page and the cli
+    pub fn summary(&self) -> String {
+        format!(
+            "{} ({}) app id {}, team {}, {} devices, expires {}",
+            self.profile.name,
+            self.kind().as_str(),
+            self.profile.application_identifier().unwrap_or("unknown"),
+            self.profile.team_identifiers.first().map(|t| t.as_str()).unwrap_or("unknown"),
+            self.profile.provisioned_devices.len(),
+            self.expires().map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_else(|| "never".to_string())
+        )
+    }
+}
+
+//expiry and device coverage problems with a profile, `udid` is the connected device if there is one
+pub fn profile_issues(stored: &StoredProfile, udid: Option<&str>, now: DateTime<Utc>) -> Vec<String> {
+    let mut issues = profile_warnings(&stored.profile, now, Duration::days(EXPIRY_WARNING_DAYS));
+    if let Some(udid) = udid {
+        let lists_devices = matches!(stored.kind(), ProfileKind::Development | ProfileKind::AdHoc);
+        if lists_devices && !stored.profile.contains_device(udid) {
+            issues.push(format!("provisioning profile {} does not include the connected device {}", stored.profile.name, udid));
+        }
+    }
+    issues
+}
+
+//imported provisioning profiles of one project, the active one is remembered in the ramp config
+#[derive(Debug, Clone)]
+pub struct ProfileStore {
+    pub dir: PathBuf,
+    project: String,
+}
+
+impl ProfileStore {
+    pub fn open(session: &Session) -> io::Result<Self> {
+        let project = session.current_project.as_ref().unwrap().to_string();
+        let dir = Path::new(session.projects_path.as_ref().unwrap()).join(&project).join(PROFILES_DIR);
+        fs::create_dir_all(&dir)?;
+        Ok(ProfileStore { dir, project })
+    }
+
+    fn active_key(&self) -> String {
+        format!("ios_profile.{}", self.project)
+    }
+
+    //copy a .mobileprovision into the store, re-importing a profile replaces it
+    pub fn import(&self, path: &Path) -> io::Result<StoredProfile> {
+        let profile = read_mobileprovision(path)?;
+        let destination = self.dir.join(format!("{}.mobileprovision", profile.uuid));
+        if path != destination {
+            fs::copy(path, &destination)?;
+        }
+        println!("imported provisioning profile {} ({})", &profile.name, &profile.uuid);
+        Ok(StoredProfile { path: destination, profile })
+    }
+
+    //every stored profile, latest expiry first
+    pub fn list(&self) -> io::Result<Vec<StoredProfile>> {
+        let mut profiles = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "mobileprovision") {
+                match read_mobileprovision(&path) {
+                    Ok(profile) => profiles.push(StoredProfile { path, profile }),
+                    Err(e) => println!("WARNING: skipping unreadable profile {}: {}", path.display(), e),
+                }
+            }
+        }
+        profiles.sort_by_key(|p| std::cmp::Reverse(p.profile.expiration_date));
+        Ok(profiles)
+    }
+
+    pub fn find(&self, uuid: &str) -> io::Result<StoredProfile> {
+        self.list()?
+            .into_iter()
+            .find(|p| p.profile.uuid.eq_ignore_ascii_case(uuid))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no provisioning profile {} for {}", uuid, self.project)))
+    }
+
+    pub fn delete(&self, session: &Session, uuid: &str) -> io::Result<()> {
+        let stored = self.find(uuid)?;
+        fs::remove_file(&stored.path)?;
+        if session.get_config(&self.active_key())?.is_some_and(|active| active.eq_ignore_ascii_case(uuid)) {
+            session.set_config(&self.active_key(), "")?;
+        }
+        Ok(())
+    }
+
+    pub fn select(&self, session: &Session, uuid: &str) -> io::Result<StoredProfile> {
+        let stored = self.find(uuid)?;
+        session.set_config(&self.active_key(), &stored.profile.uuid)?;
+        Ok(stored)
+    }
+
+    pub fn active(&self, session: &Session) -> io::Result<Option<StoredProfile>> {
+        match session.get_config(&self.active_key())? {
+            Some(uuid) if !uuid.is_empty() => self.find(&uuid).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    //the unexpired profile for the bundle id (and device when given) that expires last
+    pub fn best_match(&self, bundle_id: &str, udid: Option<&str>) -> io::Result<Option<StoredProfile>> {
+        let now = std::time::SystemTime::now();
+        Ok(self.list()?.into_iter().find(|p| {
+            !p.profile.is_expired(now)
+                && p.profile.matches_bundle_id(bundle_id)
+                && udid.is_none_or(|udid| p.profile.contains_device(udid))
+        }))
+    }
+}
+
+//make the profile the app bundle's embedded.mobileprovision, removing any other profiles in the bundle
+pub fn embed_profile(stored: &StoredProfile, app_bundle_path: &Path) -> io::Result<PathBuf> {
+    for entry in fs::read_dir(app_bundle_path)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e == "mobileprovision") {
+            fs::remove_file(&path)?;
+        }
+    }
+    let embedded = app_bundle_path.join(EMBEDDED_PROFILE);
+    fs::copy(&stored.path, &embedded)?;
+    Ok(embedded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ramp::helper::test_session;
+    use plist::Value;
+
+    const PROFILE_XML: &[u8] = include_bytes!("../../tests/fixtures/ios/profile.xml.plist");
+    //signed profiles for com.example.demo, see the names for what sets each apart
+    const DEVELOPMENT: &str = "tests/fixtures/ios/store/development.mobileprovision";
+    const NEW_PHONE: &str = "tests/fixtures/ios/store/new_phone.mobileprovision";
+    const EXPIRED: &str = "tests/fixtures/ios/store/expired.mobileprovision";
+    const APP_STORE: &str = "tests/fixtures/ios/store/app_store.mobileprovision";
+
+    const IPHONE: &str = "00008030-001A2B3C4D5E802E";
+    const NEW_IPHONE: &str = "00008120-0011223344556677";
+    const OLD_IPHONE: &str = "00008101-00AA11BB22CC33DD";
+
+    fn profile() -> ProvisioningProfile {
+        ProvisioningProfile::parse(PROFILE_XML).unwrap()
+    }
+
+    fn fixture(path: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join(path)
+    }
+
+    fn uuids(profiles: &[StoredProfile]) -> Vec<&str> {
+        profiles.iter().map(|p| p.profile.uuid.as_str()).collect()
+    }
+
+    #[test]
+    fn classifies_each_kind_of_profile() {
+        let development = profile();
+        assert_eq!(ProfileKind::classify(&development), ProfileKind::Development);
+
+        //a development profile created before any device was registered
+        let mut no_devices = development.clone();
+        no_devices.provisioned_devices.clear();
+        assert_eq!(ProfileKind::classify(&no_devices), ProfileKind::Development);
+
+        let mut ad_hoc = development.clone();
+        ad_hoc.entitlements.insert("get-task-allow".to_string(), Value::Boolean(false));
+        assert_eq!(ProfileKind::classify(&ad_hoc), ProfileKind::AdHoc);
+
+        let mut app_store = ad_hoc.clone();
+        app_store.entitlements.remove("get-task-allow");
+        app_store.provisioned_devices.clear();
+        assert_eq!(ProfileKind::classify(&app_store), ProfileKind::AppStore);
+
+        let mut enterprise = app_store.clone();
+        enterprise.provisions_all_devices = true;
+        assert_eq!(ProfileKind::classify(&enterprise), ProfileKind::Enterprise);
+    }
+
+    #[test]
+    fn flags_missing_devices_only_for_profiles_that_list_them() {
+        let stored = StoredProfile { path: PathBuf::from("demo.mobileprovision"), profile: profile() };
+        let now = "2026-10-19T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert!(profile_issues(&stored, Some(IPHONE), now).is_empty());
+        assert!(profile_issues(&stored, None, now).is_empty());
+        assert_eq!(
+            profile_issues(&stored, Some(NEW_IPHONE), now),
+            [format!("provisioning profile Demo Development does not include the connected device {}", NEW_IPHONE)]
+        );
+        let mut app_store = stored.clone();
+        app_store.profile.entitlements.remove("get-task-allow");
+        app_store.profile.provisioned_devices.clear();
+        assert!(profile_issues(&app_store, Some(NEW_IPHONE), now).is_empty());
+        //expiry comes first
+        let issues = profile_issues(&stored, Some(NEW_IPHONE), "2027-09-30T00:00:00Z".parse().unwrap());
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0], "provisioning profile Demo Development expires on 2027-10-01");
+    }
+
+    fn store(name: &str) -> (Session, ProfileStore) {
+        let mut session = test_session(name);
+        session.projects_path = Some(session.home.clone());
+        session.current_project = Some("demo".to_string());
+        let store = ProfileStore::open(&session).unwrap();
+        for path in [DEVELOPMENT, NEW_PHONE, EXPIRED, APP_STORE] {
+            store.import(&fixture(path)).unwrap();
+        }
+        (session, store)
+    }
+
+    #[test]
+    fn imports_and_lists_profiles_by_expiry() {
+        let (_session, store) = store("profiles-import");
+        let profiles = store.list().unwrap();
+        assert_eq!(
+            uuids(&profiles),
+            [
+                "bbbbbbbb-0000-4000-8000-000000000002",
+                "dddddddd-0000-4000-8000-000000000004",
+                "aaaaaaaa-0000-4000-8000-000000000001",
+                "cccccccc-0000-4000-8000-000000000003"
+            ]
+        );
+        assert_eq!(profiles[2].path, store.dir.join("aaaaaaaa-0000-4000-8000-000000000001.mobileprovision"));
+        assert_eq!(profiles.iter().map(|p| p.kind()).collect::<Vec<_>>(), [
+            ProfileKind::Development,
+            ProfileKind::AppStore,
+            ProfileKind::Development,
+            ProfileKind::Development
+        ]);
+        assert_eq!(
+            profiles[2].summary(),
+            "Demo Development (development) app id TEAMID1234.com.example.demo, team TEAMID1234, 2 devices, expires 2040-01-01"
+        );
+        //importing again replaces the stored copy, unreadable files are skipped
+        store.import(&fixture(DEVELOPMENT)).unwrap();
+        fs::write(store.dir.join("broken.mobileprovision"), b"not cms").unwrap();
+        assert_eq!(store.list().unwrap().len(), 4);
+        assert!(store.import(&store.dir.join("broken.mobileprovision")).is_err());
+    }
+
+    #[test]
+    fn selects_and_deletes_profiles() {
+        let (session, store) = store("profiles-select");
+        assert_eq!(store.active(&session).unwrap(), None);
+        let selected = store.select(&session, "AAAAAAAA-0000-4000-8000-000000000001").unwrap();
+        assert_eq!(store.active(&session).unwrap(), Some(selected));
+        assert_eq!(store.select(&session, "eeeeeeee-0000-4000-8000-000000000005").unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        //deleting another profile keeps the selection
+        store.delete(&session, "bbbbbbbb-0000-4000-8000-000000000002").unwrap();
+        assert_eq!(store.active(&session).unwrap().unwrap().profile.name, "Demo Development");
+        store.delete(&session, "aaaaaaaa-0000-4000-8000-000000000001").unwrap();
+        assert_eq!(store.active(&session).unwrap(), None);
+        assert_eq!(uuids(&store.list().unwrap()), ["dddddddd-0000-4000-8000-000000000004", "cccccccc-0000-4000-8000-000000000003"]);
+    }
+
+    #[test]
+    fn matches_the_unexpired_profile_for_the_device() {
+        let (_session, store) = store("profiles-match");
+        let best = |bundle_id: &str, udid: Option<&str>| store.best_match(bundle_id, udid).unwrap().map(|p| p.profile.name);
+        //the latest expiry wins when any device will do
+        assert_eq!(best("com.example.demo", None).as_deref(), Some("Demo Development New Phone"));
+        assert_eq!(best("com.example.demo", Some(NEW_IPHONE)).as_deref(), Some("Demo Development New Phone"));
+        //the app store profile lists no devices, so it never matches one
+        assert_eq!(best("com.example.demo", Some(IPHONE)).as_deref(), Some("Demo Development"));
+        //only the expired profile lists the old iphone
+        assert_eq!(best("com.example.demo", Some(OLD_IPHONE)), None);
+        //the wildcard app id covers other bundle ids of the team
+        assert_eq!(best("com.example.other", None).as_deref(), Some("Team App Store"));
+        assert_eq!(best("com.example.other", Some(IPHONE)), None);
+    }
+}
@@ -0,0 +1,276 @@
+use super::core::resize_png;
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+//icon sizes required for chrome's install prompt
+pub const PWA_ICON_SIZES: [u32; 2] = [192, 512];
+
+pub const DEFAULT_THEME_COLOR: &str = "#000000";
+
+#[derive(Debug, Clone)]
+pub struct PwaOptions {
+    pub name: String,
+    pub short_name: String,
+    pub theme_color: String,
+    pub background_color: String,
+}
+
+impl PwaOptions {
+    //theme_color comes from [package.metadata.ramp], the splash screen background follows it
+    pub fn for_project(project: &str, theme_color: Option<&str>) -> Self {
+        let theme_color = theme_color.filter(|c| !c.is_empty()).unwrap_or(DEFAULT_THEME_COLOR);
+        PwaOptions {
+            name: project.to_string(),
+            short_name: project.chars().take(12).collect(),
+            theme_color: theme_color.to_string(),
+            background_color: theme_color.to_string(),
+        }
+    }
+}
+
+//escape a value for embedding in a json string
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+//the web app manifest, icons are listed relative to dist
+pub fn manifest_json(options: &PwaOptions, favicon: bool) -> String {
+    let mut icons: Vec<String> = PWA_ICON_SIZES
+        .iter()
+        .map(|size| {
+            format!(
+                r#"    {{ "src": "icon-{0}.png", "sizes": "{0}x{0}", "type": "image/png", "purpose": "any maskable" }}"#,
+                size
+            )
+        })
+        .collect();
+    if favicon {
+        icons.push(r#"    { "src": "favicon.ico", "sizes": "64x64", "type": "image/x-icon" }"#.to_string());
+    }
+    format!(
+        r#"{{
+  "name": "{}",
+  "short_name": "{}",
+  "start_url": ".",
+  "scope": ".",
+  "display": "standalone",
+  "theme_color": "{}",
+  "background_color": "{}",
+  "icons": [
+{}
+  ]
+}}
+"#,
+        json_escape(&options.name),
+        json_escape(&options.short_name),
+        json_escape(&options.theme_color),
+        json_escape(&options.background_color),
+        icons.join(",\n")
+    )
+}
+
+//tags added to the index.html head of a pwa build
+pub fn pwa_head_tags(options: &PwaOptions) -> String {
+    format!(
+        "    <link rel=\"manifest\" href=\"manifest.webmanifest\">\n    <meta name=\"theme-color\" content=\"{}\">\n    <link rel=\"apple-touch-icon\" href=\"icon-{}.png\">\n",
+        options.theme_color, PWA_ICON_SIZES[0]
+    )
+}
+
+//snippet appended to loader.js to register the service worker
+pub fn register_service_worker_js() -> &'static str {
+    r#"
+if ("serviceWorker" in navigator) {
+    window.addEventListener("load", () => {
+        navigator.serviceWorker.register("./sw.js").catch((error) => console.error("service worker registration failed", error));
+    });
+}
+"#
+}
+
+//first 16 hex characters of the sha256 of a file's contents
+pub fn content_hash(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    digest.iter().take(8).fold(String::new(), |mut hex, b| {
+        let _ = write!(hex, "{:02x}", b);
+        hex
+    })
+}
+
+//hash every servable asset in dist, skipping precompressed copies and the worker itself
+pub fn hash_assets(dist_path: &str) -> io::Result<Vec<(String, String)>> {
+    let mut assets = Vec::new();
+    for entry in fs::read_dir(dist_path)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        if !path.is_file() || extension == "gz" || extension == "br" || name == "sw.js" {
+            continue;
+        }
+        assets.push((name, content_hash(&fs::read(&path)?)));
+    }
+    assets.sort();
+    Ok(assets)
+}
+
+//an offline-first service worker that precaches the hashed assets of this build
+pub fn service_worker_js(assets: &[(String, String)]) -> String {
+    let combined: String = assets.iter().map(|(name, hash)| format!("{}:{};", name, hash)).collect();
+    let cache_name = format!("ramp-{}", content_hash(combined.as_bytes()));
+    let entries: Vec<String> = assets
+        .iter()
+        .map(|(name, hash)| format!("    {{ url: \"{}\", revision: \"{}\" }}", json_escape(name), hash))
+        .collect();
+    format!(
+        r#"const CACHE_NAME = "{}";
+const ASSETS = [
+{}
+];
+
+self.addEventListener("install", (event) => {{
+    event.waitUntil(
+        caches.open(CACHE_NAME)
+            .then((cache) => cache.addAll(ASSETS.map((asset) => new Request(asset.url, {{ cache: "reload" }}))))
+            .then(() => self.skipWaiting())
+    );
+}});
+
+self.addEventListener("activate", (event) => {{
+    event.waitUntil(
+        caches.keys()
+            .then((keys) => Promise.all(keys.filter((key) => key !== CACHE_NAME).map((key) => caches.delete(key))))
+            .then(() => self.clients.claim())
+    );
+}});
+
+self.addEventListener("fetch", (event) => {{
+    if (event.request.method !== "GET") {{
+        return;
+    }}
+    event.respondWith(
+        caches.match(event.request, {{ ignoreSearch: true }}).then((cached) => {{
+            if (cached) {{
+                return cached;
+            }}
+            return fetch(event.request).catch(() => caches.match("index.html"));
+        }})
+    );
+}});
+"#,
+        cache_name,
+        entries.join(",\n")
+    )
+}
+
+//write the manifest and icons into dist, run before the service worker is generated
+//returns false without writing anything when the project has no icon to install the app with
+pub fn write_pwa_assets(project_path: &str, dist_path: &str, options: &PwaOptions) -> io::Result<bool> {
+    let icon = format!("{}/assets/resources/icons/icon.png", project_path);
+    if !Path::new(&icon).exists() {
+        println!("warning: no icon found at {}, building without the web app manifest and service worker", icon);
+        return Ok(false);
+    }
+    for size in PWA_ICON_SIZES {
+        resize_png(&icon, &format!("{}/icon-{}.png", dist_path, size), size, size)?;
+    }
+    let favicon = Path::new(dist_path).join("favicon.ico").exists();
+    fs::write(format!("{}/manifest.webmanifest", dist_path), manifest_json(options, favicon))?;
+    println!("wrote web app manifest to {}/manifest.webmanifest", dist_path);
+    Ok(true)
+}
+
+//generate sw.js from the final contents of dist, run after every other asset is written
+pub fn write_service_worker(dist_path: &str) -> io::Result<()> {
+    let assets = hash_assets(dist_path)?;
+    fs::write(format!("{}/sw.js", dist_path), service_worker_js(&assets))?;
+    println!("wrote service worker precaching {} assets", assets.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ramp::helper::test_dir;
+    use serde_json::Value;
+
+    #[test]
+    fn uses_the_configured_theme_color() {
+        let options = PwaOptions::for_project("a very long project name", Some("#1b6ef3"));
+        assert_eq!(options.short_name, "a very long ");
+        assert_eq!(options.theme_color, "#1b6ef3");
+        assert_eq!(options.background_color, "#1b6ef3");
+        assert_eq!(PwaOptions::for_project("demo", None).theme_color, DEFAULT_THEME_COLOR);
+        assert_eq!(PwaOptions::for_project("demo", Some("")).theme_color, DEFAULT_THEME_COLOR);
+    }
+
+    #[test]
+    fn writes_a_valid_web_app_manifest() {
+        let options = PwaOptions::for_project("the \"demo\"\\app", Some("#1b6ef3"));
+        let manifest: Value = serde_json::from_str(&manifest_json(&options, false)).unwrap();
+        assert_eq!(manifest["name"], "the \"demo\"\\app");
+        assert_eq!(manifest["display"], "standalone");
+        assert_eq!(manifest["theme_color"], "#1b6ef3");
+        let icons = manifest["icons"].as_array().unwrap();
+        assert_eq!(icons.len(), 2);
+        assert_eq!(icons[0]["src"], "icon-192.png");
+        assert_eq!(icons[1]["sizes"], "512x512");
+
+        let manifest: Value = serde_json::from_str(&manifest_json(&options, true)).unwrap();
+        assert_eq!(manifest["icons"][2]["src"], "favicon.ico");
+    }
+
+    #[test]
+    fn hashes_only_servable_assets() {
+        let dist = test_dir("pwa-hash");
+        fs::write(dist.join("main.js"), "abc").unwrap();
+        fs::write(dist.join("index.html"), "<html></html>").unwrap();
+        fs::write(dist.join("main_bg.wasm.br"), "compressed").unwrap();
+        fs::write(dist.join("main_bg.wasm.gz"), "compressed").unwrap();
+        fs::write(dist.join("sw.js"), "old worker").unwrap();
+        fs::create_dir(dist.join("assets")).unwrap();
+
+        let assets = hash_assets(&dist.display().to_string()).unwrap();
+        //sha256("abc") starts with ba7816bf8f01cfea
+        assert_eq!(assets, [
+            ("index.html".to_string(), content_hash(b"<html></html>")),
+            ("main.js".to_string(), "ba7816bf8f01cfea".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn names_the_worker_cache_after_the_asset_hashes() {
+        let assets = [("index.html".to_string(), "1111".to_string()), ("main.js".to_string(), "2222".to_string())];
+        let worker = service_worker_js(&assets);
+        assert!(worker.contains("    { url: \"index.html\", revision: \"1111\" },\n    { url: \"main.js\", revision: \"2222\" }\n];"));
+        let cache = |worker: &str| worker.lines().next().unwrap().to_string();
+        assert_eq!(cache(&worker), format!("const CACHE_NAME = \"ramp-{}\";", content_hash(b"index.html:1111;main.js:2222;")));
+        //a changed asset invalidates the old cache
+        let changed = service_worker_js(&[("index.html".to_string(), "1111".to_string()), ("main.js".to_string(), "3333".to_string())]);
+        assert_ne!(cache(&worker), cache(&changed));
+    }
+
+    #[test]
+    fn skips_the_pwa_without_an_icon() {
+        let project = test_dir("pwa-icon");
+        let dist = project.join("dist");
+        fs::create_dir(&dist).unwrap();
+        let written = write_pwa_assets(&project.display().to_string(), &dist.display().to_string(), &PwaOptions::for_project("demo", None)).unwrap();
+        assert!(!written);
+        assert_eq!(fs::read_dir(&dist).unwrap().count(), 0);
+    }
+}
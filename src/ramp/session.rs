@@ -23,11 +23,125 @@ pub struct Paths {
     pub ndk_bundle_path: Option<String>,
     pub java_path: Option<String>,
     pub keystore_path: Option<String>,
+    pub wasm_bindgen_path: Option<String>,
+    pub wasm_opt_path: Option<String>,
+    pub bundletool_path: Option<String>,
 }
 
-#[derive(Default, Debug)]
+//the apple certificates ramp can create, each kept as its own identity in the login keychain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SigningIdentity {
+    //debug builds on registered devices
+    Development,
+    //ad-hoc and app store builds
+    Distribution,
+    //macos apps distributed outside the app store
+    DeveloperId,
+}
+
+impl SigningIdentity {
+    pub fn parse(identity: &str) -> Option<Self> {
+        match identity {
+            "development" => Some(SigningIdentity::Development),
+            "distribution" => Some(SigningIdentity::Distribution),
+            "developer_id" => Some(SigningIdentity::DeveloperId),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SigningIdentity::Development => "development",
+            SigningIdentity::Distribution => "distribution",
+            SigningIdentity::DeveloperId => "developer_id",
+        }
+    }
+
+    //the start of the certificate's common name, which codesign matches identities by
+    pub fn common_name(&self) -> &'static str {
+        match self {
+            SigningIdentity::Development => "Apple Development",
+            SigningIdentity::Distribution => "Apple Distribution",
+            SigningIdentity::DeveloperId => "Developer ID Application",
+        }
+    }
+
+    //the certificateType App Store Connect creates it as
+    pub fn certificate_type(&self) -> &'static str {
+        match self {
+            SigningIdentity::Development => "DEVELOPMENT",
+            SigningIdentity::Distribution => "DISTRIBUTION",
+            SigningIdentity::DeveloperId => "DEVELOPER_ID_APPLICATION",
+        }
+    }
+
+    //the name the developer portal downloads the certificate as
+    pub fn certificate_file(&self) -> &'static str {
+        match self {
+            SigningIdentity::Development => "development.cer",
+            SigningIdentity::Distribution => "distribution.cer",
+            SigningIdentity::DeveloperId => "developerID_application.cer",
+        }
+    }
+
+    //debug builds use the development identity, ios releases the distribution one and macos releases Developer ID
+    pub fn for_build(target_os: &str, release: bool) -> io::Result<Self> {
+        match (target_os, release) {
+            ("ios" | "macos", false) => Ok(SigningIdentity::Development),
+            ("ios", true) => Ok(SigningIdentity::Distribution),
+            ("macos", true) => Ok(SigningIdentity::DeveloperId),
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} builds are not signed with apple certificates", target_os))),
+        }
+    }
+}
+
+//the codesign identity each apple platform and build type is signed with
+#[derive(Debug)]
 pub struct Certs {
-    pub macos: String
+    pub ios_debug: String,
+    pub ios_release: String,
+    pub macos_debug: String,
+    pub macos_release: String,
+}
+
+//the identity SigningIdentity::for_build picks, until a config override replaces it
+impl Default for Certs {
+    fn default() -> Self {
+        Certs {
+            ios_debug: SigningIdentity::Development.common_name().to_string(),
+            ios_release: SigningIdentity::Distribution.common_name().to_string(),
+            macos_debug: SigningIdentity::Development.common_name().to_string(),
+            macos_release: SigningIdentity::DeveloperId.common_name().to_string(),
+        }
+    }
+}
+
+impl Certs {
+    //the ramp config key overriding an identity, for example with "Apple Distribution: Name (TEAMID)" when several are installed
+    pub fn config_key(target_os: &str, release: bool) -> io::Result<String> {
+        SigningIdentity::for_build(target_os, release)?;
+        Ok(format!("{}_{}_cert", target_os, if release { "release" } else { "debug" }))
+    }
+
+    pub fn identity(&self, target_os: &str, release: bool) -> io::Result<&str> {
+        match (target_os, release) {
+            ("ios", false) => Ok(&self.ios_debug),
+            ("ios", true) => Ok(&self.ios_release),
+            ("macos", false) => Ok(&self.macos_debug),
+            ("macos", true) => Ok(&self.macos_release),
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} builds are not signed with apple certificates", target_os))),
+        }
+    }
+
+    fn identity_mut(&mut self, target_os: &str, release: bool) -> io::Result<&mut String> {
+        match (target_os, release) {
+            ("ios", false) => Ok(&mut self.ios_debug),
+            ("ios", true) => Ok(&mut self.ios_release),
+            ("macos", false) => Ok(&mut self.macos_debug),
+            ("macos", true) => Ok(&mut self.macos_release),
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} builds are not signed with apple certificates", target_os))),
+        }
+    }
 }
 
 #[derive(Default, Debug)]
@@ -72,10 +186,11 @@ impl Session {
             ndk_bundle_path: None,
             java_path: None,
             keystore_path: None,
+            wasm_bindgen_path: None,
+            wasm_opt_path: None,
+            bundletool_path: None,
         };
-        let certs = Certs{
-            macos: "Apple Development".to_string(),
-        };
+        let certs = Certs::default();
         Ok(Session {
             os,
             home,
@@ -133,9 +248,55 @@ impl Session {
             "ndk_bundle_path" => self.paths.ndk_bundle_path = Some(file_path.clone()),
             "java_path" => self.paths.java_path = Some(file_path.clone()),
             "keystore_path" => self.paths.keystore_path = Some(file_path.clone()),
+            "wasm_bindgen_path" => self.paths.wasm_bindgen_path = Some(file_path.clone()),
+            "wasm_opt_path" => self.paths.wasm_opt_path = Some(file_path.clone()),
+            "bundletool_path" => self.paths.bundletool_path = Some(file_path.clone()),
             _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "Unknown path name")),
         }
 
+        self.write_config_value(path_name, &file_path)?;
+
+        println!("Successfully updated path");
+
+        Ok(())
+    }
+
+    // Change the identity a platform and build type is signed with, empty restores the default
+    pub fn set_cert(&mut self, target_os: &str, release: bool, identity: &str) -> io::Result<()> {
+        let key = Certs::config_key(target_os, release)?;
+        let value = match identity.trim() {
+            "" => SigningIdentity::for_build(target_os, release)?.common_name(),
+            identity => identity,
+        };
+        *self.certs.identity_mut(target_os, release)? = value.to_string();
+        self.write_config_value(&key, identity.trim())
+    }
+
+    // Store a ramp setting that isn't a tool path in the .ramp config
+    pub fn set_config(&self, key: &str, value: &str) -> io::Result<()> {
+        self.write_config_value(key, value)
+    }
+
+    // Read a raw value from the .ramp config, None if the key is not set
+    pub fn get_config(&self, key: &str) -> io::Result<Option<String>> {
+        let config_path = format!("{}/.ramp", self.home);
+        if !Path::new(&config_path).exists() {
+            return Ok(None);
+        }
+        let reader = BufReader::new(File::open(&config_path)?);
+        for line in reader.lines() {
+            let line = line?;
+            if let Some((k, value)) = line.split_once('=') {
+                if k.trim() == key {
+                    return Ok(Some(value.trim().to_string()));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // Replace or append a key=value line in the .ramp config
+    fn write_config_value(&self, key: &str, value: &str) -> io::Result<()> {
         // Read existing config file
         let config_path = format!("{}/.ramp", self.home);
         let mut config_lines = Vec::new();
@@ -146,8 +307,8 @@ impl Session {
             let reader = BufReader::new(file);
             for line in reader.lines() {
                 let line = line?;
-                if line.starts_with(&format!("{}=", path_name)) {
-                    config_lines.push(format!("{}={}", path_name, file_path));
+                if line.starts_with(&format!("{}=", key)) {
+                    config_lines.push(format!("{}={}", key, value));
                     found = true;
                 } else {
                     config_lines.push(line);
@@ -155,22 +316,20 @@ impl Session {
             }
         }
 
-        // If the path wasn't found, append it
+        // If the key wasn't found, append it
         if !found {
-            config_lines.push(format!("{}={}", path_name, file_path));
+            config_lines.push(format!("{}={}", key, value));
         }
 
         // Write updated config back to file
         let mut file = OpenOptions::new()
             .write(true)
+            .create(true)
             .truncate(true)
             .open(&config_path)?;
         for line in config_lines {
             writeln!(file, "{}", line)?;
         }
-
-        println!("Successfully updated path");
-
         Ok(())
     }
 
@@ -204,6 +363,14 @@ impl Session {
                     "ndk_bundle_path" => self.paths.ndk_bundle_path = Some(value.trim().to_string()),
                     "java_path" => self.paths.java_path = Some(value.trim().to_string()),
                     "keystore_path" => self.paths.keystore_path = Some(value.trim().to_string()),
+                    "wasm_bindgen_path" => self.paths.wasm_bindgen_path = Some(value.trim().to_string()),
+                    "wasm_opt_path" => self.paths.wasm_opt_path = Some(value.trim().to_string()),
+                    "bundletool_path" => self.paths.bundletool_path = Some(value.trim().to_string()),
+                    //signing identity overrides, see Certs::config_key
+                    "ios_debug_cert" | "ios_release_cert" | "macos_debug_cert" | "macos_release_cert" if !value.trim().is_empty() => {
+                        let (target_os, build) = key.trim().trim_end_matches("_cert").split_once('_').unwrap_or_default();
+                        *self.certs.identity_mut(target_os, build == "release")? = value.trim().to_string();
+                    }
                     _ => (), // Ignore unknown keys
                 }
             }
@@ -270,13 +437,89 @@ impl Session {
                 .as_ref()
                 .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "java_path not set"))?
                 .to_string()),
-            "keystore_path" => Ok(self.paths.java_path
+            "keystore_path" => Ok(self.paths.keystore_path
                 .as_ref()
                 .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "keystore_path not set"))?
                 .to_string()),
+            "wasm_bindgen_path" => Ok(self.paths.wasm_bindgen_path
+                .as_ref()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "wasm_bindgen_path not set"))?
+                .to_string()),
+            "wasm_opt_path" => Ok(self.paths.wasm_opt_path
+                .as_ref()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "wasm_opt_path not set"))?
+                .to_string()),
+            "bundletool_path" => Ok(self.paths.bundletool_path
+                .as_ref()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "bundletool_path not set"))?
+                .to_string()),
             _ => Err(io::Error::new(io::ErrorKind::NotFound, format!("Unknown Key: {}", key)))
         }
 
     }
 
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ramp::helper::test_session;
+
+    const BUILDS: [(&str, bool, SigningIdentity); 4] = [
+        ("ios", false, SigningIdentity::Development),
+        ("ios", true, SigningIdentity::Distribution),
+        ("macos", false, SigningIdentity::Development),
+        ("macos", true, SigningIdentity::DeveloperId),
+    ];
+
+    #[test]
+    fn picks_the_identity_per_platform_and_build() {
+        let certs = Certs::default();
+        for (target_os, release, identity) in BUILDS {
+            assert_eq!(SigningIdentity::for_build(target_os, release).unwrap(), identity);
+            assert_eq!(certs.identity(target_os, release).unwrap(), identity.common_name());
+            assert_eq!(SigningIdentity::parse(identity.as_str()), Some(identity));
+        }
+        assert_eq!(Certs::config_key("ios", true).unwrap(), "ios_release_cert");
+        assert_eq!(Certs::config_key("macos", false).unwrap(), "macos_debug_cert");
+        for release in [false, true] {
+            assert_eq!(SigningIdentity::for_build("android", release).unwrap_err().kind(), io::ErrorKind::Unsupported);
+            assert!(certs.identity("linux", release).is_err());
+            assert!(Certs::config_key("windows", release).is_err());
+        }
+    }
+
+    #[test]
+    fn set_cert_overrides_one_identity() {
+        let mut session = test_session("session-set-cert");
+        let team = "Apple Distribution: Example Ltd (TEAMID1234)";
+        session.set_cert("ios", true, &format!(" {} ", team)).unwrap();
+        assert_eq!(session.certs.identity("ios", true).unwrap(), team);
+        assert_eq!(session.get_config("ios_release_cert").unwrap().as_deref(), Some(team));
+        //the other builds keep their defaults
+        assert_eq!(session.certs.identity("ios", false).unwrap(), "Apple Development");
+        assert_eq!(session.certs.identity("macos", true).unwrap(), "Developer ID Application");
+
+        //clearing it restores the default and leaves the key empty
+        session.set_cert("ios", true, "").unwrap();
+        assert_eq!(session.certs.identity("ios", true).unwrap(), "Apple Distribution");
+        assert_eq!(session.get_config("ios_release_cert").unwrap().as_deref(), Some(""));
+        assert!(session.set_cert("android", true, team).is_err());
+    }
+
+    #[test]
+    fn loads_identity_overrides_from_the_config() {
+        let session = test_session("session-cert-config");
+        let developer_id = "Developer ID Application: Example Ltd (TEAMID1234)";
+        session.set_config("sdk_path", "/opt/android").unwrap();
+        session.set_config("macos_release_cert", developer_id).unwrap();
+        session.set_config("ios_debug_cert", "").unwrap();
+
+        let mut loaded = Session { home: session.home.clone(), ..Default::default() };
+        loaded.get_all_paths().unwrap();
+        assert_eq!(loaded.certs.identity("macos", true).unwrap(), developer_id);
+        //an empty override keeps the default
+        assert_eq!(loaded.certs.identity("ios", false).unwrap(), "Apple Development");
+        assert_eq!(loaded.certs.identity("ios", true).unwrap(), "Apple Distribution");
+        assert_eq!(loaded.paths.sdk_path.as_deref(), Some("/opt/android"));
+    }
+}
@@ -0,0 +1,230 @@
+use super::session::Session;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant, SystemTime};
+
+//ramp config keys for where the browser saves downloads and how long ramp waits for one
+pub const DOWNLOADS_PATH_KEY: &str = "downloads_path";
+pub const DOWNLOAD_TIMEOUT_KEY: &str = "download_timeout_secs";
+
+pub const DEFAULT_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+//a file modified more recently than this may still be being written
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+//in progress downloads of safari, chrome and firefox
+const PARTIAL_EXTENSIONS: [&str; 4] = ["download", "crdownload", "part", "partial"];
+
+//which file names count as the artifact
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArtifactPattern {
+    //an exact name, also matching the "name (1).ext" copies browsers save when the name is taken
+    Named(String),
+    //any file with the extension
+    Extension(String),
+}
+
+impl ArtifactPattern {
+    //"*.ext" patterns match by extension, anything else by name
+    pub fn parse(pattern: &str) -> Self {
+        match pattern.strip_prefix("*.") {
+            Some(extension) => ArtifactPattern::Extension(extension.to_string()),
+            None => ArtifactPattern::Named(pattern.to_string()),
+        }
+    }
+
+    pub fn matches(&self, file_name: &str) -> bool {
+        match self {
+            ArtifactPattern::Extension(extension) => {
+                file_name.rsplit_once('.').is_some_and(|(stem, ext)| !stem.is_empty() && ext.eq_ignore_ascii_case(extension))
+            }
+            ArtifactPattern::Named(name) => {
+                if file_name == name {
+                    return true;
+                }
+                let (stem, ext) = name.rsplit_once('.').unwrap_or((name.as_str(), ""));
+                let Some(rest) = file_name.strip_prefix(stem).and_then(|r| r.strip_suffix(ext)) else { return false };
+                let copy = rest.strip_suffix('.').unwrap_or(rest);
+                copy.strip_prefix(" (")
+                    .and_then(|c| c.strip_suffix(')'))
+                    .or_else(|| copy.strip_prefix('-'))
+                    .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ArtifactPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArtifactPattern::Named(name) => write!(f, "{}", name),
+            ArtifactPattern::Extension(extension) => write!(f, "a .{} file", extension),
+        }
+    }
+}
+
+//stops a wait from another thread, clones share the same flag
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+//waits for a file the user downloads from a web portal, such as a provisioning profile or certificate
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArtifactWatcher {
+    pub dir: PathBuf,
+    pub pattern: ArtifactPattern,
+    pub timeout: Duration,
+    pub poll_interval: Duration,
+    //ignore matches older than this, None accepts files already in the directory
+    pub newer_than: Option<SystemTime>,
+}
+
+impl ArtifactWatcher {
+    pub fn new(dir: PathBuf, pattern: ArtifactPattern) -> Self {
+        ArtifactWatcher {
+            dir,
+            pattern,
+            timeout: DEFAULT_DOWNLOAD_TIMEOUT,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            newer_than: None,
+        }
+    }
+
+    //watch the browser's download directory, ~/Downloads unless downloads_path is set in the ramp config
+    pub fn downloads(session: &Session, pattern: ArtifactPattern) -> io::Result<Self> {
+        let dir = match session.get_config(DOWNLOADS_PATH_KEY)?.filter(|d| !d.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None => Path::new(&session.home).join("Downloads"),
+        };
+        let timeout = match session.get_config(DOWNLOAD_TIMEOUT_KEY)?.filter(|t| !t.is_empty()) {
+            Some(seconds) => Duration::from_secs(seconds.parse().map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("invalid {}: {}", DOWNLOAD_TIMEOUT_KEY, seconds))
+            })?),
+            None => DEFAULT_DOWNLOAD_TIMEOUT,
+        };
+        Ok(ArtifactWatcher { timeout, ..ArtifactWatcher::new(dir, pattern) })
+    }
+
+    pub fn newer_than(mut self, since: SystemTime) -> Self {
+        self.newer_than = Some(since);
+        self
+    }
+
+    //true while a browser is still writing the file: recently modified, empty, or with an in progress sibling
+    fn is_partial(&self, path: &Path, size: u64, modified: SystemTime) -> bool {
+        let recent = modified.elapsed().map(|age| age < SETTLE_TIME).unwrap_or(true);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let in_progress = PARTIAL_EXTENSIONS.iter().any(|ext| self.dir.join(format!("{}.{}", name, ext)).exists());
+        size == 0 || recent || in_progress
+    }
+
+    //the newest complete match in the directory, if there is one yet
+    pub fn scan(&self) -> io::Result<Option<PathBuf>> {
+        if !self.dir.exists() {
+            return Ok(None);
+        }
+        let mut newest: Option<(SystemTime, PathBuf)> = None;
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !self.pattern.matches(&name) {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            let modified = metadata.modified()?;
+            if !metadata.is_file() || self.newer_than.is_some_and(|since| modified < since) {
+                continue;
+            }
+            if self.is_partial(&entry.path(), metadata.len(), modified) {
+                continue;
+            }
+            if newest.as_ref().is_none_or(|(time, _)| modified > *time) {
+                newest = Some((modified, entry.path()));
+            }
+        }
+        Ok(newest.map(|(_, path)| path))
+    }
+
+    //block until a match shows up, failing with TimedOut after the timeout or Interrupted when cancelled
+    pub fn wait(&self, cancel: &CancelToken) -> io::Result<PathBuf> {
+        let start_time = Instant::now();
+        loop {
+            if cancel.is_cancelled() {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, format!("stopped waiting for {}", self.pattern)));
+            }
+            if let Some(path) = self.scan()? {
+                return Ok(path);
+            }
+            if start_time.elapsed() >= self.timeout {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("no {} found in {} within {} seconds", self.pattern, self.dir.display(), self.timeout.as_secs()),
+                ));
+            }
+            sleep(self.poll_interval);
+        }
+    }
+
+    //wait on a background thread, the result arrives on the receiver
+    pub fn spawn(self, cancel: CancelToken) -> Receiver<io::Result<PathBuf>> {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(self.wait(&cancel));
+        });
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ramp::helper::test_dir;
+    use std::fs::File;
+
+    //a complete download modified `age` ago
+    fn download(dir: &Path, name: &str, age: Duration) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, b"profile").unwrap();
+        File::options().write(true).open(&path).unwrap().set_modified(SystemTime::now() - age).unwrap();
+        path
+    }
+
+    #[test]
+    fn ignores_downloads_from_before_the_step() {
+        let dir = test_dir("watcher");
+        let stale = download(&dir, "old.mobileprovision", Duration::from_secs(3600));
+        let watcher = ArtifactWatcher::new(dir.clone(), ArtifactPattern::parse("*.mobileprovision"));
+        assert_eq!(watcher.scan().unwrap(), Some(stale));
+
+        let watcher = watcher.newer_than(SystemTime::now() - Duration::from_secs(60));
+        assert_eq!(watcher.scan().unwrap(), None);
+        let fresh = download(&dir, "new.mobileprovision", Duration::from_secs(10));
+        assert_eq!(watcher.scan().unwrap(), Some(fresh));
+    }
+
+    #[test]
+    fn skips_downloads_still_being_written() {
+        let dir = test_dir("watcher");
+        let watcher = ArtifactWatcher::new(dir.clone(), ArtifactPattern::parse("development.cer"));
+        download(&dir, "development (1).cer", Duration::from_secs(10));
+        fs::write(dir.join("development (1).cer.crdownload"), b"").unwrap();
+        assert_eq!(watcher.scan().unwrap(), None);
+        fs::remove_file(dir.join("development (1).cer.crdownload")).unwrap();
+        assert_eq!(watcher.scan().unwrap(), Some(dir.join("development (1).cer")));
+    }
+}
@@ -0,0 +1,513 @@
+use super::bundle::{bundle_report, optimize_dist, persist_report, OptLevel, WasmOptimization};
+use super::pwa::{pwa_head_tags, register_service_worker_js, write_pwa_assets, write_service_worker, PwaOptions};
+use super::manifest::ProjectManifest;
+use super::session::Session;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+//name of the wasm artifact produced by the template's [lib] section
+const WASM_LIB_NAME: &str = "main";
+
+//route polled by the dev loader to detect a finished rebuild
+const BUILD_ID_ROUTE: &str = "/__ramp/build";
+
+//run wasm-bindgen on the wasm build artifact and assemble a servable dist/ directory
+pub fn wasm_post_build(session: &mut Session, release: bool) -> io::Result<String> {
+    let project_path = format!(
+        "{}/{}",
+        session.projects_path.as_ref().unwrap(),
+        session.current_project.as_ref().unwrap()
+    );
+    let wasm_path = format!(
+        "{}/target/wasm32-unknown-unknown/{}/{}.wasm",
+        &project_path,
+        if release { "release" } else { "debug" },
+        WASM_LIB_NAME
+    );
+    if !Path::new(&wasm_path).exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("wasm artifact not found: {}", wasm_path),
+        ));
+    }
+    //assemble the build in an empty staging directory so stale assets are never served,
+    //dist/ is only replaced once everything succeeded and keeps serving the previous build otherwise
+    let staging_path = format!("{}/dist.staging", &project_path);
+    let result = assemble_dist(session, &project_path, &wasm_path, &staging_path, release)
+        .and_then(|()| swap_dist(&staging_path, &format!("{}/dist", &project_path)));
+    if result.is_err() && Path::new(&staging_path).exists() {
+        let _ = fs::remove_dir_all(&staging_path);
+    }
+    let dist_path = result?;
+    println!("wasm web build written to {}", &dist_path);
+    Ok(dist_path)
+}
+
+//replace dist with the finished staging directory, renames keep the swap short for the dev server
+fn swap_dist(staging_path: &str, dist_path: &str) -> io::Result<String> {
+    let previous_path = format!("{}.previous", dist_path);
+    if Path::new(&previous_path).exists() {
+        fs::remove_dir_all(&previous_path)?;
+    }
+    if Path::new(dist_path).exists() {
+        fs::rename(dist_path, &previous_path)
+            .map_err(|e| io::Error::other(format!("Failed to replace {}: {}", dist_path, e)))?;
+    }
+    fs::rename(staging_path, dist_path)
+        .map_err(|e| io::Error::other(format!("Failed to move {} to {}: {}", staging_path, dist_path, e)))?;
+    if Path::new(&previous_path).exists() {
+        fs::remove_dir_all(&previous_path)?;
+    }
+    Ok(dist_path.to_string())
+}
+
+//run wasm-bindgen, write the html shell and assets and optimise into dist_path
+fn assemble_dist(session: &mut Session, project_path: &str, wasm_path: &str, dist_path: &str, release: bool) -> io::Result<()> {
+    if Path::new(dist_path).exists() {
+        fs::remove_dir_all(dist_path).map_err(|e| {
+            io::Error::other(format!("Failed to clear {}: {}", dist_path, e))
+        })?;
+    }
+    fs::create_dir_all(dist_path)?;
+
+    //warn if the installed cli does not match the version the project links against
+    let wasm_bindgen = session.get_path("wasm_bindgen_path")?;
+    if let Some(expected) = locked_wasm_bindgen_version(project_path) {
+        match wasm_bindgen_cli_version(&wasm_bindgen) {
+            Some(installed) if installed != expected => println!(
+                "WARNING: wasm-bindgen-cli {} does not match the project's wasm-bindgen {}. Run `cargo install wasm-bindgen-cli --version {}`",
+                installed, expected, expected
+            ),
+            None => println!("WARNING: could not determine the installed wasm-bindgen-cli version"),
+            _ => {}
+        }
+    }
+
+    println!("running wasm-bindgen on {}", wasm_path);
+    let mut args = vec![
+        "--target", "web",
+        "--no-typescript",
+        "--out-name", WASM_LIB_NAME,
+        "--out-dir", dist_path,
+    ];
+    if !release {
+        args.push("--debug");
+        args.push("--keep-debug");
+    }
+    args.push(wasm_path);
+    let output = Command::new(&wasm_bindgen)
+        .args(&args)
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| io::Error::other(format!("Failed to run wasm-bindgen: {}", e)))?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "wasm-bindgen failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    //copy the favicon generated by convert_png_to_ico
+    let favicon = format!("{}/assets/resources/icons/favicon.ico", &project_path);
+    let has_favicon = Path::new(&favicon).exists();
+    if has_favicon {
+        fs::copy(&favicon, format!("{}/favicon.ico", &dist_path))?;
+    } else {
+        println!("no favicon found at {}, run update_icons to generate one", &favicon);
+    }
+
+    let title = session.current_project.as_ref().unwrap().to_string();
+    //release builds are packaged as an installable pwa, debug builds skip the worker so live reload is never cached
+    let pwa = if release {
+        let options = PwaOptions::for_project(&title, ProjectManifest::load(project_path)?.theme_color.as_deref());
+        write_pwa_assets(project_path, dist_path, &options)?.then_some(options)
+    } else {
+        None
+    };
+    let head_tags = pwa.as_ref().map(pwa_head_tags).unwrap_or_default();
+    fs::write(format!("{}/loader.js", &dist_path), loader_js(!release, pwa.is_some()))?;
+    fs::write(format!("{}/index.html", &dist_path), index_html(&title, has_favicon, &head_tags))?;
+
+    //optional optimisation stage and a size report persisted alongside earlier builds
+    let options = WasmOptimization::for_build(release, OptLevel::from_config(session)?);
+    let module = optimize_dist(session, dist_path, &options)?;
+    let report = bundle_report(dist_path, release, &module)?;
+    println!("{}", report.to_text());
+    //only release builds go into the size history, debug builds come from every dev server rebuild
+    if release {
+        persist_report(project_path, &report)?;
+    }
+    if pwa.is_some() {
+        write_service_worker(dist_path)?;
+    }
+    Ok(())
+}
+
+//the module that boots the wasm-bindgen output, polling for rebuilds when served by ramp
+pub fn loader_js(live_reload: bool, service_worker: bool) -> String {
+    let mut js = String::from(
+        r#"import init from "./main.js";
+
+init().catch((error) => {
+    console.error(error);
+    const status = document.getElementById("ramp-status");
+    if (status) {
+        status.textContent = "Failed to load: " + error;
+    }
+});
+"#,
+    );
+    if live_reload {
+        js.push_str(&format!(
+            r#"
+let currentBuild = null;
+setInterval(async () => {{
+    try {{
+        const response = await fetch("{}", {{ cache: "no-store" }});
+        if (!response.ok) {{
+            return;
+        }}
+        const build = await response.text();
+        if (currentBuild !== null && build !== currentBuild) {{
+            location.reload();
+        }}
+        currentBuild = build;
+    }} catch (_) {{}}
+}}, 1000);
+"#,
+            BUILD_ID_ROUTE
+        ));
+    }
+    if service_worker {
+        js.push_str(register_service_worker_js());
+    }
+    js
+}
+
+//the html shell that hosts the wasm canvas
+pub fn index_html(title: &str, favicon: bool, head_tags: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title>{}</title>
+{}{}    <style>
+        html, body {{ margin: 0; padding: 0; width: 100%; height: 100%; overflow: hidden; background: #000; }}
+        canvas {{ display: block; width: 100%; height: 100%; }}
+        #ramp-status {{ position: absolute; top: 8px; left: 8px; color: #fff; font-family: sans-serif; }}
+    </style>
+</head>
+<body>
+    <div id="ramp-status"></div>
+    <script type="module" src="loader.js"></script>
+</body>
+</html>
+"#,
+        title,
+        if favicon { "    <link rel=\"icon\" href=\"favicon.ico\">\n" } else { "" },
+        head_tags
+    )
+}
+
+//read the wasm-bindgen version pinned in the project's Cargo.lock
+pub fn locked_wasm_bindgen_version(project_path: &str) -> Option<String> {
+    let lock = fs::read_to_string(format!("{}/Cargo.lock", project_path)).ok()?;
+    let mut in_package = false;
+    for line in lock.lines() {
+        let trim = line.trim();
+        if trim == "[[package]]" {
+            in_package = false;
+        } else if trim == "name = \"wasm-bindgen\"" {
+            in_package = true;
+        } else if in_package && trim.starts_with("version =") {
+            return Some(trim["version =".len()..].trim().trim_matches('"').to_string());
+        }
+    }
+    None
+}
+
+//parse `wasm-bindgen --version` output, eg "wasm-bindgen 0.2.100"
+fn wasm_bindgen_cli_version(wasm_bindgen: &str) -> Option<String> {
+    let output = Command::new(wasm_bindgen).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .nth(1)
+        .map(|v| v.to_string())
+}
+
+pub fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).unwrap_or("") {
+        "html" => "text/html; charset=utf-8",
+        "js" => "text/javascript; charset=utf-8",
+        "wasm" => "application/wasm",
+        "css" => "text/css; charset=utf-8",
+        "json" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "ico" => "image/x-icon",
+        "png" => "image/png",
+        "svg" => "image/svg+xml",
+        _ => "application/octet-stream",
+    }
+}
+
+//decode %xx escapes in a url path segment
+fn percent_decode(segment: &str) -> Option<String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let digit = |b: &u8| (*b as char).to_digit(16);
+            decoded.push((digit(bytes.get(i + 1)?)? * 16 + digit(bytes.get(i + 2)?)?) as u8);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+//map a request path onto a file inside dist, refusing anything that escapes it
+pub fn resolve_request(dist_path: &Path, request_path: &str) -> Option<PathBuf> {
+    let path = request_path.split(['?', '#']).next().unwrap_or("/");
+    let mut resolved = dist_path.to_path_buf();
+    for segment in path.split('/') {
+        let segment = percent_decode(segment)?;
+        match segment.as_str() {
+            "" | "." => {}
+            //a decoded segment must stay a single file name, this also catches `..` and windows separators
+            s => {
+                let mut components = Path::new(s).components();
+                match (components.next(), components.next()) {
+                    (Some(Component::Normal(name)), None) if !s.contains('/') => resolved.push(name),
+                    _ => return None,
+                }
+            }
+        }
+    }
+    if resolved.is_dir() {
+        resolved.push("index.html");
+    }
+    if resolved.is_file() {
+        Some(resolved)
+    } else {
+        None
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+fn handle_connection(mut stream: TcpStream, dist_path: &Path, build_id: &AtomicU64) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    //drain the request headers
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("/");
+    if method != "GET" && method != "HEAD" {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", b"method not allowed");
+    }
+    if target == BUILD_ID_ROUTE {
+        let id = build_id.load(Ordering::SeqCst).to_string();
+        return respond(&mut stream, "200 OK", "text/plain", id.as_bytes());
+    }
+    match resolve_request(dist_path, target) {
+        Some(file) => {
+            let mut body = Vec::new();
+            fs::File::open(&file)?.read_to_end(&mut body)?;
+            if method == "HEAD" {
+                body.clear();
+            }
+            respond(&mut stream, "200 OK", content_type(&file), &body)
+        }
+        None => respond(&mut stream, "404 Not Found", "text/plain", b"not found"),
+    }
+}
+
+//serve a dist directory over http on localhost, returning the counter used to signal rebuilds
+pub fn start_server(dist_path: &str, port: u16) -> io::Result<Arc<AtomicU64>> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|e| {
+        io::Error::new(io::ErrorKind::AddrInUse, format!("Failed to bind 127.0.0.1:{}: {}", port, e))
+    })?;
+    let build_id = Arc::new(AtomicU64::new(0));
+    let server_build_id = Arc::clone(&build_id);
+    let dist = PathBuf::from(dist_path);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let dist = dist.clone();
+                    let build_id = Arc::clone(&server_build_id);
+                    thread::spawn(move || {
+                        if let Err(e) = handle_connection(stream, &dist, &build_id) {
+                            println!("dev server request failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => println!("dev server connection failed: {}", e),
+            }
+        }
+    });
+    println!("serving {} at http://127.0.0.1:{}", dist_path, port);
+    Ok(build_id)
+}
+
+//latest modification time of any file the wasm build depends on
+pub fn latest_source_change(project_path: &str) -> Option<SystemTime> {
+    fn walk(path: &Path, latest: &mut Option<SystemTime>) {
+        let Ok(metadata) = fs::metadata(path) else { return };
+        if metadata.is_dir() {
+            if let Ok(entries) = fs::read_dir(path) {
+                for entry in entries.flatten() {
+                    walk(&entry.path(), latest);
+                }
+            }
+        } else if let Ok(modified) = metadata.modified() {
+            if latest.is_none_or(|l| modified > l) {
+                *latest = Some(modified);
+            }
+        }
+    }
+    let mut latest = None;
+    for watched in ["src", "assets", "Cargo.toml"] {
+        walk(&Path::new(project_path).join(watched), &mut latest);
+    }
+    latest
+}
+
+//build the current project for wasm, serve dist/ locally and rebuild whenever the sources change
+pub fn serve_wasm(session: &mut Session, port: u16) -> io::Result<()> {
+    let project_path = format!(
+        "{}/{}",
+        session.projects_path.as_ref().unwrap(),
+        session.current_project.as_ref().unwrap()
+    );
+    super::core::build_output(session, "wasm".to_string(), false)?;
+    let build_id = start_server(&format!("{}/dist", &project_path), port)?;
+    let mut last_change = latest_source_change(&project_path);
+    loop {
+        thread::sleep(Duration::from_secs(1));
+        let change = latest_source_change(&project_path);
+        if change == last_change {
+            continue;
+        }
+        last_change = change;
+        println!("source change detected, rebuilding wasm...");
+        match super::core::build_output(session, "wasm".to_string(), false) {
+            Ok(()) => {
+                build_id.fetch_add(1, Ordering::SeqCst);
+                println!("rebuild complete, reloading browser");
+            }
+            Err(e) => println!("rebuild failed, still serving the previous build: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ramp::helper::test_dir;
+
+    fn dist() -> PathBuf {
+        let dir = test_dir("web-dist");
+        let dist = dir.join("dist");
+        fs::create_dir_all(dist.join("assets")).unwrap();
+        fs::write(dist.join("index.html"), "<html>").unwrap();
+        fs::write(dist.join("main_bg.wasm"), b"\0asm").unwrap();
+        fs::write(dist.join("assets/my icon.png"), b"png").unwrap();
+        fs::write(dist.join("assets/index.html"), "<html>").unwrap();
+        fs::write(dir.join("secret.txt"), "secret").unwrap();
+        dist
+    }
+
+    #[test]
+    fn resolves_requests_inside_dist() {
+        let dist = dist();
+        assert_eq!(resolve_request(&dist, "/"), Some(dist.join("index.html")));
+        assert_eq!(resolve_request(&dist, ""), Some(dist.join("index.html")));
+        assert_eq!(resolve_request(&dist, "/?build=3#top"), Some(dist.join("index.html")));
+        assert_eq!(resolve_request(&dist, "/main_bg.wasm?v=2"), Some(dist.join("main_bg.wasm")));
+        assert_eq!(resolve_request(&dist, "/assets/"), Some(dist.join("assets/index.html")));
+        assert_eq!(resolve_request(&dist, "/./assets//my%20icon.png"), Some(dist.join("assets/my icon.png")));
+        assert_eq!(resolve_request(&dist, "/missing.js"), None);
+    }
+
+    #[test]
+    fn refuses_traversal() {
+        let dist = dist();
+        for request in [
+            "/../secret.txt",
+            "/assets/../../secret.txt",
+            "/assets/../index.html",
+            "/%2e%2e/secret.txt",
+            "/%2E%2E/secret.txt",
+            "/.%2e/secret.txt",
+            "/assets/..%2f..%2fsecret.txt",
+            "/%2e%2e%5csecret.txt",
+            "/%",
+            "/%zz",
+            "/%ff",
+        ] {
+            assert_eq!(resolve_request(&dist, request), None, "{}", request);
+        }
+    }
+
+    #[test]
+    fn reads_the_locked_wasm_bindgen_version() {
+        let dir = test_dir("web-lock");
+        let project = dir.display().to_string();
+        assert_eq!(locked_wasm_bindgen_version(&project), None);
+        let lock = r#"# This file is automatically @generated by Cargo.
+version = 4
+
+[[package]]
+name = "wasm-bindgen-macro"
+version = "0.2.99"
+
+[[package]]
+name = "wasm-bindgen"
+version = "0.2.100"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = [
+ "cfg-if",
+ "wasm-bindgen-macro",
+]
+"#;
+        fs::write(dir.join("Cargo.lock"), lock).unwrap();
+        assert_eq!(locked_wasm_bindgen_version(&project).as_deref(), Some("0.2.100"));
+        let lock = "version = 4\n\n[[package]]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[[package]]\nname = \"wasm-bindgen-shared\"\nversion = \"0.2.100\"\n";
+        fs::write(dir.join("Cargo.lock"), lock).unwrap();
+        assert_eq!(locked_wasm_bindgen_version(&project), None);
+    }
+}
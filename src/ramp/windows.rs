@@ -0,0 +1,432 @@
+use super::helper::capitalize_first;
+use super::manifest::ProjectManifest;
+use super::session::Session;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+pub const WINDOWS_TARGET: &str = "x86_64-pc-windows-gnu";
+
+//the app icon inside the staged package, update_icons generates it from the project icon
+const WIX_ICON: &str = "assets/resources/icons/windows_icon.ico";
+
+//product details embedded in the exe's VERSIONINFO and the msi
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowsVersionInfo {
+    pub product_name: String,
+    pub file_name: String,
+    pub version: String,
+    pub company: String,
+    pub description: String,
+}
+
+impl WindowsVersionInfo {
+    //company comes from [package.metadata.ramp] or the first author
+    pub fn from_manifest(manifest: &ProjectManifest) -> Self {
+        let product_name = capitalize_first(&manifest.name);
+        //authors are written as "Name <email>"
+        let author = manifest
+            .authors
+            .first()
+            .map(|a| a.split('<').next().unwrap_or("").trim().to_string())
+            .filter(|a| !a.is_empty());
+        WindowsVersionInfo {
+            version: manifest.version.clone(),
+            company: manifest.company.clone().or(author).unwrap_or_else(|| product_name.clone()),
+            description: manifest.description.clone().unwrap_or_else(|| product_name.clone()),
+            product_name,
+            file_name: manifest.name.clone(),
+        }
+    }
+
+    //VERSIONINFO wants four numeric components, pre-release and build metadata are dropped
+    pub fn numeric_version(&self) -> [u16; 4] {
+        let core = self.version.split(['-', '+']).next().unwrap_or("");
+        if core.len() < self.version.len() {
+            println!("warning: version {} is stamped as {} without its pre-release or build suffix", self.version, core);
+        }
+        let mut parts = [0u16; 4];
+        for (i, part) in core.split('.').take(4).enumerate() {
+            parts[i] = part.parse().unwrap_or_else(|_| {
+                println!("warning: version component `{}` of {} is not a number up to 65535, using 0", part, self.version);
+                0
+            });
+        }
+        parts
+    }
+}
+
+fn rc_escape(value: &str) -> String {
+    value.replace('"', "\"\"")
+}
+
+//resource script with the app icon and version information
+pub fn resource_script(info: &WindowsVersionInfo, ico_path: &str) -> String {
+    let [major, minor, patch, build] = info.numeric_version();
+    format!(
+        r#"#include <winver.h>
+
+IDI_ICON1 ICON "{ico}"
+
+VS_VERSION_INFO VERSIONINFO
+FILEVERSION {major},{minor},{patch},{build}
+PRODUCTVERSION {major},{minor},{patch},{build}
+FILEFLAGSMASK VS_FFI_FILEFLAGSMASK
+FILEFLAGS 0
+FILEOS VOS_NT_WINDOWS32
+FILETYPE VFT_APP
+FILESUBTYPE VFT2_UNKNOWN
+BEGIN
+    BLOCK "StringFileInfo"
+    BEGIN
+        BLOCK "040904B0"
+        BEGIN
+            VALUE "CompanyName", "{company}"
+            VALUE "FileDescription", "{description}"
+            VALUE "FileVersion", "{version}"
+            VALUE "InternalName", "{file_name}"
+            VALUE "OriginalFilename", "{file_name}.exe"
+            VALUE "ProductName", "{product}"
+            VALUE "ProductVersion", "{version}"
+        END
+    END
+    BLOCK "VarFileInfo"
+    BEGIN
+        VALUE "Translation", 0x409, 1200
+    END
+END
+"#,
+        ico = ico_path.replace('\\', "/"),
+        company = rc_escape(&info.company),
+        description = rc_escape(&info.description),
+        version = rc_escape(&info.version),
+        file_name = rc_escape(&info.file_name),
+        product = rc_escape(&info.product_name),
+    )
+}
+
+//regenerate app.rc from the project's Cargo.toml
+pub fn write_resource_file(session: &Session) -> io::Result<String> {
+    let project_path = format!(
+        "{}/{}",
+        session.projects_path.as_ref().unwrap(),
+        session.current_project.as_ref().unwrap()
+    );
+    let info = WindowsVersionInfo::from_manifest(&ProjectManifest::load(&project_path)?);
+    let ico_path = format!("{}/assets/resources/icons/windows_icon.ico", &project_path);
+    let rc = format!("{}/app.rc", &project_path);
+    let mut rc_file = File::create(&rc)?;
+    rc_file.write_all(resource_script(&info, &ico_path).as_bytes())?;
+    rc_file.flush()?;
+    println!("created resource file: {}", &rc);
+    Ok(rc)
+}
+
+//a stable guid derived from a seed, so upgrades keep the same UpgradeCode and component ids
+pub fn stable_guid(seed: &str) -> String {
+    let digest = Sha256::digest(seed.as_bytes());
+    let hex: String = digest.iter().take(16).map(|b| format!("{:02X}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+//wix ids only allow letters, digits, underscores and periods
+fn wix_id(prefix: &str, path: &str) -> String {
+    format!("{}_{}", prefix, &stable_guid(path).replace('-', "")[..24])
+}
+
+//list every file under a directory relative to it, sorted for reproducible output
+pub fn relative_files(root: &Path) -> io::Result<Vec<String>> {
+    fn walk(root: &Path, dir: &Path, files: &mut Vec<String>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                walk(root, &path, files)?;
+            } else {
+                files.push(path.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"));
+            }
+        }
+        Ok(())
+    }
+    let mut files = Vec::new();
+    if root.exists() {
+        walk(root, root, &mut files)?;
+    }
+    files.sort();
+    Ok(files)
+}
+
+//wix 3 source installing the staged exe and assets under Program Files with a start menu shortcut
+pub fn wix_source(info: &WindowsVersionInfo, staged_files: &[String]) -> String {
+    let [major, minor, patch, _] = info.numeric_version();
+    //wix wants the icon id's extension to match its source, so the staged .ico is used rather than the exe
+    let icon = staged_files.iter().any(|f| f == WIX_ICON);
+    let upgrade_code = stable_guid(&format!("ramp-upgrade-{}", info.file_name));
+    let exe = format!("{}.exe", info.file_name);
+
+    //build the nested directory tree for the staged files
+    let mut tree = String::new();
+    let mut component_refs = String::new();
+    let mut open_dirs: Vec<String> = Vec::new();
+    for file in staged_files {
+        let parts: Vec<&str> = file.split('/').collect();
+        let dirs = &parts[..parts.len() - 1];
+        let common = open_dirs.iter().zip(dirs.iter()).take_while(|(a, b)| a == b).count();
+        while open_dirs.len() > common {
+            open_dirs.pop();
+            tree.push_str(&format!("{}</Directory>\n", "  ".repeat(open_dirs.len() + 5)));
+        }
+        for dir in &dirs[common..] {
+            let path = format!("{}/{}", open_dirs.join("/"), dir);
+            tree.push_str(&format!(
+                "{}<Directory Id=\"{}\" Name=\"{}\">\n",
+                "  ".repeat(open_dirs.len() + 5),
+                wix_id("dir", &path),
+                xml_escape(dir)
+            ));
+            open_dirs.push(dir.to_string());
+        }
+        let component_id = wix_id("cmp", file);
+        let indent = "  ".repeat(open_dirs.len() + 5);
+        tree.push_str(&format!(
+            "{indent}<Component Id=\"{id}\" Guid=\"{guid}\">\n{indent}  <File Id=\"{file_id}\" Source=\"{source}\" KeyPath=\"yes\"/>\n",
+            indent = indent,
+            id = component_id,
+            guid = stable_guid(&format!("ramp-component-{}-{}", info.file_name, file)),
+            file_id = if *file == exe { "MainExecutable".to_string() } else { wix_id("fil", file) },
+            source = xml_escape(file),
+        ));
+        if *file == exe {
+            tree.push_str(&format!(
+                "{indent}  <Shortcut Id=\"StartMenuShortcut\" Directory=\"ProgramMenuFolder\" Name=\"{name}\" WorkingDirectory=\"INSTALLDIR\"{icon} Advertise=\"yes\"/>\n",
+                indent = indent,
+                name = xml_escape(&info.product_name),
+                icon = if icon { " Icon=\"AppIcon.ico\"" } else { "" },
+            ));
+        }
+        tree.push_str(&format!("{}</Component>\n", indent));
+        component_refs.push_str(&format!("      <ComponentRef Id=\"{}\"/>\n", component_id));
+    }
+    while !open_dirs.is_empty() {
+        open_dirs.pop();
+        tree.push_str(&format!("{}</Directory>\n", "  ".repeat(open_dirs.len() + 5)));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<Wix xmlns="http://schemas.microsoft.com/wix/2006/wi">
+  <Product Id="*" Name="{name}" Language="1033" Version="{major}.{minor}.{patch}" Manufacturer="{company}" UpgradeCode="{upgrade_code}">
+    <Package InstallerVersion="200" Compressed="yes" InstallScope="perMachine" Platform="x64" Description="{description}"/>
+    <MajorUpgrade DowngradeErrorMessage="A newer version of {name} is already installed."/>
+    <Media Id="1" Cabinet="product.cab" EmbedCab="yes"/>
+{icon}    <Directory Id="TARGETDIR" Name="SourceDir">
+      <Directory Id="ProgramMenuFolder"/>
+      <Directory Id="ProgramFiles64Folder">
+        <Directory Id="INSTALLDIR" Name="{name}">
+{tree}        </Directory>
+      </Directory>
+    </Directory>
+    <Feature Id="Main" Level="1">
+{component_refs}    </Feature>
+  </Product>
+</Wix>
+"#,
+        name = xml_escape(&info.product_name),
+        company = xml_escape(&info.company),
+        description = xml_escape(&info.description),
+        icon = if icon {
+            format!("    <Icon Id=\"AppIcon.ico\" SourceFile=\"{}\"/>\n    <Property Id=\"ARPPRODUCTICON\" Value=\"AppIcon.ico\"/>\n", WIX_ICON)
+        } else {
+            String::new()
+        },
+        major = major,
+        minor = minor,
+        patch = patch,
+        upgrade_code = upgrade_code,
+        tree = tree,
+        component_refs = component_refs,
+    )
+}
+
+//zip a staged directory, every entry nested under a top level folder
+pub fn zip_directory(source: &Path, top_level: &str, zip_path: &Path) -> io::Result<()> {
+    let file = File::create(zip_path)?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for relative in relative_files(source)? {
+        zip.start_file(format!("{}/{}", top_level, relative), options)
+            .map_err(|e| io::Error::other(format!("Failed to add {} to zip: {}", relative, e)))?;
+        zip.write_all(&fs::read(source.join(&relative))?)?;
+    }
+    zip.finish().map_err(|e| io::Error::other(format!("Failed to finish zip: {}", e)))?;
+    Ok(())
+}
+
+fn copy_dir(source: &Path, destination: &Path) -> io::Result<()> {
+    for relative in relative_files(source)? {
+        let target = destination.join(&relative);
+        fs::create_dir_all(target.parent().unwrap())?;
+        fs::copy(source.join(&relative), target)?;
+    }
+    Ok(())
+}
+
+//stage the cross compiled exe with its assets, then produce a portable zip and a wix source (and msi when wixl is available)
+pub fn package_windows(session: &mut Session, release: bool) -> io::Result<String> {
+    let project_path = format!(
+        "{}/{}",
+        session.projects_path.as_ref().unwrap(),
+        session.current_project.as_ref().unwrap()
+    );
+    let info = WindowsVersionInfo::from_manifest(&ProjectManifest::load(&project_path)?);
+    let profile = if release { "release" } else { "debug" };
+    let exe_path = format!("{}/target/{}/{}/{}.exe", &project_path, WINDOWS_TARGET, profile, info.file_name);
+    if !Path::new(&exe_path).exists() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("windows executable not found: {}", exe_path)));
+    }
+    let package_path = format!("{}/packages/windows", &project_path);
+    let stage_path = format!("{}/{}", &package_path, info.product_name);
+    if Path::new(&package_path).exists() {
+        fs::remove_dir_all(&package_path)?;
+    }
+    fs::create_dir_all(&stage_path)?;
+    fs::copy(&exe_path, format!("{}/{}.exe", &stage_path, info.file_name))?;
+    copy_dir(&Path::new(&project_path).join("assets"), &Path::new(&stage_path).join("assets"))?;
+
+    //portable zip
+    let zip_path = format!("{}/{}-{}-windows-x64.zip", &package_path, info.file_name, info.version);
+    zip_directory(Path::new(&stage_path), &info.product_name, Path::new(&zip_path))?;
+    println!("created portable zip: {}", &zip_path);
+
+    //wix source, sources are resolved relative to the staged directory
+    let staged = relative_files(Path::new(&stage_path))?;
+    let wxs_path = format!("{}/{}.wxs", &package_path, info.file_name);
+    fs::write(&wxs_path, wix_source(&info, &staged))?;
+    println!("created wix source: {}", &wxs_path);
+
+    let msi_path = format!("{}/{}-{}-x64.msi", &package_path, info.file_name, info.version);
+    match Command::new("wixl")
+        .args(["--arch", "x64", "--output", &msi_path, &wxs_path])
+        .current_dir(&stage_path)
+        .output()
+    {
+        Ok(output) if output.status.success() => println!("created msi: {}", &msi_path),
+        Ok(output) => println!("WARNING: wixl failed to build the msi: {}", String::from_utf8_lossy(&output.stderr)),
+        Err(_) => println!("wixl not found, install msitools to build the msi from {}", &wxs_path),
+    }
+    Ok(package_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(version: &str) -> WindowsVersionInfo {
+        WindowsVersionInfo {
+            product_name: "Demo & Co".to_string(),
+            file_name: "demo".to_string(),
+            version: version.to_string(),
+            company: "Example".to_string(),
+            description: "A demo".to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_numeric_versions() {
+        assert_eq!(info("1.2.3").numeric_version(), [1, 2, 3, 0]);
+        assert_eq!(info("1.2.3.4.5").numeric_version(), [1, 2, 3, 4]);
+        assert_eq!(info("2.0").numeric_version(), [2, 0, 0, 0]);
+        assert_eq!(info("1.2.3-beta.1").numeric_version(), [1, 2, 3, 0]);
+        assert_eq!(info("1.2.3+build.7").numeric_version(), [1, 2, 3, 0]);
+        //components that aren't numbers or don't fit a u16 fall back to 0
+        assert_eq!(info("1.x.3").numeric_version(), [1, 0, 3, 0]);
+        assert_eq!(info("70000.1.0").numeric_version(), [0, 1, 0, 0]);
+        assert_eq!(info("").numeric_version(), [0, 0, 0, 0]);
+    }
+
+    //the installed tree as `name/` for directories, `end` when one closes and the source of each file, indented by depth
+    fn install_tree(wxs: &str) -> Vec<String> {
+        let attr = |line: &str, key: &str| line.split(&format!("{}=\"", key)).nth(1).and_then(|v| v.split('"').next()).map(|v| v.to_string());
+        let start = wxs.find("<Directory Id=\"INSTALLDIR\"").unwrap();
+        let end = wxs.find("<Feature").unwrap();
+        wxs[start..end]
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let depth = ((line.len() - line.trim_start().len()) / 2).checked_sub(5)?;
+                let trim = line.trim();
+                let (depth, entry) = if trim.starts_with("<Directory Id=\"dir_") {
+                    (depth, format!("{}/", attr(trim, "Name")?))
+                } else if trim.starts_with("<File ") {
+                    //files sit inside their component
+                    (depth - 1, attr(trim, "Source")?)
+                } else if trim == "</Directory>" {
+                    (depth, "end".to_string())
+                } else {
+                    return None;
+                };
+                Some(format!("{}{}", "  ".repeat(depth), entry))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nests_staged_files_in_directories() {
+        let staged: Vec<String> = [
+            "assets/fonts/a.ttf",
+            "assets/fonts/b.ttf",
+            "assets/logo.png",
+            "assets/resources/icons/windows_icon.ico",
+            "data/levels/1.json",
+            "demo.exe",
+        ]
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+        let wxs = wix_source(&info("1.2.3"), &staged);
+        assert_eq!(
+            install_tree(&wxs),
+            [
+                "assets/",
+                "  fonts/",
+                "    assets/fonts/a.ttf",
+                "    assets/fonts/b.ttf",
+                "  end",
+                "  assets/logo.png",
+                "  resources/",
+                "    icons/",
+                "      assets/resources/icons/windows_icon.ico",
+                "    end",
+                "  end",
+                "end",
+                "data/",
+                "  levels/",
+                "    data/levels/1.json",
+                "  end",
+                "end",
+                "demo.exe",
+            ]
+        );
+        //every opened directory is closed, ProgramMenuFolder closes itself
+        assert_eq!(wxs.matches("<Directory ").count(), wxs.matches("</Directory>").count() + 1);
+        assert_eq!(wxs.matches("<Component ").count(), 6);
+        assert_eq!(wxs.matches("<ComponentRef ").count(), 6);
+        assert!(wxs.contains("Name=\"Demo &amp; Co\" Language=\"1033\" Version=\"1.2.3\""));
+        assert!(wxs.contains("<File Id=\"MainExecutable\" Source=\"demo.exe\""));
+        assert!(wxs.contains("Icon=\"AppIcon.ico\" Advertise=\"yes\""));
+        //the same names give the same ids, so upgrades replace components in place
+        assert_eq!(wxs, wix_source(&info("1.2.3"), &staged));
+        assert_ne!(wix_id("dir", "/assets/fonts"), wix_id("dir", "/data/fonts"));
+    }
+}
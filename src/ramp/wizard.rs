@@ -0,0 +1,764 @@
+use super::core::{apply_bundle_id, install_provisioning_profile};
+use super::install::{create_signing_request, import_signing_certificate, keychain_unlocked};
+use super::session::{Session, SigningIdentity};
+use super::watcher::{ArtifactPattern, ArtifactWatcher, CancelToken};
+use super::appstore::{AppStoreClient, ProfileType};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+//time between completion checks while a step waits on a download or the keychain
+pub const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+//values a wizard keeps between steps and runs, saved as wizard.<kind>.<key> in the ramp config
+const VALUE_KEYS: [&str; 14] = [
+    "project", "udid", "target_os", "release", "bundle_id", "email", "full_name", "org", "artifact", "api", "identity", "step_started",
+    "certificate_id", "certificate",
+];
+
+//the flows that need the user to act in the apple developer portal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WizardKind {
+    Keychain,
+    BundleId,
+    Provision,
+}
+
+impl WizardKind {
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "keychain" => Some(WizardKind::Keychain),
+            "bundle_id" => Some(WizardKind::BundleId),
+            "provision" => Some(WizardKind::Provision),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WizardKind::Keychain => "keychain",
+            WizardKind::BundleId => "bundle_id",
+            WizardKind::Provision => "provision",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            WizardKind::Keychain => "Signing Certificate",
+            WizardKind::BundleId => "App Bundle ID",
+            WizardKind::Provision => "Device Provisioning",
+        }
+    }
+}
+
+//how a step is known to be done
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Completion {
+    //the user says they followed the instructions
+    Confirmed,
+    //every required input has a value
+    Inputs,
+    //the login keychain is unlocked
+    KeychainUnlocked,
+    //a file matching the pattern is downloaded, or imported by hand through the artifact input
+    Download(&'static str),
+    //ramp does the step itself through the App Store Connect API
+    Automatic,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepInput {
+    pub key: &'static str,
+    pub label: &'static str,
+    //used when the input is left empty
+    pub default: Option<String>,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WizardStep {
+    pub id: &'static str,
+    pub title: &'static str,
+    pub instructions: Vec<String>,
+    pub inputs: Vec<StepInput>,
+    //opened in safari when the step starts
+    pub url: Option<&'static str>,
+    pub completion: Completion,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepStatus {
+    Complete,
+    //what the step is still waiting for
+    Waiting(String),
+}
+
+//a resumable run through one of the flows, the same state drives the terminal and the wizard page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wizard {
+    pub kind: WizardKind,
+    pub step: usize,
+    pub values: BTreeMap<String, String>,
+}
+
+fn config_key(kind: WizardKind, key: &str) -> String {
+    format!("wizard.{}.{}", kind.as_str(), key)
+}
+
+impl Wizard {
+    pub fn new(kind: WizardKind) -> Self {
+        let mut wizard = Wizard { kind, step: 0, values: BTreeMap::new() };
+        wizard.mark_step_started();
+        wizard
+    }
+
+    //remember when the current step began, downloads from before then belong to something else
+    fn mark_step_started(&mut self) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        self.values.insert("step_started".to_string(), now.to_string());
+    }
+
+    pub fn step_started(&self) -> Option<SystemTime> {
+        self.value("step_started").and_then(|s| s.parse().ok()).map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    pub fn with_value(mut self, key: &str, value: &str) -> Self {
+        self.set_input(key, value);
+        self
+    }
+
+    //let the App Store Connect API do the portal steps when a key is set up
+    pub fn with_api_if_configured(mut self, session: &Session) -> io::Result<Self> {
+        if AppStoreClient::from_config(session)?.is_some() {
+            self.set_input("api", "true");
+        }
+        Ok(self)
+    }
+
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|v| v.as_str())
+    }
+
+    //store an input, empty values are removed so defaults apply
+    pub fn set_input(&mut self, key: &str, value: &str) {
+        let value = value.trim();
+        if value.is_empty() {
+            self.values.remove(key);
+        } else {
+            self.values.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    //the unfinished wizard of a kind saved in the ramp config, if there is one
+    pub fn load(session: &Session, kind: WizardKind) -> io::Result<Option<Self>> {
+        let Some(step) = session.get_config(&config_key(kind, "step"))?.filter(|s| !s.is_empty()) else {
+            return Ok(None);
+        };
+        let step = step.parse().map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("invalid {} wizard step: {}", kind.as_str(), step))
+        })?;
+        let mut values = BTreeMap::new();
+        for key in VALUE_KEYS {
+            if let Some(value) = session.get_config(&config_key(kind, key))?.filter(|v| !v.is_empty()) {
+                values.insert(key.to_string(), value);
+            }
+        }
+        Ok(Some(Wizard { kind, step, values }))
+    }
+
+    pub fn save(&self, session: &Session) -> io::Result<()> {
+        session.set_config(&config_key(self.kind, "step"), &self.step.to_string())?;
+        for key in VALUE_KEYS {
+            session.set_config(&config_key(self.kind, key), self.value(key).unwrap_or(""))?;
+        }
+        Ok(())
+    }
+
+    pub fn clear(session: &Session, kind: WizardKind) -> io::Result<()> {
+        session.set_config(&config_key(kind, "step"), "")?;
+        for key in VALUE_KEYS {
+            session.set_config(&config_key(kind, key), "")?;
+        }
+        Ok(())
+    }
+
+    fn default_bundle_id(&self) -> String {
+        format!("com.ramp.{}", self.value("project").unwrap_or("ramp"))
+    }
+
+    pub fn steps(&self) -> Vec<WizardStep> {
+        let steps = self.portal_steps();
+        //with an App Store Connect API key the developer portal steps need no clicking
+        match self.value("api") {
+            Some("true") => steps.into_iter().map(|step| if step.url.is_some() { self.api_step(step) } else { step }).collect(),
+            _ => steps,
+        }
+    }
+
+    //the certificate a keychain wizard creates, development unless set
+    pub fn identity(&self) -> SigningIdentity {
+        self.value("identity").and_then(SigningIdentity::parse).unwrap_or(SigningIdentity::Development)
+    }
+
+    fn profile_name(&self) -> &'static str {
+        if self.value("release") == Some("true") { "Ramp Release" } else { "Ramp Debug" }
+    }
+
+    fn api_step(&self, step: WizardStep) -> WizardStep {
+        let instruction = match step.id {
+            "certificate" => format!("Ramp uploads ramp.csr to App Store Connect and installs the {} certificate it returns.", self.identity().common_name()),
+            "register" => format!("Ramp registers {} with App Store Connect.", self.value("bundle_id").unwrap_or_default()),
+            "device" => format!("Ramp registers the device {} with App Store Connect.", self.value("udid").unwrap_or_default()),
+            _ => format!("Ramp creates the \"{}\" provisioning profile with App Store Connect and installs it on the device.", self.profile_name()),
+        };
+        WizardStep { instructions: vec![instruction], inputs: Vec::new(), url: None, completion: Completion::Automatic, ..step }
+    }
+
+    fn portal_steps(&self) -> Vec<WizardStep> {
+        let udid = self.value("udid").unwrap_or("unknown");
+        let bundle_id = self.value("bundle_id").map(|b| b.to_string()).unwrap_or_else(|| self.default_bundle_id());
+        let identity = self.identity();
+        match self.kind {
+            WizardKind::Keychain => vec![
+                WizardStep {
+                    id: "unlock",
+                    title: "Unlock your keychain",
+                    instructions: vec!["Ramp keeps your signing key in the login keychain, unlock it in Keychain Access if it is locked.".to_string()],
+                    inputs: Vec::new(),
+                    url: None,
+                    completion: Completion::KeychainUnlocked,
+                },
+                WizardStep {
+                    id: "details",
+                    title: "Enter your developer details",
+                    instructions: vec!["These are used for the certificate signing request and must match your apple developer account.".to_string()],
+                    inputs: vec![
+                        StepInput { key: "email", label: "Apple developer email", default: None, required: true },
+                        StepInput { key: "full_name", label: "Full legal name", default: None, required: true },
+                        StepInput { key: "org", label: "Organization name (blank if individual)", default: None, required: false },
+                    ],
+                    url: None,
+                    completion: Completion::Inputs,
+                },
+                WizardStep {
+                    id: "certificate",
+                    title: match identity {
+                        SigningIdentity::Development => "Create a development certificate",
+                        SigningIdentity::Distribution => "Create a distribution certificate",
+                        SigningIdentity::DeveloperId => "Create a Developer ID certificate",
+                    },
+                    instructions: [
+                        vec![
+                            "Now go to the safari window and login to your developer account.".to_string(),
+                            "Once you have logged in, click the + button next to \"Certificates\".".to_string(),
+                            format!("Select the \"{}\" checkbox and then hit next.", identity.common_name()),
+                        ],
+                        match identity {
+                            SigningIdentity::DeveloperId => vec![
+                                "Only the account holder can create Developer ID certificates.".to_string(),
+                                "Choose the \"G2 Sub-CA\" profile type if asked.".to_string(),
+                            ],
+                            _ => Vec::new(),
+                        },
+                        vec![
+                            "Next drag and drop the \"ramp.csr\" file from the file finder window into Safari. Click the next button.".to_string(),
+                            "Click the download button.".to_string(),
+                        ],
+                    ].concat(),
+                    inputs: vec![artifact_input()],
+                    url: Some("https://developer.apple.com/account/resources/certificates/list"),
+                    completion: Completion::Download(identity.certificate_file()),
+                },
+            ],
+            WizardKind::BundleId => vec![
+                WizardStep {
+                    id: "bundle_id",
+                    title: "Choose your app bundle ID",
+                    instructions: vec![format!("Enter your app bundle ID, leave it empty for the recommended default \"{}\".", self.default_bundle_id())],
+                    inputs: vec![StepInput { key: "bundle_id", label: "App bundle ID", default: Some(self.default_bundle_id()), required: true }],
+                    url: None,
+                    completion: Completion::Inputs,
+                },
+                WizardStep {
+                    id: "register",
+                    title: "Register the bundle ID",
+                    instructions: vec![
+                        "Now go to the safari window and login to your developer account.".to_string(),
+                        "Once you have logged in, click the + button next to \"Identifiers\".".to_string(),
+                        "Select \"App IDs\" and click the continue button.".to_string(),
+                        "Select \"App\" for the type and click the continue button.".to_string(),
+                        format!("Copy and paste your app bundle ID into the \"Bundle ID\" box: {}", bundle_id),
+                        "Give your app whatever description you like and then press the continue button.".to_string(),
+                        "Click the Register button.".to_string(),
+                    ],
+                    inputs: Vec::new(),
+                    url: Some("https://developer.apple.com/account/resources/identifiers/list/bundleId"),
+                    completion: Completion::Confirmed,
+                },
+            ],
+            WizardKind::Provision => {
+                let target_os = self.value("target_os").unwrap_or("ios");
+                let profile_name = self.profile_name();
+                //releases are ad-hoc profiles signed by the distribution certificate
+                let (profile_kind, certificate_kind) = match self.value("release") {
+                    Some("true") => ("Ad Hoc", "(Distribution)"),
+                    _ => ("App Development", "(Development)"),
+                };
+                vec![
+                    WizardStep {
+                        id: "device",
+                        title: "Register your device",
+                        instructions: vec![
+                            "Now go to the safari window and login to your developer account.".to_string(),
+                            "Once you have logged in, click the + button next to \"Devices\".".to_string(),
+                            format!("Give the device whatever name you would like and copy and paste {} into \"Device ID (UUID)\".", udid),
+                            "Click the continue button.".to_string(),
+                        ],
+                        inputs: Vec::new(),
+                        url: Some("https://developer.apple.com/account/resources/devices/list"),
+                        completion: Completion::Confirmed,
+                    },
+                    WizardStep {
+                        id: "profile",
+                        title: "Create a provisioning profile",
+                        instructions: vec![
+                            "Now go to the safari window and login to your developer account if necessary.".to_string(),
+                            "Once you have logged in, click the + button next to \"Profiles\".".to_string(),
+                            format!("Check your development or distribution options: we recommend choosing \"{} {}\", then press continue.", target_os, profile_kind),
+                            format!("Select your App ID from the dropdown list: {}", bundle_id),
+                            "Select whether you would like offline support (choose No if you're not sure). Then press continue.".to_string(),
+                            format!("Select your appropriate \"{}\" certificate. Then press continue.", certificate_kind),
+                            format!("Select the device profile corresponding to UUID: {}", udid),
+                            "Click the continue button.".to_string(),
+                            format!("Enter your provisioning profile name. Recommended: \"{}\"", profile_name),
+                            "Click \"Generate\". Then click \"Download\".".to_string(),
+                        ],
+                        inputs: vec![artifact_input()],
+                        url: Some("https://developer.apple.com/account/resources/profiles/list"),
+                        completion: Completion::Download("*.mobileprovision"),
+                    },
+                ]
+            }
+        }
+    }
+
+    pub fn current(&self) -> Option<WizardStep> {
+        self.steps().into_iter().nth(self.step)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.current().is_none()
+    }
+
+    //open the developer portal page and folders the current step refers to
+    pub fn start(&self, session: &mut Session) -> io::Result<()> {
+        let Some(step) = self.current() else { return Ok(()) };
+        if let Some(url) = step.url {
+            let output = Command::new("open")
+                .args(["-a", "safari", url])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .output()
+                .map_err(|e| io::Error::other(format!("Failed to open Apple developer portal: {}", e)))?;
+            if !output.status.success() {
+                return Err(io::Error::other("Failed to open Apple developer portal"));
+            }
+        }
+        if (self.kind, step.id) == (WizardKind::Keychain, "certificate") && step.url.is_some() {
+            //open the file explorer to show the CSR
+            let keystore_path = session.get_path("keystore_path")?;
+            let output = Command::new("open").arg(&keystore_path).output()?;
+            if !output.status.success() {
+                return Err(io::Error::other(format!("Failed to open CSR in file explorer at path {}", &keystore_path)));
+            }
+        }
+        Ok(())
+    }
+
+    //whether the current step is done, without moving on
+    pub fn check(&self, session: &Session) -> io::Result<StepStatus> {
+        let Some(step) = self.current() else { return Ok(StepStatus::Complete) };
+        Ok(match step.completion {
+            Completion::Confirmed | Completion::Automatic => StepStatus::Complete,
+            Completion::Inputs => match step.inputs.iter().find(|i| i.required && self.value(i.key).is_none()) {
+                Some(missing) => StepStatus::Waiting(format!("{} is required", missing.label)),
+                None => StepStatus::Complete,
+            },
+            Completion::KeychainUnlocked => match keychain_unlocked(session)? {
+                true => StepStatus::Complete,
+                false => StepStatus::Waiting("waiting for the login keychain to be unlocked".to_string()),
+            },
+            Completion::Download(pattern) => match self.value("artifact") {
+                Some(path) if !Path::new(path).is_file() => StepStatus::Waiting(format!("{} does not exist", path)),
+                Some(_) => StepStatus::Complete,
+                None => {
+                    let watcher = self.download_watcher(session, pattern)?;
+                    match watcher.scan()? {
+                        Some(_) => StepStatus::Complete,
+                        None => StepStatus::Waiting(format!("waiting for {} in {}", watcher.pattern, watcher.dir.display())),
+                    }
+                }
+            },
+        })
+    }
+
+    //check the current step and, once it is done, run its action and move to the next step
+    //progress is saved after every call so the wizard resumes where it was left, and cleared when finished
+    pub fn advance(&mut self, session: &mut Session) -> io::Result<StepStatus> {
+        let Some(step) = self.current() else { return Ok(StepStatus::Complete) };
+        for input in &step.inputs {
+            if let (None, Some(default)) = (self.value(input.key), &input.default) {
+                self.values.insert(input.key.to_string(), default.clone());
+            }
+        }
+        self.save(session)?;
+        let status = self.check(session)?;
+        if status == StepStatus::Complete {
+            self.complete(session, &step)?;
+            //a file imported for this step isn't the next step's artifact
+            self.values.remove("artifact");
+            self.step += 1;
+            self.mark_step_started();
+            match self.is_finished() {
+                true => Wizard::clear(session, self.kind)?,
+                false => self.save(session)?,
+            }
+        }
+        Ok(status)
+    }
+
+    fn required(&self, key: &str) -> io::Result<String> {
+        self.value(key)
+            .map(|v| v.to_string())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} not set for the {} wizard", key, self.kind.as_str())))
+    }
+
+    //the watcher for the current step's download, None for steps that don't wait on one
+    pub fn watcher(&self, session: &Session) -> io::Result<Option<ArtifactWatcher>> {
+        match self.current().map(|step| step.completion) {
+            Some(Completion::Download(pattern)) => Ok(Some(self.download_watcher(session, pattern)?)),
+            _ => Ok(None),
+        }
+    }
+
+    //wizards saved before step start times were recorded accept any matching download
+    fn download_watcher(&self, session: &Session, pattern: &str) -> io::Result<ArtifactWatcher> {
+        let watcher = ArtifactWatcher::downloads(session, ArtifactPattern::parse(pattern))?;
+        Ok(match self.step_started() {
+            Some(since) => watcher.newer_than(since),
+            None => watcher,
+        })
+    }
+
+    //the file imported by hand, or else the newest download matching the step's pattern
+    //downloads are removed once the step's action has used them, imported files are left alone
+    fn artifact(&self, session: &Session) -> io::Result<(PathBuf, bool)> {
+        if let Some(path) = self.value("artifact") {
+            return Ok((PathBuf::from(path), false));
+        }
+        let watcher = self.watcher(session)?.ok_or_else(|| io::Error::other("the step doesn't download a file"))?;
+        let path = watcher.scan()?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no {} found in {}", watcher.pattern, watcher.dir.display())))?;
+        Ok((path, true))
+    }
+
+    fn complete(&mut self, session: &mut Session, step: &WizardStep) -> io::Result<()> {
+        if step.completion == Completion::Automatic {
+            return self.complete_with_api(session, step);
+        }
+        match (self.kind, step.id) {
+            (WizardKind::Keychain, "details") => {
+                create_signing_request(session, &self.required("email")?, &self.required("full_name")?, self.value("org").unwrap_or(""))
+            }
+            (WizardKind::Keychain, "certificate") => {
+                let (certificate, downloaded) = self.artifact(session)?;
+                import_signing_certificate(session, &certificate, self.identity())?;
+                if downloaded {
+                    fs::remove_file(&certificate)?;
+                }
+                Ok(())
+            }
+            (WizardKind::BundleId, "register") => {
+                let bundle_id = self.required("bundle_id")?;
+                if bundle_id != self.default_bundle_id() {
+                    apply_bundle_id(session, &bundle_id)?;
+                }
+                Ok(())
+            }
+            (WizardKind::Provision, "profile") => {
+                let (profile, downloaded) = self.artifact(session)?;
+                install_provisioning_profile(
+                    session,
+                    &profile,
+                    &self.required("udid")?,
+                    &self.required("target_os")?,
+                    self.value("release") == Some("true"),
+                )?;
+                if downloaded {
+                    fs::remove_file(&profile)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    //do a developer portal step through the App Store Connect API
+    fn complete_with_api(&mut self, session: &mut Session, step: &WizardStep) -> io::Result<()> {
+        let client = AppStoreClient::from_config(session)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no App Store Connect API key configured"))?;
+        let project = self.value("project").unwrap_or("ramp").to_string();
+        let target_os = self.value("target_os").unwrap_or("ios").to_string();
+        match (self.kind, step.id) {
+            (WizardKind::Keychain, "certificate") => {
+                let identity = self.identity();
+                //apple limits how many certificates an account can have, so one created by a failed attempt is reused
+                let content = match self.value("certificate") {
+                    Some(saved) => STANDARD.decode(saved).map_err(|e| {
+                        io::Error::new(io::ErrorKind::InvalidData, format!("invalid saved certificate: {}", e))
+                    })?,
+                    None => {
+                        let keystore_path = session.get_path("keystore_path")?;
+                        let csr = fs::read_to_string(format!("{}/ramp.csr", keystore_path))?;
+                        let certificate = client.create_certificate(&csr, identity.certificate_type())?;
+                        self.set_input("certificate_id", &certificate.id);
+                        self.set_input("certificate", &STANDARD.encode(&certificate.content));
+                        self.save(session)?;
+                        certificate.content
+                    }
+                };
+                let download = std::env::temp_dir().join(identity.certificate_file());
+                fs::write(&download, &content)?;
+                let result = import_signing_certificate(session, &download, identity);
+                fs::remove_file(&download)?;
+                result?;
+                self.values.remove("certificate_id");
+                self.values.remove("certificate");
+                Ok(())
+            }
+            (WizardKind::BundleId, "register") => {
+                let bundle_id = self.required("bundle_id")?;
+                client.ensure_bundle_id(&bundle_id, &project, &target_os)?;
+                if bundle_id != self.default_bundle_id() {
+                    apply_bundle_id(session, &bundle_id)?;
+                }
+                Ok(())
+            }
+            (WizardKind::Provision, "device") => {
+                let udid = self.required("udid")?;
+                client.ensure_device(&device_name(&project, &udid), &udid, &target_os)?;
+                Ok(())
+            }
+            (WizardKind::Provision, "profile") => {
+                let bundle_id = client.ensure_bundle_id(&self.required("bundle_id")?, &project, &target_os)?;
+                let release = self.value("release") == Some("true");
+                let profile_type = if release { ProfileType::AdHoc } else { ProfileType::Development };
+                let name = format!("{} {}", self.profile_name(), bundle_id.identifier);
+                let profile = client.regenerate_profile(&name, profile_type, &bundle_id, &target_os)?;
+                let download = std::env::temp_dir().join(format!("{}.mobileprovision", profile.uuid));
+                fs::write(&download, &profile.content)?;
+                let result = install_provisioning_profile(session, &download, &self.required("udid")?, &target_os, release);
+                fs::remove_file(&download)?;
+                result
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+//the name a device is registered under, the udid is whatever the user typed so it's cut by characters
+fn device_name(project: &str, udid: &str) -> String {
+    format!("{} {}", project, udid.chars().take(8).collect::<String>())
+}
+
+//the manual fallback for steps waiting on a download
+fn artifact_input() -> StepInput {
+    StepInput { key: "artifact", label: "Downloaded file path (optional, found automatically)", default: None, required: false }
+}
+
+//what running a wizard led to, pending wizards are saved for their page in the gui
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WizardOutcome {
+    Finished,
+    Pending,
+}
+
+//drive the wizard in the terminal when ramp has one, otherwise save it for the wizard page instead of blocking on stdin
+pub fn run_wizard(session: &mut Session, wizard: Wizard) -> io::Result<WizardOutcome> {
+    let mut wizard = wizard.with_api_if_configured(session)?;
+    wizard.save(session)?;
+    if !io::stdin().is_terminal() {
+        println!("{} needs your input, continue it on its wizard page in ramp", wizard.kind.title());
+        return Ok(WizardOutcome::Pending);
+    }
+    drive_in_terminal(session, &mut wizard)?;
+    Ok(WizardOutcome::Finished)
+}
+
+fn prompt(message: &str) -> io::Result<String> {
+    println!("{}", message);
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .map_err(|e| io::Error::other(format!("Failed to receive user input: {}", e)))?;
+    Ok(input.trim().to_string())
+}
+
+//print each step's instructions, read its inputs and wait until it is complete
+pub fn drive_in_terminal(session: &mut Session, wizard: &mut Wizard) -> io::Result<()> {
+    while let Some(step) = wizard.current() {
+        wizard.start(session)?;
+        println!("**********************************");
+        println!("{}", step.title);
+        for (index, line) in step.instructions.iter().enumerate() {
+            println!("{}. {}", index + 1, line);
+        }
+        for input in &step.inputs {
+            let message = match &input.default {
+                Some(default) => format!("{} (press enter for {}):", input.label, default),
+                None => format!("{}:", input.label),
+            };
+            let value = prompt(&message)?;
+            wizard.set_input(input.key, &value);
+        }
+        if step.completion == Completion::Confirmed {
+            prompt("Press enter here in the terminal to continue")?;
+        }
+        let mut waiting = None;
+        loop {
+            //wait on the download unless a file was given, asking for one if it doesn't show up in time
+            if let (Some(watcher), None) = (wizard.watcher(session)?, wizard.value("artifact")) {
+                println!("waiting for {} in {}", watcher.pattern, watcher.dir.display());
+                if let Err(e) = watcher.wait(&CancelToken::default()) {
+                    if e.kind() != io::ErrorKind::TimedOut {
+                        return Err(e);
+                    }
+                    let path = prompt(&format!("{}. Enter the file's path, or press enter to keep waiting:", e))?;
+                    wizard.set_input("artifact", &path);
+                }
+            }
+            match wizard.advance(session)? {
+                StepStatus::Complete => break,
+                //missing inputs are asked for again
+                StepStatus::Waiting(reason) if step.completion == Completion::Inputs => {
+                    println!("{}", reason);
+                    break;
+                }
+                StepStatus::Waiting(reason) => {
+                    //a path that doesn't exist is dropped so the download is waited on again
+                    wizard.set_input("artifact", "");
+                    if waiting.as_ref() != Some(&reason) {
+                        println!("{}", reason);
+                        waiting = Some(reason);
+                    }
+                    sleep(POLL_INTERVAL);
+                }
+            }
+        }
+    }
+    println!("{} finished", wizard.kind.title());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ramp::helper::test_session;
+
+    #[test]
+    fn resumes_from_the_saved_config() {
+        let session = test_session("wizard-resume");
+        assert_eq!(Wizard::load(&session, WizardKind::Provision).unwrap(), None);
+        let mut wizard = Wizard::new(WizardKind::Provision).with_value("udid", "00008030-001A2B3C4D5E802E").with_value("release", "true");
+        wizard.step = 1;
+        wizard.save(&session).unwrap();
+
+        let loaded = Wizard::load(&session, WizardKind::Provision).unwrap().unwrap();
+        assert_eq!(loaded, wizard);
+        assert_eq!(loaded.step_started(), wizard.step_started());
+        assert!(loaded.step_started().unwrap() <= SystemTime::now());
+        assert_eq!(loaded.current().unwrap().id, "profile");
+        //each kind is saved separately
+        assert_eq!(Wizard::load(&session, WizardKind::Keychain).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_a_corrupt_saved_step() {
+        let session = test_session("wizard-corrupt");
+        session.set_config("wizard.keychain.step", "two").unwrap();
+        assert_eq!(Wizard::load(&session, WizardKind::Keychain).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn applies_input_defaults_and_clears_when_finished() {
+        let mut session = test_session("wizard-defaults");
+        let mut wizard = Wizard::new(WizardKind::BundleId).with_value("project", "demo");
+        assert_eq!(wizard.advance(&mut session).unwrap(), StepStatus::Complete);
+        assert_eq!(wizard.value("bundle_id"), Some("com.ramp.demo"));
+        assert_eq!(wizard.current().unwrap().id, "register");
+        assert_eq!(Wizard::load(&session, WizardKind::BundleId).unwrap().unwrap().value("bundle_id"), Some("com.ramp.demo"));
+
+        //registering the default bundle id leaves the project alone
+        assert_eq!(wizard.advance(&mut session).unwrap(), StepStatus::Complete);
+        assert!(wizard.is_finished());
+        assert_eq!(Wizard::load(&session, WizardKind::BundleId).unwrap(), None);
+        assert_eq!(session.get_config("wizard.bundle_id.project").unwrap().as_deref(), Some(""));
+        assert_eq!(wizard.advance(&mut session).unwrap(), StepStatus::Complete);
+    }
+
+    #[test]
+    fn waits_for_required_inputs() {
+        let session = test_session("wizard-inputs");
+        let mut wizard = Wizard::new(WizardKind::Keychain);
+        wizard.step = 1;
+        assert_eq!(wizard.check(&session).unwrap(), StepStatus::Waiting("Apple developer email is required".to_string()));
+        wizard.set_input("email", "dev@example.com");
+        //whitespace doesn't count as a value
+        wizard.set_input("full_name", "   ");
+        assert_eq!(wizard.check(&session).unwrap(), StepStatus::Waiting("Full legal name is required".to_string()));
+        wizard.set_input("full_name", "Ramp Developer");
+        //the organization is optional
+        assert_eq!(wizard.check(&session).unwrap(), StepStatus::Complete);
+    }
+
+    #[test]
+    fn drops_the_artifact_between_steps() {
+        let mut session = test_session("wizard-artifact");
+        let mut wizard = Wizard::new(WizardKind::Provision).with_value("udid", "00008030-001A2B3C4D5E802E");
+        wizard.set_input("artifact", "/downloads/old.mobileprovision");
+        wizard.values.insert("step_started".to_string(), "1".to_string());
+        assert_eq!(wizard.advance(&mut session).unwrap(), StepStatus::Complete);
+        assert_eq!(wizard.current().unwrap().id, "profile");
+        assert_eq!(wizard.value("artifact"), None);
+        assert!(wizard.step_started().unwrap() > UNIX_EPOCH + Duration::from_secs(1));
+        let saved = Wizard::load(&session, WizardKind::Provision).unwrap().unwrap();
+        assert_eq!(saved.value("artifact"), None);
+        //a path that doesn't exist keeps the step waiting
+        wizard.set_input("artifact", "/downloads/missing.mobileprovision");
+        assert_eq!(
+            wizard.check(&session).unwrap(),
+            StepStatus::Waiting("/downloads/missing.mobileprovision does not exist".to_string())
+        );
+    }
+
+    #[test]
+    fn hands_portal_steps_to_the_api() {
+        let wizard = Wizard::new(WizardKind::Provision).with_value("udid", "0000-é").with_value("api", "true");
+        let steps = wizard.steps();
+        assert!(steps.iter().all(|step| step.completion == Completion::Automatic && step.url.is_none()));
+        assert_eq!(steps[0].instructions, ["Ramp registers the device 0000-é with App Store Connect."]);
+        assert_eq!(device_name("demo", "00008030-001A2B3C4D5E802E"), "demo 00008030");
+        assert_eq!(device_name("demo", "ééééééééé"), "demo éééééééé");
+        assert_eq!(device_name("demo", "0000-é"), "demo 0000-é");
+        let wizard = Wizard::new(WizardKind::Keychain).with_value("api", "true").with_value("identity", "developer_id");
+        let completions: Vec<Completion> = wizard.steps().iter().map(|s| s.completion).collect();
+        assert_eq!(completions, [Completion::KeychainUnlocked, Completion::Inputs, Completion::Automatic]);
+    }
+}
@@ -0,0 +1,8 @@
+use std::env;
+
+fn main() {
+    println!("cargo:rerun-if-changed=proto/");
+    if env::var("PROFILE").as_deref() == Ok("release") {
+        println!("cargo:rustc-cfg=shipping");
+    }
+}
@@ -0,0 +1,11 @@
+
+        use std::io;
+
+        fn main() {
+            if std::env::var("CARGO_CFG_TARGET_OS").unwrap() == "windows" && std::path::Path::new("/Users/dev/ramp/demo/assets/resources/icons/windows_icon.ico").exists() {
+                embed_resource::compile("app.rc", embed_resource::NONE)
+                .manifest_optional();
+            }
+    }
+    
+        
\ No newline at end of file